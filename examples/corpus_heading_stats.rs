@@ -12,7 +12,7 @@ use std::io::{BufWriter, Error};
 use std::time::Instant;
 
 use libxml::xpath::Context;
-use llamapun::dnm::SpecialTagsOption;
+use llamapun::dnm::DNMParameters;
 use llamapun::parallel_data::*;
 use llamapun::util::data_helpers;
 use llamapun::util::data_helpers::LexicalOptions;
@@ -43,26 +43,7 @@ pub fn main() -> Result<(), Error> {
   let mut corpus = Corpus::new(corpus_path);
   // we are interested in canonical heading statistics, so discard a lot of the counting machinery
   // and special content
-  corpus
-    .dnm_parameters
-    .special_tag_name_options
-    .insert("math".to_string(), SpecialTagsOption::Skip);
-  corpus
-    .dnm_parameters
-    .special_tag_class_options
-    .insert("ltx_equation".to_string(), SpecialTagsOption::Skip);
-  corpus
-    .dnm_parameters
-    .special_tag_class_options
-    .insert("ltx_equationgroup".to_string(), SpecialTagsOption::Skip);
-  corpus
-    .dnm_parameters
-    .special_tag_name_options
-    .insert("cite".to_string(), SpecialTagsOption::Skip);
-  corpus
-    .dnm_parameters
-    .special_tag_class_options
-    .insert("ltx_ref".to_string(), SpecialTagsOption::Skip);
+  corpus.dnm_parameters = DNMParameters::headings();
 
   let mut catalog = corpus.catalog_with_parallel_walk(|document| {
     let mut heading_count: u64 = 0;
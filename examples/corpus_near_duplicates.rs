@@ -0,0 +1,38 @@
+/// Reports clusters of near-duplicate paragraphs and documents in an unpacked corpus of HTML files
+/// $ cargo run --release --example corpus_near_duplicates /path/to/corpus/ [threshold]
+use std::env;
+use std::time::Instant;
+
+use llamapun::dedup::NearDuplicateOptions;
+use llamapun::parallel_data::Corpus;
+
+pub fn main() {
+  let start = Instant::now();
+  // Read input arguments
+  let mut input_args = env::args();
+  let _ = input_args.next(); // skip process name
+  let corpus_path = match input_args.next() {
+    Some(path) => path,
+    None => "tests/resources/".to_string(),
+  };
+  let mut options = NearDuplicateOptions::default();
+  if let Some(threshold) = input_args.next().and_then(|arg| arg.parse().ok()) {
+    options.threshold = threshold;
+  }
+
+  let corpus = Corpus::new(corpus_path);
+  let duplicates = corpus.near_duplicates(&options);
+
+  for cluster in &duplicates.documents {
+    println!("-- near-duplicate documents: {}", cluster.join(", "));
+  }
+  for cluster in &duplicates.paragraphs {
+    println!("-- near-duplicate paragraphs: {}", cluster.join(", "));
+  }
+  println!(
+    "-- found {} document and {} paragraph clusters in {:?}s",
+    duplicates.documents.len(),
+    duplicates.paragraphs.len(),
+    start.elapsed().as_secs()
+  );
+}
@@ -0,0 +1,39 @@
+/// Trains a Punkt sentence splitter on the paragraphs of an unpacked corpus of HTML files, and
+/// saves the model as JSON
+/// $ cargo run --release --example corpus_punkt_training /path/to/corpus/ punkt_model.json
+use std::env;
+use std::time::Instant;
+
+use llamapun::parallel_data::Corpus;
+use llamapun::punkt::PunktTrainer;
+
+pub fn main() {
+  let start = Instant::now();
+  // Read input arguments
+  let mut input_args = env::args();
+  let _ = input_args.next(); // skip process name
+  let corpus_path = match input_args.next() {
+    Some(path) => path,
+    None => "tests/resources/".to_string(),
+  };
+  let model_path = match input_args.next() {
+    Some(path) => path,
+    None => "punkt_model.json".to_string(),
+  };
+
+  let mut trainer = PunktTrainer::new();
+  trainer.train_corpus(&Corpus::new(corpus_path));
+  let model = trainer.finalize();
+  if let Err(e) = model.save(&model_path) {
+    eprintln!("-- failed to save the model to {}: {}", model_path, e);
+    return;
+  }
+  println!(
+    "-- learned {} abbreviations, {} collocations and {} sentence starters from {} tokens in {:?}s",
+    model.abbreviations.len(),
+    model.collocations.len(),
+    model.sentence_starters.len(),
+    trainer.token_count(),
+    start.elapsed().as_secs()
+  );
+}
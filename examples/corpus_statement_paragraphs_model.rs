@@ -21,7 +21,7 @@ use crypto::sha2::Sha256;
 use libxml::xpath::Context;
 use llamapun::ams;
 use llamapun::ams::{AmsEnv, StructuralEnv};
-use llamapun::dnm::SpecialTagsOption;
+use llamapun::dnm::DNMParameters;
 use llamapun::parallel_data::*;
 use llamapun::util::data_helpers;
 use llamapun::util::data_helpers::LexicalOptions;
@@ -60,18 +60,7 @@ pub fn main() -> Result<(), Error> {
   let mut corpus = Corpus::new(corpus_path);
   if discard_math {
     println!("-- will discard math.");
-    corpus
-      .dnm_parameters
-      .special_tag_name_options
-      .insert("math".to_string(), SpecialTagsOption::Skip);
-    corpus
-      .dnm_parameters
-      .special_tag_class_options
-      .insert("ltx_equation".to_string(), SpecialTagsOption::Skip);
-    corpus
-      .dnm_parameters
-      .special_tag_class_options
-      .insert("ltx_equationgroup".to_string(), SpecialTagsOption::Skip);
+    corpus.dnm_parameters = DNMParameters::discard_math();
   } else {
     println!("-- will lexematize math.")
   }
@@ -231,12 +220,9 @@ fn extract_document_statements(
     let mut word_count = 0;
     let mut invalid_paragraph = false;
     let mut paragraph_buffer = String::new();
-    'words: for word in paragraph.word_and_punct_iter() {
-      if word.range.is_empty() {
-        continue 'words;
-      }
-      let word_string = match data_helpers::ams_normalize_word_range(
-        &word.range,
+    'words: for token in paragraph.token_iter() {
+      let word_string = match data_helpers::ams_normalize_token(
+        &token,
         &mut context,
         LexicalOptions {
           discard_math,
@@ -0,0 +1,47 @@
+//! Reports the memory retained by the plaintext of a DNM and its compact `ByteOffsets` index,
+//! compared with the same byte offsets held in a `Vec<usize>`, the representation before the
+//! compact index. Only the sizes retained by the built DNM are reported, not the peak allocation
+//! during its construction.
+//!
+//! Usage: cargo run --release --example dnm_memory [document.html]
+extern crate libxml;
+extern crate llamapun;
+
+use std::env;
+use std::mem;
+use std::time::Instant;
+
+use libxml::parser::Parser;
+use llamapun::dnm::{DNMParameters, DNM};
+
+fn main() {
+  let path = env::args()
+    .nth(1)
+    .unwrap_or_else(|| "tests/resources/0903.1000.html".to_string());
+  let parser = Parser::default_html();
+  let doc = parser.parse_file(&path).unwrap();
+
+  let start = Instant::now();
+  let dnm = DNM::new(
+    doc.get_root_readonly().unwrap(),
+    DNMParameters::llamapun_normalization(),
+  );
+  let duration = start.elapsed();
+
+  let char_count = dnm.byte_offsets.len() - 1;
+  let plaintext = dnm.plaintext.capacity();
+  let compact = dnm.byte_offsets.heap_size();
+  // a Vec<usize> takes a full usize per offset, not counting any spare capacity
+  let vec_offsets = dnm.byte_offsets.len() * mem::size_of::<usize>();
+
+  println!("{path}: {char_count} plaintext chars, DNM built in {duration:?}");
+  println!("  plaintext:             {plaintext:>10} bytes");
+  println!("  ByteOffsets index:     {compact:>10} bytes");
+  println!("  as a Vec<usize>:       {vec_offsets:>10} bytes");
+  println!(
+    "  retained, plaintext and offsets: {} bytes, previously at least {} bytes ({:.1}x)",
+    plaintext + compact,
+    plaintext + vec_offsets,
+    (plaintext + vec_offsets) as f64 / (plaintext + compact) as f64
+  );
+}
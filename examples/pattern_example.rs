@@ -80,6 +80,7 @@ fn print_marker(marker: &MarkerEnum, alt_dnm: &DNM, xpath_context: &Context) {
         "<h5>TextMarker</h5> \"{}\" \n <br /><br /><p>{}</p>",
         &get_pattern_marker_string(&text_marker.marker),
         DNMRange::deserialize(&text_marker.range.serialize(), alt_dnm, xpath_context)
+          .unwrap()
           .get_plaintext()
       );
     },
@@ -174,6 +175,7 @@ pub fn main() {
       println!(
         "<h4>Sentence</h4>\n<p>{}</p>",
         DNMRange::deserialize(&sentence_2.range.serialize(), &alt_dnm, &xpath_context)
+          .unwrap()
           .get_plaintext()
       );
       for m in &matches {
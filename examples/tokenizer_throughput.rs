@@ -0,0 +1,115 @@
+//! Compares the throughput of sentence and word tokenization, between the lazy iterators
+//! (`Tokenizer::sentence_iter` and `Tokenizer::word_iter`, yielding ranges on demand) and the
+//! collecting `Tokenizer::sentences` and `Tokenizer::words` (materializing a `Vec` of ranges per
+//! paragraph and per sentence), over the paragraphs of the test resources.
+//!
+//! Both are measured with the current tokenizer. The collecting methods keep their signatures
+//! from before the lazy iterators, so the same `collected` measurement, run at an earlier
+//! revision, gives the numbers of the previous tokenizer.
+//!
+//! Usage: cargo run --release --example tokenizer_throughput [rounds] [directory]
+extern crate libxml;
+extern crate llamapun;
+
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use libxml::parser::Parser;
+use libxml::tree::Document;
+use libxml::xpath::Context;
+use llamapun::dnm::{DNMParameters, DNM};
+use llamapun::tokenizer::Tokenizer;
+
+/// Runs `tokenize` over all paragraphs `rounds` times, returning the elapsed time and the
+/// sentence and word counts of one round
+fn measure<F>(dnms: &[DNM], rounds: usize, tokenize: F) -> (Duration, usize, usize)
+where F: Fn(&DNM) -> (usize, usize) {
+  let start = Instant::now();
+  let (mut sentences, mut words) = (0, 0);
+  for _ in 0..rounds {
+    sentences = 0;
+    words = 0;
+    for dnm in dnms {
+      let (dnm_sentences, dnm_words) = tokenize(dnm);
+      sentences += dnm_sentences;
+      words += dnm_words;
+    }
+  }
+  (start.elapsed(), sentences, words)
+}
+
+fn report(name: &str, rounds: usize, (duration, sentences, words): (Duration, usize, usize)) {
+  let seconds = duration.as_secs_f64();
+  println!(
+    "  {name:<9}: {duration:?} for {rounds} rounds, {:.0} sentences/s, {:.0} words/s",
+    (rounds * sentences) as f64 / seconds,
+    (rounds * words) as f64 / seconds
+  );
+}
+
+fn main() {
+  let mut args = env::args().skip(1);
+  let rounds: usize = args.next().map_or(20, |rounds| rounds.parse().unwrap());
+  let directory = args.next().unwrap_or_else(|| "tests/resources".to_string());
+
+  let mut documents: Vec<Document> = Vec::new();
+  for entry in fs::read_dir(&directory).unwrap() {
+    let path = entry.unwrap().path();
+    let parser = match path.extension().and_then(|extension| extension.to_str()) {
+      Some("html") => Parser::default_html(),
+      Some("xhtml") => Parser::default(),
+      _ => continue,
+    };
+    documents.push(parser.parse_file(path.to_str().unwrap()).unwrap());
+  }
+  let mut dnms = Vec::new();
+  for document in &documents {
+    let context = Context::new(document).unwrap();
+    let paragraphs = context
+      .evaluate("//*[contains(@class,'ltx_para')]")
+      .unwrap()
+      .get_readonly_nodes_as_vec();
+    for paragraph in paragraphs {
+      dnms.push(DNM::new(paragraph, DNMParameters::llamapun_normalization()));
+    }
+  }
+  let chars: usize = dnms.iter().map(|dnm| dnm.plaintext.chars().count()).sum();
+  println!(
+    "{directory}: {} documents, {} paragraphs, {chars} plaintext chars",
+    documents.len(),
+    dnms.len()
+  );
+
+  let tokenizer = Tokenizer::default();
+  let collected = measure(&dnms, rounds, |dnm| {
+    let sentences = tokenizer.sentences(dnm);
+    let words = sentences
+      .iter()
+      .map(|sentence| tokenizer.words(sentence).len())
+      .sum();
+    (sentences.len(), words)
+  });
+  let lazy = measure(&dnms, rounds, |dnm| {
+    let (mut sentences, mut words) = (0, 0);
+    for sentence in tokenizer.sentence_iter(dnm) {
+      sentences += 1;
+      words += tokenizer.word_iter(&sentence).count();
+    }
+    (sentences, words)
+  });
+  println!(
+    "  collected: {} sentences, {} words per round",
+    collected.1, collected.2
+  );
+  println!(
+    "  lazy:      {} sentences, {} words per round",
+    lazy.1, lazy.2
+  );
+  report("collected", rounds, collected);
+  report("lazy", rounds, lazy);
+  println!(
+    "  speedup: {:.2}x",
+    collected.0.as_secs_f64() / lazy.0.as_secs_f64()
+  );
+}
@@ -5,7 +5,7 @@ use walkdir::IntoIter as WalkDirIterator;
 use walkdir::WalkDir;
 
 use crate::dnm::{DNMParameters, DNMRange, DNM};
-use crate::tokenizer::Tokenizer;
+use crate::tokenizer::{RangeIter, Segmenter, Tokenizer};
 
 use libxml::parser::{Parser, XmlParseError};
 use libxml::readonly::RoNode;
@@ -25,8 +25,8 @@ pub struct Corpus {
   pub xml_parser: Parser,
   /// document HTML5 parser
   pub html_parser: Parser,
-  /// `DNM`-aware sentence and word tokenizer
-  pub tokenizer: Tokenizer,
+  /// `DNM`-aware sentence and word segmenter, the rule-based `Tokenizer` by default
+  pub tokenizer: Box<dyn Segmenter>,
   /// `Senna` object for shallow language analysis
   pub senna: RefCell<Senna>,
   /// `Senna` parsing options
@@ -77,7 +77,9 @@ pub struct Paragraph<'p> {
 /// An iterator over the sentences of a document/paragraph
 pub struct SentenceIterator<'iter> {
   /// The walker over the sentence ranges
-  walker: IntoIter<DNMRange<'iter>>,
+  walker: RangeIter<'iter>,
+  /// The segmenter chosen for the DNM of the sentences
+  segmenter: &'iter dyn Segmenter,
   // pub paragraph : &'iter Paragraph<'iter>
   /// A reference to the document we are working on
   pub document: &'iter Document<'iter>,
@@ -90,6 +92,8 @@ pub struct Sentence<'s> {
   // pub paragraph : &'s Paragraph<'s>
   /// The document containing this sentence
   pub document: &'s Document<'s>,
+  /// The segmenter chosen for the DNM of the sentence, see `Segmenter::for_dnm`
+  pub segmenter: &'s dyn Segmenter,
   /// If it exists, also the senna version of the sentence,
   /// which can contain additional information such as
   /// POS tags and syntactic parse trees
@@ -100,7 +104,7 @@ pub struct Sentence<'s> {
 /// by their ranges
 pub struct SimpleWordIterator<'iter> {
   /// The walker over the words
-  walker: IntoIter<DNMRange<'iter>>,
+  walker: RangeIter<'iter>,
   /// The sentence containing the words
   pub sentence: &'iter Sentence<'iter>,
 }
@@ -165,7 +169,7 @@ impl Default for Corpus {
     Corpus {
       extension: None,
       path: ".".to_string(),
-      tokenizer: Tokenizer::default(),
+      tokenizer: Box::new(Tokenizer::default()),
       xml_parser: Parser::default(),
       html_parser: Parser::default_html(),
       senna: RefCell::new(Senna::new(SENNA_PATH.to_owned())),
@@ -184,6 +188,11 @@ impl Corpus {
     }
   }
 
+  /// Use an alternative sentence and word segmenter for the document iterators
+  pub fn set_segmenter<S: Segmenter + 'static>(&mut self, segmenter: S) {
+    self.tokenizer = Box::new(segmenter);
+  }
+
   /// Get an iterator over the documents
   pub fn iter(&mut self) -> DocumentIterator {
     DocumentIterator {
@@ -264,10 +273,11 @@ impl<'d> Document<'d> {
         self.dnm = Some(DNM::new(root, self.corpus.dnm_parameters.clone()));
       }
     }
-    let tokenizer = &self.corpus.tokenizer;
-    let sentences = tokenizer.sentences(self.dnm.as_ref().unwrap());
+    let dnm = self.dnm.as_ref().unwrap();
+    let segmenter = self.corpus.tokenizer.for_dnm(dnm);
     SentenceIterator {
-      walker: sentences.into_iter(),
+      walker: segmenter.sentence_iter(dnm),
+      segmenter,
       document: self,
     }
   }
@@ -293,10 +303,10 @@ impl<'iter> Iterator for ParagraphIterator<'iter> {
 impl<'p> Paragraph<'p> {
   /// Get an iterator over the sentences in this paragraph
   pub fn iter(&'p mut self) -> SentenceIterator<'p> {
-    let tokenizer = &self.document.corpus.tokenizer;
-    let sentences = tokenizer.sentences(&self.dnm);
+    let segmenter = self.document.corpus.tokenizer.for_dnm(&self.dnm);
     SentenceIterator {
-      walker: sentences.into_iter(),
+      walker: segmenter.sentence_iter(&self.dnm),
+      segmenter,
       document: self.document,
     }
   }
@@ -314,6 +324,7 @@ impl<'iter> Iterator for SentenceIterator<'iter> {
           let sentence = Sentence {
             range,
             document: self.document,
+            segmenter: self.segmenter,
             senna_sentence: None,
           };
           Some(sentence)
@@ -326,10 +337,8 @@ impl<'iter> Iterator for SentenceIterator<'iter> {
 impl<'s> Sentence<'s> {
   /// Get an iterator over the words (using rudimentary heuristics)
   pub fn simple_iter(&'s mut self) -> SimpleWordIterator<'s> {
-    let tokenizer = &self.document.corpus.tokenizer;
-    let words = tokenizer.words(&self.range);
     SimpleWordIterator {
-      walker: words.into_iter(),
+      walker: self.segmenter.word_iter(&self.range),
       sentence: self,
     }
   }
@@ -0,0 +1,399 @@
+//! Near-duplicate detection via locality-sensitive hashing
+//!
+//! Texts (e.g. the plaintext or canonical form of a paragraph DNM) are reduced to `MinHash` or
+//! `SimHash` fingerprints of their word shingles. An `LshIndex` buckets fingerprints by bands, so
+//! that near-duplicates can be found without comparing all pairs of texts.
+use crate::dnm::DNM;
+use crate::parallel_data::Corpus;
+use std::collections::{HashMap, HashSet};
+use xxhash_rust::xxh3::{xxh3_64, xxh3_64_with_seed};
+
+/// A fingerprint that can be indexed by an `LshIndex`
+pub trait Fingerprint {
+  /// The bucket keys of the fingerprint, one per band
+  fn band_keys(&self, bands: usize) -> Vec<u64>;
+  /// The estimated similarity to another fingerprint, between 0 and 1
+  fn similarity(&self, other: &Self) -> f64;
+}
+
+/// Helper function: the hashes of the lowercased word `size`-shingles of a text. Texts shorter
+/// than a shingle are a single shingle.
+fn shingle_hashes(text: &str, size: usize) -> HashSet<u64> {
+  let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+  let size = size.max(1);
+  if words.len() < size {
+    if words.is_empty() {
+      return HashSet::new();
+    }
+    return std::iter::once(xxh3_64(words.join(" ").as_bytes())).collect();
+  }
+  words
+    .windows(size)
+    .map(|shingle| xxh3_64(shingle.join(" ").as_bytes()))
+    .collect()
+}
+
+/// Computes `MinHash` fingerprints, estimating the Jaccard similarity of shingle sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinHasher {
+  /// number of hash functions, i.e. the length of the fingerprints
+  pub num_hashes: usize,
+  /// number of words per shingle
+  pub shingle_size: usize,
+}
+
+impl Default for MinHasher {
+  fn default() -> MinHasher {
+    MinHasher {
+      num_hashes: 128,
+      shingle_size: 3,
+    }
+  }
+}
+
+/// A `MinHash` fingerprint: the minimal hash of the shingles, per hash function
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MinHash(pub Vec<u64>);
+
+impl MinHasher {
+  /// Create a hasher with `num_hashes` hash functions over `shingle_size`-word shingles
+  pub fn new(num_hashes: usize, shingle_size: usize) -> Self {
+    MinHasher {
+      num_hashes,
+      shingle_size,
+    }
+  }
+
+  /// The fingerprint of a text
+  pub fn fingerprint(&self, text: &str) -> MinHash {
+    let shingles = shingle_hashes(text, self.shingle_size);
+    MinHash(
+      (0..self.num_hashes as u64)
+        .map(|seed| {
+          shingles
+            .iter()
+            .map(|shingle| xxh3_64_with_seed(&shingle.to_le_bytes(), seed))
+            .min()
+            .unwrap_or(u64::MAX)
+        })
+        .collect(),
+    )
+  }
+
+  /// The fingerprint of the plaintext of a DNM
+  pub fn fingerprint_dnm(&self, dnm: &DNM) -> MinHash { self.fingerprint(&dnm.plaintext) }
+}
+
+impl MinHash {
+  /// The fingerprint of the union of two shingle sets, e.g. of a document from its paragraphs
+  pub fn union(&self, other: &MinHash) -> MinHash {
+    MinHash(
+      self
+        .0
+        .iter()
+        .zip(other.0.iter())
+        .map(|(a, b)| *a.min(b))
+        .collect(),
+    )
+  }
+}
+
+impl Fingerprint for MinHash {
+  fn band_keys(&self, bands: usize) -> Vec<u64> {
+    let rows = (self.0.len() / bands.max(1)).max(1);
+    self
+      .0
+      .chunks(rows)
+      .take(bands)
+      .map(|band| {
+        let bytes: Vec<u8> = band.iter().flat_map(|value| value.to_le_bytes()).collect();
+        xxh3_64(&bytes)
+      })
+      .collect()
+  }
+
+  fn similarity(&self, other: &MinHash) -> f64 {
+    if self.0.is_empty() {
+      return 0.0;
+    }
+    let equal = self
+      .0
+      .iter()
+      .zip(other.0.iter())
+      .filter(|(a, b)| a == b && **a != u64::MAX)
+      .count();
+    equal as f64 / self.0.len() as f64
+  }
+}
+
+/// A 64 bit `SimHash` fingerprint, estimating the cosine similarity of shingle sets by the
+/// Hamming distance of the fingerprints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimHash(pub u64);
+
+impl SimHash {
+  /// The fingerprint of a text, over its `shingle_size`-word shingles
+  pub fn new(text: &str, shingle_size: usize) -> SimHash {
+    let mut weights = [0i64; 64];
+    for shingle in shingle_hashes(text, shingle_size) {
+      for (bit, weight) in weights.iter_mut().enumerate() {
+        if shingle & (1 << bit) != 0 {
+          *weight += 1;
+        } else {
+          *weight -= 1;
+        }
+      }
+    }
+    SimHash(
+      weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |fingerprint, (bit, _)| fingerprint | (1 << bit)),
+    )
+  }
+
+  /// Number of differing bits
+  pub fn hamming_distance(&self, other: &SimHash) -> u32 { (self.0 ^ other.0).count_ones() }
+}
+
+impl Fingerprint for SimHash {
+  /// Fingerprints within a Hamming distance below `bands` share at least one band
+  fn band_keys(&self, bands: usize) -> Vec<u64> {
+    let bands = bands.clamp(1, 64);
+    let width = 64 / bands;
+    (0..bands)
+      .map(|band| {
+        let bits = if band + 1 == bands {
+          64 - band * width
+        } else {
+          width
+        };
+        let mask = if bits == 64 {
+          u64::MAX
+        } else {
+          (1 << bits) - 1
+        };
+        (self.0 >> (band * width)) & mask
+      })
+      .collect()
+  }
+
+  fn similarity(&self, other: &SimHash) -> f64 {
+    1.0 - f64::from(self.hamming_distance(other)) / 64.0
+  }
+}
+
+/// A locality-sensitive hashing index over keyed fingerprints
+#[derive(Debug, Clone)]
+pub struct LshIndex<F: Fingerprint> {
+  /// number of bands
+  bands: usize,
+  /// per band, the entries with a given band key
+  buckets: Vec<HashMap<u64, Vec<usize>>>,
+  /// the keys and fingerprints of the entries
+  entries: Vec<(String, F)>,
+}
+
+impl<F: Fingerprint> LshIndex<F> {
+  /// Create an empty index with `bands` bands. For `MinHash` fingerprints of `n` values in `b`
+  /// bands, pairs with Jaccard similarity `s` become candidates with probability
+  /// `1 - (1 - s^(n/b))^b`.
+  pub fn new(bands: usize) -> Self {
+    let bands = bands.max(1);
+    LshIndex {
+      bands,
+      buckets: vec![HashMap::new(); bands],
+      entries: Vec::new(),
+    }
+  }
+
+  /// Number of entries in the index
+  pub fn len(&self) -> usize { self.entries.len() }
+
+  /// Checks whether the index is empty
+  pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+  /// Add a fingerprint under `key`, returning its entry index
+  pub fn insert(&mut self, key: String, fingerprint: F) -> usize {
+    let index = self.entries.len();
+    for (band, band_key) in fingerprint.band_keys(self.bands).into_iter().enumerate() {
+      self.buckets[band].entry(band_key).or_default().push(index);
+    }
+    self.entries.push((key, fingerprint));
+    index
+  }
+
+  /// The key and fingerprint of an entry
+  pub fn get(&self, index: usize) -> Option<(&str, &F)> {
+    self
+      .entries
+      .get(index)
+      .map(|(key, fingerprint)| (key.as_str(), fingerprint))
+  }
+
+  /// Entries sharing at least one band with `fingerprint`
+  pub fn candidates(&self, fingerprint: &F) -> Vec<usize> {
+    let mut candidates: Vec<usize> = fingerprint
+      .band_keys(self.bands)
+      .into_iter()
+      .enumerate()
+      .filter_map(|(band, band_key)| self.buckets[band].get(&band_key))
+      .flatten()
+      .copied()
+      .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+    candidates
+  }
+
+  /// Keys of the entries with a similarity of at least `threshold` to `fingerprint`, with their
+  /// similarity, most similar first
+  pub fn query(&self, fingerprint: &F, threshold: f64) -> Vec<(&str, f64)> {
+    let mut matches: Vec<(&str, f64)> = self
+      .candidates(fingerprint)
+      .into_iter()
+      .filter_map(|index| {
+        let (key, candidate) = &self.entries[index];
+        let similarity = fingerprint.similarity(candidate);
+        if similarity >= threshold {
+          Some((key.as_str(), similarity))
+        } else {
+          None
+        }
+      })
+      .collect();
+    matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    matches
+  }
+
+  /// Groups of at least two keys, transitively connected by a similarity of at least `threshold`
+  pub fn clusters(&self, threshold: f64) -> Vec<Vec<String>> {
+    let mut parents: Vec<usize> = (0..self.entries.len()).collect();
+    fn find(parents: &mut [usize], index: usize) -> usize {
+      let mut root = index;
+      while parents[root] != root {
+        root = parents[root];
+      }
+      parents[index] = root;
+      root
+    }
+    for bucket in self.buckets.iter().flat_map(HashMap::values) {
+      for (position, &a) in bucket.iter().enumerate() {
+        for &b in &bucket[position + 1..] {
+          let (root_a, root_b) = (find(&mut parents, a), find(&mut parents, b));
+          if root_a != root_b && self.entries[a].1.similarity(&self.entries[b].1) >= threshold {
+            parents[root_b] = root_a;
+          }
+        }
+      }
+    }
+    let mut groups: HashMap<usize, Vec<String>> = HashMap::new();
+    for (index, (key, _)) in self.entries.iter().enumerate() {
+      let root = find(&mut parents, index);
+      groups.entry(root).or_default().push(key.clone());
+    }
+    let mut clusters: Vec<Vec<String>> = groups
+      .into_values()
+      .filter(|group| group.len() > 1)
+      .map(|mut group| {
+        group.sort();
+        group
+      })
+      .collect();
+    clusters.sort();
+    clusters
+  }
+}
+
+/// The text a paragraph is fingerprinted from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FingerprintSource {
+  /// The DNM plaintext (default)
+  #[default]
+  Plaintext,
+  /// The basic canonical form (see `DNM::to_c14n_basic`), which also reflects markup
+  C14n,
+}
+
+/// Options for `Corpus::near_duplicates`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NearDuplicateOptions {
+  /// The `MinHash` parameters
+  pub hasher: MinHasher,
+  /// Number of LSH bands
+  pub bands: usize,
+  /// Minimal estimated Jaccard similarity of near-duplicates
+  pub threshold: f64,
+  /// Paragraphs with fewer words are ignored
+  pub min_words: usize,
+  /// The text paragraphs are fingerprinted from
+  pub source: FingerprintSource,
+}
+
+impl Default for NearDuplicateOptions {
+  fn default() -> NearDuplicateOptions {
+    NearDuplicateOptions {
+      hasher: MinHasher::default(),
+      bands: 32,
+      threshold: 0.8,
+      min_words: 10,
+      source: FingerprintSource::Plaintext,
+    }
+  }
+}
+
+/// Near-duplicate clusters found in a corpus
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NearDuplicates {
+  /// Clusters of paragraphs, keyed as `path#id` (or `path#index` for paragraphs without id)
+  pub paragraphs: Vec<Vec<String>>,
+  /// Clusters of documents, keyed by path
+  pub documents: Vec<Vec<String>>,
+}
+
+impl Corpus {
+  /// Walk the corpus in parallel, fingerprinting every paragraph and document, and report the
+  /// clusters of near-duplicate paragraphs and documents
+  pub fn near_duplicates(&self, options: &NearDuplicateOptions) -> NearDuplicates {
+    let fingerprints = self.map_with_parallel_walk(|document| {
+      let mut paragraphs = Vec::new();
+      let mut document_fingerprint: Option<MinHash> = None;
+      for (index, paragraph) in document.paragraph_iter().enumerate() {
+        let dnm = &paragraph.dnm;
+        if dnm.plaintext.split_whitespace().count() < options.min_words {
+          continue;
+        }
+        let fingerprint = match options.source {
+          FingerprintSource::Plaintext => options.hasher.fingerprint_dnm(dnm),
+          FingerprintSource::C14n => options.hasher.fingerprint(&dnm.to_c14n_basic()),
+        };
+        document_fingerprint = Some(match document_fingerprint {
+          Some(previous) => previous.union(&fingerprint),
+          None => fingerprint.clone(),
+        });
+        let key = match dnm.root_node.get_property("id") {
+          Some(id) => format!("{}#{}", document.path, id),
+          None => format!("{}#{}", document.path, index),
+        };
+        paragraphs.push((key, fingerprint));
+      }
+      (document.path.clone(), document_fingerprint, paragraphs)
+    });
+
+    let mut paragraph_index = LshIndex::new(options.bands);
+    let mut document_index = LshIndex::new(options.bands);
+    for (path, document_fingerprint, paragraphs) in fingerprints {
+      if let Some(fingerprint) = document_fingerprint {
+        document_index.insert(path, fingerprint);
+      }
+      for (key, fingerprint) in paragraphs {
+        paragraph_index.insert(key, fingerprint);
+      }
+    }
+    NearDuplicates {
+      paragraphs: paragraph_index.clusters(options.threshold),
+      documents: document_index.clusters(options.threshold),
+    }
+  }
+}
@@ -0,0 +1,305 @@
+//! The `dnm::annotation` submodule provides a standoff annotation store for a `DNM`.
+//!
+//! Annotations are spans of DNM plaintext offsets (as in `DNMRange`), carrying typed attributes.
+//! They are grouped in named layers, e.g. `"sentences"`, `"words"` or `"patterns"`, and each
+//! layer is kept sorted by offsets, so that span queries don't need to scan the whole layer.
+use crate::dnm::{DNMRange, DNM};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// Name of the layer populated by `Tokenizer::annotate_sentences`
+pub const SENTENCE_LAYER: &str = "sentences";
+/// Name of the layer populated by `Tokenizer::annotate_words`
+pub const WORD_LAYER: &str = "words";
+/// Name of the layer populated by `patterns::annotate_matches`
+pub const PATTERN_LAYER: &str = "patterns";
+
+/// The value of an annotation attribute
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AnnotationValue {
+  /// a flag
+  Bool(bool),
+  /// an integer, e.g. an index into another layer
+  Int(i64),
+  /// a real number, e.g. a confidence score
+  Float(f64),
+  /// a string, e.g. a POS tag or entity type
+  Text(String),
+}
+
+impl From<bool> for AnnotationValue {
+  fn from(value: bool) -> Self { AnnotationValue::Bool(value) }
+}
+impl From<i64> for AnnotationValue {
+  fn from(value: i64) -> Self { AnnotationValue::Int(value) }
+}
+impl From<usize> for AnnotationValue {
+  fn from(value: usize) -> Self { AnnotationValue::Int(value as i64) }
+}
+impl From<f64> for AnnotationValue {
+  fn from(value: f64) -> Self { AnnotationValue::Float(value) }
+}
+impl From<&str> for AnnotationValue {
+  fn from(value: &str) -> Self { AnnotationValue::Text(value.to_string()) }
+}
+impl From<String> for AnnotationValue {
+  fn from(value: String) -> Self { AnnotationValue::Text(value) }
+}
+
+impl AnnotationValue {
+  /// The value as a boolean, if it is one
+  pub fn as_bool(&self) -> Option<bool> {
+    match *self {
+      AnnotationValue::Bool(value) => Some(value),
+      _ => None,
+    }
+  }
+  /// The value as an integer, if it is one
+  pub fn as_int(&self) -> Option<i64> {
+    match *self {
+      AnnotationValue::Int(value) => Some(value),
+      _ => None,
+    }
+  }
+  /// The value as a real number, if it is a number
+  pub fn as_float(&self) -> Option<f64> {
+    match *self {
+      AnnotationValue::Float(value) => Some(value),
+      AnnotationValue::Int(value) => Some(value as f64),
+      _ => None,
+    }
+  }
+  /// The value as a string, if it is one
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      AnnotationValue::Text(value) => Some(value),
+      _ => None,
+    }
+  }
+}
+
+/// A single standoff annotation: a span of DNM offsets, with attributes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+  /// Offset of the beginning of the span
+  pub start: usize,
+  /// Offset of the end of the span
+  pub end: usize,
+  /// The attributes of the annotation
+  #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+  pub attributes: HashMap<String, AnnotationValue>,
+}
+
+impl Annotation {
+  /// Create an annotation without attributes
+  pub fn new(start: usize, end: usize) -> Self {
+    Annotation {
+      start,
+      end,
+      attributes: HashMap::new(),
+    }
+  }
+
+  /// Create an annotation spanning a `DNMRange`
+  pub fn from_range(range: &DNMRange) -> Self { Annotation::new(range.start, range.end) }
+
+  /// Builder-style: add an attribute
+  pub fn with_attribute<V: Into<AnnotationValue>>(mut self, key: &str, value: V) -> Self {
+    self.attributes.insert(key.to_string(), value.into());
+    self
+  }
+
+  /// Get an attribute
+  pub fn get(&self, key: &str) -> Option<&AnnotationValue> { self.attributes.get(key) }
+
+  /// The annotated span as a `DNMRange` of `dnm`
+  pub fn range<'dnm>(&self, dnm: &'dnm DNM) -> DNMRange<'dnm> {
+    DNMRange {
+      start: self.start,
+      end: self.end,
+      dnm,
+    }
+  }
+
+  /// Checks whether the span overlaps with `start..end`
+  pub fn overlaps(&self, start: usize, end: usize) -> bool { self.start < end && start < self.end }
+
+  /// Checks whether the span contains `start..end`
+  pub fn contains(&self, start: usize, end: usize) -> bool {
+    self.start <= start && end <= self.end
+  }
+}
+
+/// A named layer of annotations, sorted by their spans
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationLayer {
+  /// The annotations, sorted by `(start, end)`
+  annotations: Vec<Annotation>,
+  /// The largest span length in the layer, bounding how far back span queries have to look
+  max_length: usize,
+}
+
+impl AnnotationLayer {
+  /// Add an annotation, keeping the layer sorted. Returns the index of the new annotation.
+  pub fn insert(&mut self, annotation: Annotation) -> usize {
+    let key = (annotation.start, annotation.end);
+    let index = self
+      .annotations
+      .partition_point(|existing| (existing.start, existing.end) <= key);
+    self.max_length = self
+      .max_length
+      .max(annotation.end.saturating_sub(annotation.start));
+    self.annotations.insert(index, annotation);
+    index
+  }
+
+  /// Number of annotations in the layer
+  pub fn len(&self) -> usize { self.annotations.len() }
+
+  /// Checks whether the layer is empty
+  pub fn is_empty(&self) -> bool { self.annotations.is_empty() }
+
+  /// Get an annotation by its index
+  pub fn get(&self, index: usize) -> Option<&Annotation> { self.annotations.get(index) }
+
+  /// All annotations, in order
+  pub fn iter(&self) -> std::slice::Iter<Annotation> { self.annotations.iter() }
+
+  /// Annotations overlapping with `start..end`
+  pub fn overlapping(&self, start: usize, end: usize) -> impl Iterator<Item = &Annotation> {
+    self
+      .candidates(start, end)
+      .filter(move |annotation| annotation.overlaps(start, end))
+  }
+
+  /// Annotations containing `start..end`
+  pub fn containing(&self, start: usize, end: usize) -> impl Iterator<Item = &Annotation> {
+    self
+      .candidates(start, end)
+      .filter(move |annotation| annotation.contains(start, end))
+  }
+
+  /// Annotations contained in `start..end`
+  pub fn within(&self, start: usize, end: usize) -> impl Iterator<Item = &Annotation> {
+    let first = self
+      .annotations
+      .partition_point(|annotation| annotation.start < start);
+    self.annotations[first..]
+      .iter()
+      .take_while(move |annotation| annotation.start <= end)
+      .filter(move |annotation| annotation.end <= end)
+  }
+
+  /// Helper function: the annotations that could intersect with `start..end`
+  fn candidates(&self, start: usize, end: usize) -> impl Iterator<Item = &Annotation> {
+    let earliest = start.saturating_sub(self.max_length);
+    let first = self
+      .annotations
+      .partition_point(|annotation| annotation.start < earliest);
+    self.annotations[first..]
+      .iter()
+      .take_while(move |annotation| annotation.start <= end)
+  }
+}
+
+impl<'a> IntoIterator for &'a AnnotationLayer {
+  type Item = &'a Annotation;
+  type IntoIter = std::slice::Iter<'a, Annotation>;
+  fn into_iter(self) -> Self::IntoIter { self.annotations.iter() }
+}
+
+/// A store of named annotation layers over the plaintext of a `DNM`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationStore {
+  /// The layers, by name
+  layers: BTreeMap<String, AnnotationLayer>,
+}
+
+impl AnnotationStore {
+  /// Create an empty store
+  pub fn new() -> Self { AnnotationStore::default() }
+
+  /// Get a layer
+  pub fn layer(&self, name: &str) -> Option<&AnnotationLayer> { self.layers.get(name) }
+
+  /// Get a layer for modification, creating it if necessary
+  pub fn layer_mut(&mut self, name: &str) -> &mut AnnotationLayer {
+    self.layers.entry(name.to_string()).or_default()
+  }
+
+  /// Remove a layer, returning it
+  pub fn remove_layer(&mut self, name: &str) -> Option<AnnotationLayer> { self.layers.remove(name) }
+
+  /// The names of all layers, in alphabetical order
+  pub fn layer_names(&self) -> impl Iterator<Item = &str> { self.layers.keys().map(String::as_str) }
+
+  /// Add an annotation to a layer, creating the layer if necessary. Returns the index of the
+  /// annotation in the layer.
+  pub fn add(&mut self, layer: &str, annotation: Annotation) -> usize {
+    self.layer_mut(layer).insert(annotation)
+  }
+
+  /// Add an annotation spanning a `DNMRange` to a layer
+  pub fn add_range(&mut self, layer: &str, range: &DNMRange) -> usize {
+    self.add(layer, Annotation::from_range(range))
+  }
+
+  /// Annotations of a layer overlapping with a range (none if the layer doesn't exist)
+  pub fn overlapping<'s>(
+    &'s self,
+    layer: &str,
+    range: &DNMRange,
+  ) -> impl Iterator<Item = &'s Annotation> {
+    let (start, end) = (range.start, range.end);
+    self
+      .layers
+      .get(layer)
+      .into_iter()
+      .flat_map(move |layer| layer.overlapping(start, end))
+  }
+
+  /// Annotations of a layer containing a range (none if the layer doesn't exist)
+  pub fn containing<'s>(
+    &'s self,
+    layer: &str,
+    range: &DNMRange,
+  ) -> impl Iterator<Item = &'s Annotation> {
+    let (start, end) = (range.start, range.end);
+    self
+      .layers
+      .get(layer)
+      .into_iter()
+      .flat_map(move |layer| layer.containing(start, end))
+  }
+
+  /// Annotations of a layer contained in a range (none if the layer doesn't exist)
+  pub fn within<'s>(
+    &'s self,
+    layer: &str,
+    range: &DNMRange,
+  ) -> impl Iterator<Item = &'s Annotation> {
+    let (start, end) = (range.start, range.end);
+    self
+      .layers
+      .get(layer)
+      .into_iter()
+      .flat_map(move |layer| layer.within(start, end))
+  }
+
+  /// Write the store to a JSON file
+  pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, self)?;
+    Ok(())
+  }
+
+  /// Read a store from a JSON file
+  pub fn load(path: &str) -> Result<AnnotationStore, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+  }
+}
@@ -2,20 +2,72 @@
 //!      The core purpose for canonicalization is linguistic comparison,
 //! so the c14n module tries to strip away markup artefacts unrelated to the underlying
 //! content, such as xml:ids.
-use crate::dnm::DNM;
-use crypto::digest::Digest;
-use crypto::md5::Md5;
+use crate::dnm::{HashAlgorithm, DNM};
 use libxml::readonly::RoNode;
 use libxml::tree::NodeType::{ElementNode, TextNode};
+use log::{debug, warn};
 use regex::Regex;
-use std::sync::Mutex;
+use std::collections::{BTreeSet, HashSet};
 
 lazy_static! {
-  static ref MD5_HASHER: Mutex<Md5> = Mutex::new(Md5::new());
   static ref MATH_LEXEMES_RE: Regex =
     Regex::new(r"(?:(?:NUM|(?:(?:\S+_)+(?:\S+)))(\s|$))+").unwrap();
 }
 
+/// How whitespace in text nodes is canonicalized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum C14nWhitespace {
+  /// Keep all text as it is, including whitespace-only text nodes
+  Preserve,
+  /// Drop whitespace-only text nodes (default behaviour)
+  #[default]
+  DropBlank,
+  /// Drop whitespace-only text nodes, and collapse whitespace sequences into a single space
+  Collapse,
+}
+
+/// The layout of the canonical form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum C14nOutput {
+  /// Everything on a single line (default behaviour)
+  #[default]
+  Compact,
+  /// Every node on its own line, indented by depth
+  Indented,
+}
+
+/// Options for the canonicalization of a DOM node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct C14nOptions {
+  /// Attributes kept on elements, in sorted order. The whitespace-separated values of `class` are
+  /// sorted as well, other values are kept verbatim (escaped)
+  pub attributes: BTreeSet<String>,
+  /// Elements dropped together with their content, e.g. math annotations
+  pub drop_elements: HashSet<String>,
+  /// Elements replaced by their content
+  pub unwrap_elements: HashSet<String>,
+  /// How to treat whitespace in text nodes
+  pub whitespace: C14nWhitespace,
+  /// The layout of the canonical form
+  pub output: C14nOutput,
+}
+
+impl Default for C14nOptions {
+  /// The linguistic canonical form: only the `class` attribute is kept, math annotations are
+  /// dropped, `semantics` elements unwrapped, and whitespace-only text nodes dropped
+  fn default() -> C14nOptions {
+    C14nOptions {
+      attributes: ["class".to_string()].into_iter().collect(),
+      drop_elements: ["annotation".to_string(), "annotation-xml".to_string()]
+        .into_iter()
+        .collect(),
+      unwrap_elements: ["semantics".to_string()].into_iter().collect(),
+      whitespace: C14nWhitespace::DropBlank,
+      output: C14nOutput::Compact,
+    }
+  }
+}
+
 impl DNM {
   /// Our linguistic canonical form will only include 1) node name, 2) class attribute and 3)
   /// textual content - excludes certain experimental markup, such as all math annotation
@@ -24,8 +76,23 @@ impl DNM {
 
   /// Canonicalize a single node of choice
   pub fn node_c14n_basic(&self, node: RoNode) -> String {
+    self.node_c14n(node, &C14nOptions::default())
+  }
+
+  /// Canonical form of the entire DOM, as configured by `options`
+  pub fn to_c14n(&self, options: &C14nOptions) -> String { self.node_c14n(self.root_node, options) }
+
+  /// Canonical form of a single node of choice, as configured by `options`
+  pub fn node_c14n(&self, node: RoNode, options: &C14nOptions) -> String {
     let mut canonical_node = String::new();
-    self.canonical_internal(node, None, &mut canonical_node);
+    let indent = match options.output {
+      C14nOutput::Compact => None,
+      C14nOutput::Indented => Some(1),
+    };
+    self.canonical_internal(node, indent, options, &mut canonical_node);
+    if options.output == C14nOutput::Indented && canonical_node.starts_with('\n') {
+      canonical_node.remove(0);
+    }
     canonical_node
   }
 
@@ -34,13 +101,16 @@ impl DNM {
 
   /// Obtain an MD5 hash from the canonical string of a Node
   pub fn node_hash_basic(&self, node: RoNode) -> String {
-    let mut hasher = MD5_HASHER.lock().unwrap();
-    hasher.reset();
-    hasher.input_str(&self.node_c14n_basic(node));
-    hasher.result_str()
+    HashAlgorithm::Md5.digest(self.node_c14n_basic(node).as_bytes())
   }
 
-  fn canonical_internal(&self, node: RoNode, indent: Option<u32>, canonical_node: &mut String) {
+  pub(crate) fn canonical_internal(
+    &self,
+    node: RoNode,
+    indent: Option<u32>,
+    options: &C14nOptions,
+    canonical_node: &mut String,
+  ) {
     // Bookkeep indents, if requested
     let indent_string = match indent {
       Some(level) => String::new() + "\n" + &(1..level).map(|_| " ").collect::<String>(),
@@ -52,66 +122,119 @@ impl DNM {
       Some(TextNode) => {
         if let Ok(range) = self.get_range_of_node(node) {
           let text = range.get_plaintext();
-          if !text.trim().is_empty() {
-            canonical_node.push_str(&indent_string);
-            canonical_node.push_str(text);
-          } else {
-            // ignore empty nodes
+          match options.whitespace {
+            C14nWhitespace::Preserve => {
+              canonical_node.push_str(&indent_string);
+              canonical_node.push_str(text);
+            },
+            _ if text.trim().is_empty() => {
+              // ignore empty nodes
+            },
+            C14nWhitespace::DropBlank => {
+              canonical_node.push_str(&indent_string);
+              canonical_node.push_str(text);
+            },
+            C14nWhitespace::Collapse => {
+              canonical_node.push_str(&indent_string);
+              canonical_node.push_str(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+            },
           }
         }
       },
       Some(ElementNode) => {
         // Skip artefact nodes
         let name: String = node.get_name();
-        if (name == "annotation") || (name == "annotation-xml") {
+        if options.drop_elements.contains(&name) {
           return;
         }
+        let unwrap = options.unwrap_elements.contains(&name);
 
         // Open the current node
-        if name != "semantics" {
-          // ignore unwrappable nodes
+        if !unwrap {
           canonical_node.push_str(&indent_string);
-          canonical_node.push('<');
-          canonical_node.push_str(&name);
-
-          let class_attr = node.get_property("class").unwrap_or_default();
-          let mut classes_split = class_attr.split_whitespace().collect::<Vec<_>>();
-          if !classes_split.is_empty() {
-            classes_split.sort_unstable();
-            canonical_node.push_str(" class=\"");
-            for class_value in classes_split {
-              canonical_node.push_str(class_value);
-              canonical_node.push(' ');
-            }
-            canonical_node.pop();
-            canonical_node.push('"');
-          }
-          canonical_node.push('>');
+          push_open_tag(node, &name, options, canonical_node);
         }
 
-        // Recurse into children
+        // Recurse into children; unwrapped nodes don't add a level of indentation
+        let child_indent = if unwrap { indent } else { next_indent_level };
         if let Some(child) = node.get_first_child() {
-          self.canonical_internal(child, next_indent_level, canonical_node);
+          self.canonical_internal(child, child_indent, options, canonical_node);
           let mut child_node = child;
 
           while let Some(child) = child_node.get_next_sibling() {
-            self.canonical_internal(child, next_indent_level, canonical_node);
+            self.canonical_internal(child, child_indent, options, canonical_node);
             child_node = child;
           }
         }
 
         // Close the current node
-        if name != "semantics" {
-          // ignore unwrappable nodes
+        if !unwrap {
           canonical_node.push_str(&indent_string);
           canonical_node.push_str("</");
           canonical_node.push_str(&name);
           canonical_node.push('>');
         }
       },
-      _ => {
-        println!("-- Skipping node {:?}", node.get_name());
-      }, // skip all other node types for now
+      Some(other) => {
+        // comments, processing instructions, etc. carry no linguistic content
+        debug!(
+          "llamapun::dnm::c14n: skipping {:?} node {:?}",
+          other,
+          node.get_name()
+        );
+      },
+      None => {
+        warn!(
+          "llamapun::dnm::c14n: skipping node {:?} of unknown type",
+          node.get_name()
+        );
+      },
+    }
+  }
+}
+
+/// Helper function: the canonical opening tag of an element, with its allowed attributes
+pub(crate) fn push_open_tag(
+  node: RoNode,
+  name: &str,
+  options: &C14nOptions,
+  canonical_node: &mut String,
+) {
+  canonical_node.push('<');
+  canonical_node.push_str(name);
+  for attribute in &options.attributes {
+    let value = match node.get_property(attribute) {
+      Some(value) => value,
+      None => continue,
+    };
+    let value = if attribute == "class" {
+      // only the class list is normalized, other values are kept as they are
+      let mut classes_split = value.split_whitespace().collect::<Vec<_>>();
+      if classes_split.is_empty() {
+        continue;
+      }
+      classes_split.sort_unstable();
+      classes_split.join(" ")
+    } else {
+      value
+    };
+    canonical_node.push(' ');
+    canonical_node.push_str(attribute);
+    canonical_node.push_str("=\"");
+    push_escaped_attribute_value(&value, canonical_node);
+    canonical_node.push('"');
+  }
+  canonical_node.push('>');
+}
+
+/// Helper function: an attribute value, escaped for a double-quoted attribute
+fn push_escaped_attribute_value(value: &str, canonical_node: &mut String) {
+  for character in value.chars() {
+    match character {
+      '&' => canonical_node.push_str("&amp;"),
+      '<' => canonical_node.push_str("&lt;"),
+      '"' => canonical_node.push_str("&quot;"),
+      _ => canonical_node.push(character),
     }
   }
 }
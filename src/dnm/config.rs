@@ -0,0 +1,219 @@
+//! The `dnm::config` submodule provides a declarative form of `DNMParameters`, which can be
+//! saved to and loaded from JSON or TOML files, e.g.
+//!
+//! ```toml
+//! preset = "llamapun_normalization"
+//! wrap_tokens = false
+//!
+//! [special_tag_name_options]
+//! math = "skip"
+//! cite = { normalize = "CitationElement" }
+//! ```
+//!
+//! A config starts from a named `preset` (see `DNMParameters::preset`), and overrides its rules
+//! and options. Rules computed by closures (`SpecialTagsOption::FunctionNormalize`) can't be
+//! expressed declaratively.
+use crate::dnm::{
+  BlockBoundaries, DNMParameters, SpecialTagsOption, TagSelector, UnicodeNormalization,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// A declarative rule for a special tag, mirroring `SpecialTagsOption`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagRule {
+  /// Recurse into tag
+  Enter,
+  /// Normalize tag, replacing it by some token
+  Normalize(String),
+  /// Skip tag
+  Skip,
+}
+
+/// A declarative selector-keyed rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorRule {
+  /// the selector, e.g. `figcaption span.ltx_tag`
+  pub selector: String,
+  /// the rule for matching tags
+  pub rule: TagRule,
+}
+
+/// The declarative, serializable form of `DNMParameters`. Unset options keep the preset's value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DNMParametersConfig {
+  /// Named preset to start from, "default" if unset
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub preset: Option<String>,
+  /// see `DNMParameters::normalize_white_spaces`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub normalize_white_spaces: Option<bool>,
+  /// see `DNMParameters::wrap_tokens`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub wrap_tokens: Option<bool>,
+  /// see `DNMParameters::normalize_unicode`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub normalize_unicode: Option<bool>,
+  /// see `DNMParameters::unicode_normalization`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub unicode_normalization: Option<UnicodeNormalization>,
+  /// see `DNMParameters::stem_words_once`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub stem_words_once: Option<bool>,
+  /// see `DNMParameters::stem_words_full`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub stem_words_full: Option<bool>,
+  /// see `DNMParameters::convert_to_lowercase`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub convert_to_lowercase: Option<bool>,
+  /// see `DNMParameters::support_back_mapping`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub support_back_mapping: Option<bool>,
+  /// see `DNMParameters::block_boundaries`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub block_boundaries: Option<BlockBoundaries>,
+  /// Tag name rules, added to (and overriding) the preset's
+  pub special_tag_name_options: BTreeMap<String, TagRule>,
+  /// Tag class rules, added to (and overriding) the preset's
+  pub special_tag_class_options: BTreeMap<String, TagRule>,
+  /// Selector rules, tried after the preset's
+  pub special_tag_selector_options: Vec<SelectorRule>,
+}
+
+impl TagRule {
+  fn to_option(&self) -> SpecialTagsOption {
+    match self {
+      TagRule::Enter => SpecialTagsOption::Enter,
+      TagRule::Normalize(token) => SpecialTagsOption::Normalize(token.clone()),
+      TagRule::Skip => SpecialTagsOption::Skip,
+    }
+  }
+
+  fn from_option(option: &SpecialTagsOption) -> Result<TagRule, String> {
+    match option {
+      SpecialTagsOption::Enter => Ok(TagRule::Enter),
+      SpecialTagsOption::Normalize(token) => Ok(TagRule::Normalize(token.clone())),
+      SpecialTagsOption::Skip => Ok(TagRule::Skip),
+      SpecialTagsOption::FunctionNormalize(_) => {
+        Err("FunctionNormalize rules can't be saved in a DNMParameters config".to_string())
+      },
+    }
+  }
+}
+
+impl DNMParametersConfig {
+  /// Build the `DNMParameters` described by this config
+  pub fn to_parameters(&self) -> Result<DNMParameters, String> {
+    let mut parameters = DNMParameters::preset(self.preset.as_deref().unwrap_or("default"))?;
+    for (name, rule) in &self.special_tag_name_options {
+      parameters
+        .special_tag_name_options
+        .insert(name.clone(), rule.to_option());
+    }
+    for (class, rule) in &self.special_tag_class_options {
+      parameters
+        .special_tag_class_options
+        .insert(class.clone(), rule.to_option());
+    }
+    for selector_rule in &self.special_tag_selector_options {
+      parameters.special_tag_selector_options.push((
+        TagSelector::parse(&selector_rule.selector)?,
+        selector_rule.rule.to_option(),
+      ));
+    }
+    macro_rules! override_option {
+      ($($field: ident),*) => {
+        $(if let Some(value) = self.$field {
+          parameters.$field = value;
+        })*
+      };
+    }
+    override_option!(
+      normalize_white_spaces,
+      wrap_tokens,
+      normalize_unicode,
+      unicode_normalization,
+      stem_words_once,
+      stem_words_full,
+      convert_to_lowercase,
+      support_back_mapping
+    );
+    if self.block_boundaries.is_some() {
+      parameters.block_boundaries = self.block_boundaries.clone();
+    }
+    Ok(parameters)
+  }
+
+  /// Describe `parameters` in full, without relying on a preset
+  pub fn from_parameters(parameters: &DNMParameters) -> Result<DNMParametersConfig, String> {
+    let mut config = DNMParametersConfig {
+      preset: None,
+      normalize_white_spaces: Some(parameters.normalize_white_spaces),
+      wrap_tokens: Some(parameters.wrap_tokens),
+      normalize_unicode: Some(parameters.normalize_unicode),
+      unicode_normalization: Some(parameters.unicode_normalization),
+      stem_words_once: Some(parameters.stem_words_once),
+      stem_words_full: Some(parameters.stem_words_full),
+      convert_to_lowercase: Some(parameters.convert_to_lowercase),
+      support_back_mapping: Some(parameters.support_back_mapping),
+      block_boundaries: parameters.block_boundaries.clone(),
+      ..Default::default()
+    };
+    for (name, option) in &parameters.special_tag_name_options {
+      config
+        .special_tag_name_options
+        .insert(name.clone(), TagRule::from_option(option)?);
+    }
+    for (class, option) in &parameters.special_tag_class_options {
+      config
+        .special_tag_class_options
+        .insert(class.clone(), TagRule::from_option(option)?);
+    }
+    for (selector, option) in &parameters.special_tag_selector_options {
+      config.special_tag_selector_options.push(SelectorRule {
+        selector: selector.to_string(),
+        rule: TagRule::from_option(option)?,
+      });
+    }
+    Ok(config)
+  }
+}
+
+impl DNMParameters {
+  /// Load parameters from a config file, in TOML format if the extension is `.toml`, and in JSON
+  /// format otherwise
+  pub fn load(path: &str) -> Result<DNMParameters, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let config: DNMParametersConfig = if is_toml(path) {
+      toml::from_str(&content)?
+    } else {
+      serde_json::from_str(&content)?
+    };
+    Ok(config.to_parameters()?)
+  }
+
+  /// Save the parameters to a config file, in TOML format if the extension is `.toml`, and in
+  /// JSON format otherwise
+  pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+    let config = DNMParametersConfig::from_parameters(self)?;
+    let content = if is_toml(path) {
+      toml::to_string(&config)?
+    } else {
+      serde_json::to_string_pretty(&config)?
+    };
+    fs::write(path, content)?;
+    Ok(())
+  }
+}
+
+/// Helper function: whether a config path is in TOML format
+fn is_toml(path: &str) -> bool {
+  Path::new(path)
+    .extension()
+    .is_some_and(|extension| extension == "toml")
+}
@@ -0,0 +1,332 @@
+//! The `dnm::diff` submodule compares two documents via their canonical forms (see `dnm::c14n`),
+//! e.g. two conversions of the same paper by different converter releases.
+//!
+//! The DOM trees are aligned via the digests of their canonical subtrees (see `MerkleTree`), so
+//! that unchanged sections are skipped cheaply. Aligned subtrees which differ are compared in
+//! turn, down to the nodes that were inserted, deleted or changed. Changed text is further
+//! compared word by word, and reported as pairs of `DNMRange`s over both plaintexts.
+use crate::dnm::c14n::push_open_tag;
+use crate::dnm::{C14nOptions, DNMRange, HashAlgorithm, MerkleTree, DNM};
+use crate::util::data_helpers::whitespace_words;
+use libxml::readonly::RoNode;
+use libxml::tree::NodeType::{ElementNode, TextNode};
+
+/// A structural change between two DOM trees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeChange {
+  /// A node only present in the new document
+  Inserted(RoNode),
+  /// A node only present in the old document
+  Deleted(RoNode),
+  /// An element whose canonical tag changed, or a text node whose text changed, as (old, new)
+  Changed(RoNode, RoNode),
+}
+
+/// A plaintext difference, as the ranges of the old and the new text. One side is empty for
+/// pure insertions and deletions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChange<'old, 'new> {
+  /// The replaced text in the old document
+  pub old: DNMRange<'old>,
+  /// The replacing text in the new document
+  pub new: DNMRange<'new>,
+}
+
+/// The differences between two documents
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentDiff<'old, 'new> {
+  /// The structural changes, in document order
+  pub nodes: Vec<NodeChange>,
+  /// The plaintext changes, in document order
+  pub text: Vec<TextChange<'old, 'new>>,
+}
+
+impl<'old, 'new> DocumentDiff<'old, 'new> {
+  /// Checks whether the documents have equal canonical forms
+  pub fn is_empty(&self) -> bool { self.nodes.is_empty() && self.text.is_empty() }
+}
+
+impl DNM {
+  /// Compares the canonical form of this DNM's document (the old one) with the one of `other`
+  /// (the new one), as configured by `options`
+  pub fn diff<'old, 'new>(
+    &'old self,
+    other: &'new DNM,
+    options: &C14nOptions,
+  ) -> DocumentDiff<'old, 'new> {
+    let mut differ = Differ {
+      old: self,
+      new: other,
+      options,
+      diff: DocumentDiff::default(),
+    };
+    let old_tree = self.to_merkle_tree(options, HashAlgorithm::Xxh3);
+    let new_tree = other.to_merkle_tree(options, HashAlgorithm::Xxh3);
+    match (old_tree, new_tree) {
+      (Some(old_tree), Some(new_tree)) => differ.compare(&old_tree, &new_tree),
+      (Some(old_tree), None) => differ.delete(&old_tree, 0),
+      (None, Some(new_tree)) => differ.insert(&new_tree, 0),
+      (None, None) => {},
+    }
+    differ.diff
+  }
+}
+
+/// Helper struct: the state of a comparison
+struct Differ<'old, 'new, 'o> {
+  old: &'old DNM,
+  new: &'new DNM,
+  options: &'o C14nOptions,
+  diff: DocumentDiff<'old, 'new>,
+}
+
+impl<'old, 'new, 'o> Differ<'old, 'new, 'o> {
+  /// Compares two aligned subtrees
+  fn compare(&mut self, old: &MerkleTree, new: &MerkleTree) {
+    if old.digest == new.digest {
+      return;
+    }
+    match (old.node.get_type(), new.node.get_type()) {
+      (Some(TextNode), Some(TextNode)) => {
+        self
+          .diff
+          .nodes
+          .push(NodeChange::Changed(old.node, new.node));
+        if let (Ok(old_range), Ok(new_range)) = (
+          self.old.get_range_of_node(old.node),
+          self.new.get_range_of_node(new.node),
+        ) {
+          self.compare_words(&old_range, &new_range);
+        }
+      },
+      _ if self.compatible(old, new) => {
+        if self.open_tag(old.node) != self.open_tag(new.node) {
+          self
+            .diff
+            .nodes
+            .push(NodeChange::Changed(old.node, new.node));
+        }
+        self.compare_children(old, new);
+      },
+      _ => {
+        let new_start = self.new_start(new);
+        self.delete(old, new_start);
+        let old_end = self.old_end(old);
+        self.insert(new, old_end);
+      },
+    }
+  }
+
+  /// Aligns the children of two subtrees on their digests, and compares the unaligned gaps
+  fn compare_children(&mut self, old: &MerkleTree, new: &MerkleTree) {
+    let old_digests: Vec<&str> = old.children.iter().map(|c| c.digest.as_str()).collect();
+    let new_digests: Vec<&str> = new.children.iter().map(|c| c.digest.as_str()).collect();
+    let mut anchors = common_subsequence(&old_digests, &new_digests);
+    anchors.push((old.children.len(), new.children.len()));
+
+    let (mut old_index, mut new_index) = (0, 0);
+    let mut old_cursor = self.old_start(old);
+    let mut new_cursor = self.new_start(new);
+    for (old_anchor, new_anchor) in anchors {
+      let old_gap = &old.children[old_index..old_anchor];
+      let new_gap = &new.children[new_index..new_anchor];
+      // pair up the nodes of the gaps in order, as long as they are of the same kind
+      let paired = old_gap
+        .iter()
+        .zip(new_gap.iter())
+        .take_while(|(old_child, new_child)| self.compatible(old_child, new_child))
+        .count();
+      for (old_child, new_child) in old_gap.iter().zip(new_gap.iter()).take(paired) {
+        self.compare(old_child, new_child);
+        old_cursor = self.old_end(old_child).max(old_cursor);
+        new_cursor = self.new_end(new_child).max(new_cursor);
+      }
+      for old_child in &old_gap[paired..] {
+        self.delete(old_child, new_cursor);
+        old_cursor = self.old_end(old_child).max(old_cursor);
+      }
+      for new_child in &new_gap[paired..] {
+        self.insert(new_child, old_cursor);
+        new_cursor = self.new_end(new_child).max(new_cursor);
+      }
+      if old_anchor < old.children.len() {
+        old_cursor = self.old_end(&old.children[old_anchor]).max(old_cursor);
+        new_cursor = self.new_end(&new.children[new_anchor]).max(new_cursor);
+      }
+      old_index = old_anchor + 1;
+      new_index = new_anchor + 1;
+    }
+  }
+
+  /// Records a deleted subtree, whose text is removed at `new_position`
+  fn delete(&mut self, old: &MerkleTree, new_position: usize) {
+    self.diff.nodes.push(NodeChange::Deleted(old.node));
+    if let Ok(old_range) = self.old.get_range_of_node(old.node) {
+      if !old_range.is_empty() {
+        self.diff.text.push(TextChange {
+          old: old_range,
+          new: empty_range(self.new, new_position),
+        });
+      }
+    }
+  }
+
+  /// Records an inserted subtree, whose text is added at `old_position`
+  fn insert(&mut self, new: &MerkleTree, old_position: usize) {
+    self.diff.nodes.push(NodeChange::Inserted(new.node));
+    if let Ok(new_range) = self.new.get_range_of_node(new.node) {
+      if !new_range.is_empty() {
+        self.diff.text.push(TextChange {
+          old: empty_range(self.old, old_position),
+          new: new_range,
+        });
+      }
+    }
+  }
+
+  /// Compares the words of two text ranges, recording the differing runs of words
+  fn compare_words(&mut self, old: &DNMRange<'old>, new: &DNMRange<'new>) {
+    let old_words = whitespace_words(old.get_plaintext());
+    let new_words = whitespace_words(new.get_plaintext());
+    let old_text: Vec<&str> = old_words.iter().map(|word| word.0).collect();
+    let new_text: Vec<&str> = new_words.iter().map(|word| word.0).collect();
+    let mut anchors = common_subsequence(&old_text, &new_text);
+    anchors.push((old_words.len(), new_words.len()));
+
+    let (mut old_index, mut new_index) = (0, 0);
+    for (old_anchor, new_anchor) in anchors {
+      if old_index < old_anchor || new_index < new_anchor {
+        // the gap spans from the first unmatched word to the last, or is empty at the anchor
+        let old_start = position(old, &old_words, old_index);
+        let old_end = if old_index < old_anchor {
+          old_words[old_anchor - 1].2
+        } else {
+          old_start
+        };
+        let new_start = position(new, &new_words, new_index);
+        let new_end = if new_index < new_anchor {
+          new_words[new_anchor - 1].2
+        } else {
+          new_start
+        };
+        self.diff.text.push(TextChange {
+          old: DNMRange {
+            start: old.start + old_start,
+            end: old.start + old_end,
+            dnm: self.old,
+          },
+          new: DNMRange {
+            start: new.start + new_start,
+            end: new.start + new_end,
+            dnm: self.new,
+          },
+        });
+      }
+      old_index = old_anchor + 1;
+      new_index = new_anchor + 1;
+    }
+  }
+
+  /// Whether two subtrees can be compared node to node: both text, or elements of the same name
+  fn compatible(&self, old: &MerkleTree, new: &MerkleTree) -> bool {
+    match (old.node.get_type(), new.node.get_type()) {
+      (Some(TextNode), Some(TextNode)) => true,
+      (Some(ElementNode), Some(ElementNode)) => old.node.get_name() == new.node.get_name(),
+      _ => false,
+    }
+  }
+
+  /// The canonical opening tag of an element
+  fn open_tag(&self, node: RoNode) -> String {
+    let mut tag = String::new();
+    push_open_tag(node, &node.get_name(), self.options, &mut tag);
+    tag
+  }
+
+  fn old_start(&self, tree: &MerkleTree) -> usize {
+    self
+      .old
+      .get_range_of_node(tree.node)
+      .map_or(0, |range| range.start)
+  }
+  fn old_end(&self, tree: &MerkleTree) -> usize {
+    self
+      .old
+      .get_range_of_node(tree.node)
+      .map_or(0, |range| range.end)
+  }
+  fn new_start(&self, tree: &MerkleTree) -> usize {
+    self
+      .new
+      .get_range_of_node(tree.node)
+      .map_or(0, |range| range.start)
+  }
+  fn new_end(&self, tree: &MerkleTree) -> usize {
+    self
+      .new
+      .get_range_of_node(tree.node)
+      .map_or(0, |range| range.end)
+  }
+}
+
+/// Helper function: an empty range at `position`
+fn empty_range(dnm: &DNM, position: usize) -> DNMRange {
+  DNMRange {
+    start: position,
+    end: position,
+    dnm,
+  }
+}
+
+/// Helper function: the char offset of the `index`-th word in a range, or the range's end
+fn position(range: &DNMRange, words: &[(&str, usize, usize)], index: usize) -> usize {
+  match words.get(index) {
+    Some(word) => word.1,
+    None => range.end - range.start,
+  }
+}
+
+/// Helper function: the index pairs of a longest common subsequence of two sequences
+fn common_subsequence<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(usize, usize)> {
+  // skip the common prefix and suffix, which are the bulk of most revisions
+  let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+  let suffix = old[prefix..]
+    .iter()
+    .rev()
+    .zip(new[prefix..].iter().rev())
+    .take_while(|(a, b)| a == b)
+    .count();
+  let (old_middle, new_middle) = (
+    &old[prefix..old.len() - suffix],
+    &new[prefix..new.len() - suffix],
+  );
+
+  // lengths[i][j] is the length of a longest common subsequence of old_middle[i..], new_middle[j..]
+  let mut lengths = vec![vec![0; new_middle.len() + 1]; old_middle.len() + 1];
+  for i in (0..old_middle.len()).rev() {
+    for j in (0..new_middle.len()).rev() {
+      lengths[i][j] = if old_middle[i] == new_middle[j] {
+        lengths[i + 1][j + 1] + 1
+      } else {
+        lengths[i + 1][j].max(lengths[i][j + 1])
+      };
+    }
+  }
+
+  let mut pairs: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+  let (mut i, mut j) = (0, 0);
+  while i < old_middle.len() && j < new_middle.len() {
+    if old_middle[i] == new_middle[j] {
+      pairs.push((prefix + i, prefix + j));
+      i += 1;
+      j += 1;
+    } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+      i += 1;
+    } else {
+      j += 1;
+    }
+  }
+  let (old_rest, new_rest) = (old.len() - suffix, new.len() - suffix);
+  pairs.extend((0..suffix).map(|k| (old_rest + k, new_rest + k)));
+  pairs
+}
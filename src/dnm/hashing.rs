@@ -0,0 +1,180 @@
+//! The `dnm::hashing` submodule computes digests of canonical forms (see `dnm::c14n`).
+//!
+//! Every call uses its own hasher, so hashing scales across threads, e.g. in a parallel corpus
+//! walk. Besides flat digests of a node's canonical form, a Merkle-style tree of subtree digests
+//! can be built, to locate the changed sections of two versions of a document without comparing
+//! their full canonical forms.
+use crate::dnm::c14n::push_open_tag;
+use crate::dnm::{C14nOptions, DNM};
+use crypto::digest::Digest as _;
+use crypto::md5::Md5;
+use libxml::readonly::RoNode;
+use libxml::tree::NodeType::{ElementNode, TextNode};
+use sha2::{Digest as _, Sha256};
+use std::collections::HashSet;
+
+/// The hash function used for digests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgorithm {
+  /// MD5, only kept for the digests of `node_hash_basic`
+  Md5,
+  /// SHA-256 (default), suitable for provenance records
+  #[default]
+  Sha256,
+  /// BLAKE3, a fast cryptographic hash
+  Blake3,
+  /// XXH3 (128 bit), a fast non-cryptographic hash, for deduplication and caching
+  Xxh3,
+}
+
+impl HashAlgorithm {
+  /// The lowercase hexadecimal digest of `data`
+  pub fn digest(self, data: &[u8]) -> String {
+    match self {
+      HashAlgorithm::Md5 => {
+        let mut hasher = Md5::new();
+        hasher.input(data);
+        hasher.result_str()
+      },
+      HashAlgorithm::Sha256 => format!("{:x}", Sha256::digest(data)),
+      HashAlgorithm::Blake3 => blake3::hash(data).to_hex().to_string(),
+      HashAlgorithm::Xxh3 => format!("{:032x}", xxhash_rust::xxh3::xxh3_128(data)),
+    }
+  }
+}
+
+/// A tree of digests over the canonical form of a DOM subtree. Each digest covers the canonical
+/// opening tag of its node and the digests of its children, so two subtrees with equal digests
+/// have equal canonical forms.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+  /// The element or text node
+  pub node: RoNode,
+  /// The digest of the subtree
+  pub digest: String,
+  /// The subtrees of the canonical children. Children of unwrapped elements are lifted into the
+  /// parent, as in the canonical form.
+  pub children: Vec<MerkleTree>,
+}
+
+impl MerkleTree {
+  /// Locates the nodes of `self` whose subtrees differ from `other`: the smallest changed
+  /// subtrees if the children can be aligned, or the node itself otherwise. Empty if the digests
+  /// are equal.
+  pub fn changed_nodes(&self, other: &MerkleTree) -> Vec<RoNode> {
+    let mut changed = Vec::new();
+    self.changed_internal(other, &mut changed);
+    changed
+  }
+
+  fn changed_internal(&self, other: &MerkleTree, changed: &mut Vec<RoNode>) {
+    if self.digest == other.digest {
+      return;
+    }
+    let found = changed.len();
+    if self.children.len() == other.children.len() {
+      // aligned children, descend into the differing ones
+      for (child, other_child) in self.children.iter().zip(other.children.iter()) {
+        child.changed_internal(other_child, changed);
+      }
+    } else {
+      // children were inserted or removed, report the ones absent from `other`
+      let other_digests: HashSet<&str> = other
+        .children
+        .iter()
+        .map(|child| child.digest.as_str())
+        .collect();
+      for child in &self.children {
+        if !other_digests.contains(child.digest.as_str()) {
+          changed.push(child.node);
+        }
+      }
+    }
+    if changed.len() == found {
+      // only the node's own tag (or a removed child) differs
+      changed.push(self.node);
+    }
+  }
+
+  /// Helper function: whether the node is an element replaced by its content
+  fn is_unwrapped(&self, options: &C14nOptions) -> bool {
+    matches!(self.node.get_type(), Some(ElementNode))
+      && options.unwrap_elements.contains(&self.node.get_name())
+  }
+}
+
+impl DNM {
+  /// Digest of the canonical form of the entire DOM, as configured by `options`
+  pub fn to_hash(&self, options: &C14nOptions, algorithm: HashAlgorithm) -> String {
+    self.node_hash(self.root_node, options, algorithm)
+  }
+
+  /// Digest of the canonical form of a node, as configured by `options`
+  pub fn node_hash(&self, node: RoNode, options: &C14nOptions, algorithm: HashAlgorithm) -> String {
+    algorithm.digest(self.node_c14n(node, options).as_bytes())
+  }
+
+  /// The Merkle tree of the canonical form of a node, or `None` if the node has no canonical
+  /// form (e.g. a dropped element or a whitespace-only text node)
+  pub fn merkle_tree(
+    &self,
+    node: RoNode,
+    options: &C14nOptions,
+    algorithm: HashAlgorithm,
+  ) -> Option<MerkleTree> {
+    match node.get_type() {
+      Some(TextNode) => {
+        let mut text = String::new();
+        self.canonical_internal(node, None, options, &mut text);
+        if text.is_empty() {
+          return None;
+        }
+        let mut data = vec![0];
+        data.extend_from_slice(text.as_bytes());
+        Some(MerkleTree {
+          node,
+          digest: algorithm.digest(&data),
+          children: Vec::new(),
+        })
+      },
+      Some(ElementNode) => {
+        let name = node.get_name();
+        if options.drop_elements.contains(&name) {
+          return None;
+        }
+        let mut children = Vec::new();
+        for child in node.get_child_nodes() {
+          if let Some(subtree) = self.merkle_tree(child, options, algorithm) {
+            if subtree.is_unwrapped(options) {
+              children.extend(subtree.children);
+            } else {
+              children.push(subtree);
+            }
+          }
+        }
+        let mut data = String::from("\u{1}");
+        if !options.unwrap_elements.contains(&name) {
+          push_open_tag(node, &name, options, &mut data);
+        }
+        for child in &children {
+          data.push_str(&child.digest);
+        }
+        Some(MerkleTree {
+          node,
+          digest: algorithm.digest(data.as_bytes()),
+          children,
+        })
+      },
+      _ => None,
+    }
+  }
+
+  /// The Merkle tree of the canonical form of the entire DOM
+  pub fn to_merkle_tree(
+    &self,
+    options: &C14nOptions,
+    algorithm: HashAlgorithm,
+  ) -> Option<MerkleTree> {
+    self.merkle_tree(self.root_node, options, algorithm)
+  }
+}
@@ -0,0 +1,241 @@
+//! The `dnm::markup` submodule writes DNM ranges back into the DOM, by wrapping the text they
+//! cover in new elements, e.g. `<span class="llamapun_sentence">`.
+//!
+//! The DNM only holds read-only nodes, so the changes are made to the `Document` it was built
+//! from. A range crossing element boundaries is split into one wrapper per text node (or
+//! normalized element) it covers, so that the result stays well-formed. Afterwards the DNM no
+//! longer reflects the document, and should be rebuilt if needed.
+use crate::dnm::{DNMRange, DNM};
+use libxml::readonly::RoNode;
+use libxml::tree::{Document, Node, NodeType};
+use std::collections::HashMap;
+use std::error::Error;
+use std::ptr;
+
+/// Class used for wrapping sentences, e.g. with `wrap_ranges(doc, &sentences, "span",
+/// SENTENCE_CLASS)`
+pub const SENTENCE_CLASS: &str = "llamapun_sentence";
+
+/// The part of a range that falls into a single DOM node
+enum Segment {
+  /// characters `start..end` of a text node
+  Text(RoNode, usize, usize),
+  /// a whole element, which was normalized in the DNM
+  Element(RoNode),
+}
+
+impl DNM {
+  /// Wraps the text of each of the `ranges` in new `element_name` elements with the given `class`,
+  /// modifying the `document` the DNM was built from. Ranges may not overlap. Returns the number
+  /// of elements created.
+  pub fn wrap_ranges(
+    &self,
+    document: &mut Document,
+    ranges: &[DNMRange],
+    element_name: &str,
+    class: &str,
+  ) -> Result<usize, Box<dyn Error>> {
+    if !self.parameters.support_back_mapping {
+      return Err("DNM did not generate the back_map".into());
+    }
+    let mut ranges: Vec<&DNMRange> = ranges.iter().collect();
+    ranges.sort();
+    for (index, range) in ranges.iter().enumerate() {
+      if !ptr::eq(range.dnm, self) {
+        return Err("DNMRange belongs to a different DNM".into());
+      }
+      if range.end > self.back_map.len() {
+        return Err(format!("DNMRange {}..{} exceeds the DNM", range.start, range.end).into());
+      }
+      if index > 0 && ranges[index - 1].end > range.start {
+        return Err(
+          format!(
+            "DNMRange {}..{} overlaps another range",
+            range.start, range.end
+          )
+          .into(),
+        );
+      }
+    }
+
+    // Collect all segments, before any node is modified
+    let mut text_segments: Vec<(RoNode, Vec<(usize, usize)>)> = Vec::new();
+    let mut text_index: HashMap<usize, usize> = HashMap::new();
+    let mut elements: Vec<RoNode> = Vec::new();
+    for range in ranges {
+      for segment in self.segments(range) {
+        match segment {
+          Segment::Text(node, start, end) => {
+            let index = *text_index.entry(node.to_hashable()).or_insert_with(|| {
+              text_segments.push((node, Vec::new()));
+              text_segments.len() - 1
+            });
+            text_segments[index].1.push((start, end));
+          },
+          Segment::Element(node) => {
+            // an element split between two ranges is wrapped once
+            if !elements.contains(&node) {
+              elements.push(node);
+            }
+          },
+        }
+      }
+    }
+
+    // Resolve the mutable counterparts of the nodes, also before any modification
+    let root = document
+      .get_root_element()
+      .ok_or("Document has no root element")?;
+    let mut created = 0;
+    let mut resolved_text = Vec::with_capacity(text_segments.len());
+    for (node, segments) in text_segments {
+      resolved_text.push((resolve_node(&root, node)?, segments));
+    }
+    let mut resolved_elements = Vec::with_capacity(elements.len());
+    for node in elements {
+      resolved_elements.push(resolve_node(&root, node)?);
+    }
+
+    for (mut text_node, mut segments) in resolved_text {
+      segments.sort_unstable();
+      let chars: Vec<char> = text_node.get_content().chars().collect();
+      let piece = |start: usize, end: usize| -> String { chars[start..end].iter().collect() };
+      // the original text node keeps the text before the first wrapper
+      let mut cursor = segments[0].0;
+      text_node
+        .set_content(&piece(0, cursor))
+        .map_err(|_| "Failed to set text content")?;
+      let mut anchor = text_node;
+      for (start, end) in segments {
+        let start = start.max(cursor);
+        if start >= end {
+          continue;
+        }
+        if cursor < start {
+          let mut between = Node::new_text(&piece(cursor, start), document)
+            .map_err(|_| "Failed to create text node")?;
+          anchor
+            .add_next_sibling(&mut between)
+            .map_err(|_| "Failed to insert node")?;
+          anchor = between;
+        }
+        let mut wrapper = new_wrapper(document, element_name, class)?;
+        wrapper
+          .append_text(&piece(start, end))
+          .map_err(|_| "Failed to add text to wrapper")?;
+        anchor
+          .add_next_sibling(&mut wrapper)
+          .map_err(|_| "Failed to insert node")?;
+        anchor = wrapper;
+        created += 1;
+        cursor = end;
+      }
+      if cursor < chars.len() {
+        let mut after = Node::new_text(&piece(cursor, chars.len()), document)
+          .map_err(|_| "Failed to create text node")?;
+        anchor
+          .add_next_sibling(&mut after)
+          .map_err(|_| "Failed to insert node")?;
+      }
+    }
+
+    for mut element in resolved_elements {
+      let mut wrapper = new_wrapper(document, element_name, class)?;
+      element
+        .add_prev_sibling(&mut wrapper)
+        .map_err(|_| "Failed to insert node")?;
+      element.unlink_node();
+      wrapper
+        .add_child(&mut element)
+        .map_err(|_| "Failed to insert node")?;
+      created += 1;
+    }
+    Ok(created)
+  }
+
+  /// Helper function: splits a range into the parts falling into single DOM nodes
+  fn segments(&self, range: &DNMRange) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for offset in range.start..range.end {
+      let (node, node_offset) = self.back_map[offset];
+      if node_offset >= 0 {
+        let node_offset = node_offset as usize;
+        if let Some(Segment::Text(last, start, end)) = segments.last_mut() {
+          if *last == node {
+            *start = (*start).min(node_offset);
+            *end = (*end).max(node_offset + 1);
+            continue;
+          }
+        }
+        segments.push(Segment::Text(node, node_offset, node_offset + 1));
+      } else if node.get_type() == Some(NodeType::ElementNode)
+        && !self.is_block_separator(node, offset)
+        && !self.plaintext[self.byte_offsets.at(offset)..self.byte_offsets.at(offset + 1)]
+          .trim()
+          .is_empty()
+      {
+        // whitespace around wrapped tokens doesn't make the element part of the range
+        if let Some(Segment::Element(last)) = segments.last() {
+          if *last == node {
+            continue;
+          }
+        }
+        segments.push(Segment::Element(node));
+      }
+    }
+    segments
+  }
+
+  /// Helper function: checks whether the element mapped at a plaintext offset is only there as
+  /// one of the block separators around it, which lie outside of its own (normalized) text
+  fn is_block_separator(&self, node: RoNode, offset: usize) -> bool {
+    match self.node_map.get(&node.to_hashable()) {
+      Some(&(start, end)) => offset < start || offset >= end,
+      None => false,
+    }
+  }
+}
+
+/// Helper function: creates a new wrapper element
+fn new_wrapper(
+  document: &Document,
+  element_name: &str,
+  class: &str,
+) -> Result<Node, Box<dyn Error>> {
+  let mut wrapper =
+    Node::new(element_name, None, document).map_err(|_| "Failed to create wrapper element")?;
+  if !class.is_empty() {
+    wrapper
+      .set_attribute("class", class)
+      .map_err(|_| "Failed to set wrapper class")?;
+  }
+  Ok(wrapper)
+}
+
+/// Helper function: finds the `Node` for a `RoNode` of the same document, via its path of child
+/// indices from the root element
+fn resolve_node(root: &Node, node: RoNode) -> Result<Node, Box<dyn Error>> {
+  let mut path = Vec::new();
+  let mut current = node;
+  while let Some(parent) = current.get_parent() {
+    if !matches!(parent.get_type(), Some(NodeType::ElementNode)) {
+      break;
+    }
+    let index = parent
+      .get_child_nodes()
+      .into_iter()
+      .position(|child| child == current)
+      .ok_or("Inconsistent DOM: node is not a child of its parent")?;
+    path.push(index);
+    current = parent;
+  }
+  let mut resolved = root.clone();
+  for index in path.into_iter().rev() {
+    resolved = resolved
+      .get_child_nodes()
+      .into_iter()
+      .nth(index)
+      .ok_or("DNM node not found in the document")?;
+  }
+  Ok(resolved)
+}
@@ -1,21 +1,50 @@
 //! The `dnm` can be used for easier switching between the DOM
 //! (Document Object Model) representation and the plain text representation,
 //! which is needed for most NLP tools.
+mod annotation;
 mod c14n;
+mod config;
+mod diff;
+mod hashing;
+mod markup;
 /// Node auxiliaries for DNMs
 pub mod node;
+mod offsets;
 mod parameters;
 mod range;
+mod selector;
+mod snapshot;
+mod unicode;
+mod web_annotation;
+mod xpointer;
 
 use libxml::readonly::RoNode;
 use libxml::tree::*;
+use std::cmp;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
-use unidecode::{unidecode, unidecode_char};
 
-pub use crate::dnm::parameters::{DNMParameters, RuntimeParseData, SpecialTagsOption};
-pub use crate::dnm::range::DNMRange;
+pub use crate::dnm::annotation::{
+  Annotation, AnnotationLayer, AnnotationStore, AnnotationValue, PATTERN_LAYER, SENTENCE_LAYER,
+  WORD_LAYER,
+};
+pub use crate::dnm::c14n::{C14nOptions, C14nOutput, C14nWhitespace};
+pub use crate::dnm::config::{DNMParametersConfig, SelectorRule, TagRule};
+pub use crate::dnm::diff::{DocumentDiff, NodeChange, TextChange};
+pub use crate::dnm::hashing::{HashAlgorithm, MerkleTree};
+pub use crate::dnm::markup::SENTENCE_CLASS;
+pub use crate::dnm::offsets::{ByteOffsets, OffsetIndex, OffsetUnit};
+pub use crate::dnm::parameters::{
+  BlockBoundaries, DNMParameters, ParameterConflict, ParameterError, RuntimeParseData,
+  SpecialTagsOption,
+};
+pub use crate::dnm::range::{DNMRange, DNMRangeNodes};
+pub use crate::dnm::selector::TagSelector;
+pub use crate::dnm::snapshot::DNMSnapshot;
+pub use crate::dnm::unicode::UnicodeNormalization;
+pub use crate::dnm::web_annotation::{AnnotationSelector, SourceText, DEFAULT_QUOTE_CONTEXT};
+pub use crate::dnm::xpointer::XPointerError;
 
 /// The `DNM` is essentially a wrapper around the plain text representation
 /// of the document, which facilitates mapping plaintext pieces to the DOM.
@@ -23,8 +52,8 @@ pub use crate::dnm::range::DNMRange;
 pub struct DNM {
   /// The plaintext
   pub plaintext: String,
-  /// As the plaintext is UTF-8: the byte offsets of the characters
-  pub byte_offsets: Vec<usize>,
+  /// As the plaintext is UTF-8: the byte offsets of the characters (and the total length)
+  pub byte_offsets: ByteOffsets,
   /// The options for generation
   pub parameters: DNMParameters,
   /// The root node of the underlying xml tree
@@ -40,13 +69,40 @@ pub struct DNM {
   /// this is e.g. used if a node is replaced by a token.
   pub back_map: Vec<(RoNode, i32)>,
 }
+/// Errors when constructing a `DNM`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DNMError {
+  /// The root node is missing (null)
+  MissingRootNode,
+  /// The parameters have conflicting settings
+  InvalidParameters(ParameterError),
+}
+
+impl fmt::Display for DNMError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DNMError::MissingRootNode => write!(f, "missing root node for DNM"),
+      DNMError::InvalidParameters(error) => write!(f, "{error}"),
+    }
+  }
+}
+
+impl Error for DNMError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      DNMError::MissingRootNode => None,
+      DNMError::InvalidParameters(error) => Some(error),
+    }
+  }
+}
+
 impl Default for DNM {
   fn default() -> DNM {
     DNM {
       parameters: DNMParameters::default(),
       root_node: RoNode::null(),
       plaintext: String::new(),
-      byte_offsets: Vec::new(),
+      byte_offsets: ByteOffsets::new(),
       node_map: HashMap::new(),
       runtime: RuntimeParseData::default(),
       back_map: Vec::new(),
@@ -70,7 +126,7 @@ impl fmt::Debug for DNM {
 #[macro_export]
 macro_rules! record_node_map(
   ($dnm: expr, $node: expr, $offset_start: expr) => {{
-    $dnm.node_map.insert($node.to_hashable(), ($offset_start, $dnm.runtime.chars.len()));
+    $dnm.node_map.insert($node.to_hashable(), ($offset_start, $dnm.byte_offsets.len()));
   }}
 );
 
@@ -81,11 +137,9 @@ macro_rules! push_token(
       push_whitespace!($dnm, $node, -1);
     }
 
-    if !$dnm.parameters.support_back_mapping {
-      $dnm.runtime.chars.extend($token.chars());
-    } else {
-      for c in $token.chars() {
-        $dnm.runtime.chars.push(c);
+    for c in $token.chars() {
+      $dnm.push_char(c);
+      if $dnm.parameters.support_back_mapping {
         $dnm.back_map.push(($node, -1));
       }
     }
@@ -102,7 +156,7 @@ macro_rules! push_whitespace(
   ($dnm: expr, $node: expr, $offset: expr) => (
   {
     if !$dnm.runtime.had_whitespace || !$dnm.parameters.normalize_white_spaces {
-      $dnm.runtime.chars.push(' ');
+      $dnm.push_char(' ');
       $dnm.runtime.had_whitespace = true;
       if $dnm.parameters.support_back_mapping {
         $dnm.back_map.push(($node.clone(), $offset));
@@ -116,14 +170,36 @@ macro_rules! push_whitespace(
 );
 
 impl DNM {
-  /// Creates a `DNM` for `root`
+  /// Creates a `DNM` for `root`. Conflicting parameters are reported, but tolerated;
+  /// see `DNM::try_new` for a strict constructor
   pub fn new(root_node: RoNode, parameters: DNMParameters) -> DNM {
-    parameters.check();
+    if let Err(error) = parameters.check() {
+      log::warn!("llamapun::dnm: {error}");
+    }
+    DNM::construct(root_node, parameters)
+  }
+
+  /// Creates a `DNM` for `root`, refusing a missing root node and conflicting parameters.
+  /// Harmless conflicts (see `ParameterConflict::is_warning`) are only logged.
+  pub fn try_new(root_node: RoNode, parameters: DNMParameters) -> Result<DNM, DNMError> {
+    if root_node.is_null() {
+      return Err(DNMError::MissingRootNode);
+    }
+    if let Err(error) = parameters.check() {
+      if !error.conflicts.iter().all(ParameterConflict::is_warning) {
+        return Err(DNMError::InvalidParameters(error));
+      }
+      log::warn!("llamapun::dnm: {error}");
+    }
+    Ok(DNM::construct(root_node, parameters))
+  }
+
+  fn construct(root_node: RoNode, parameters: DNMParameters) -> DNM {
     let mut dnm = DNM {
       parameters,
       root_node,
       back_map: Vec::new(),
-      byte_offsets: Vec::new(),
+      byte_offsets: ByteOffsets::new(),
       node_map: HashMap::new(),
       plaintext: String::new(),
       runtime: RuntimeParseData::default(),
@@ -133,13 +209,11 @@ impl DNM {
     // building a node<->text map.
     dnm.recurse_node_create(root_node);
 
-    // generate plaintext
-    assert_eq!(dnm.plaintext.len(), 0);
-    for c in &dnm.runtime.chars {
-      dnm.byte_offsets.push(dnm.plaintext.len());
-      dnm.plaintext.push(*c);
-    }
-    dnm.byte_offsets.push(dnm.plaintext.len()); // to have the length of the last char as well
+    // the plaintext was written directly, only the length of the last char is missing
+    dnm.byte_offsets.push(dnm.plaintext.len());
+    dnm.plaintext.shrink_to_fit();
+    dnm.byte_offsets.shrink_to_fit();
+    dnm.back_map.shrink_to_fit();
 
     dnm
   }
@@ -205,13 +279,51 @@ impl DNM {
   fn recurse_node_create(&mut self, node: RoNode) {
     if node.is_text_node() {
       self.text_node_create(node)
+    } else if self.is_block_boundary(node) {
+      self.push_block_separator(node);
+      self.intermediate_node_create(node);
+      self.push_block_separator(node);
     } else {
       self.intermediate_node_create(node)
     }
   }
 
+  fn is_block_boundary(&self, node: RoNode) -> bool {
+    match self.parameters.block_boundaries {
+      Some(ref boundaries) => boundaries.elements.contains(&node.get_name()),
+      None => false,
+    }
+  }
+
+  fn push_block_separator(&mut self, node: RoNode) {
+    let separator = match self.parameters.block_boundaries {
+      Some(ref boundaries) => boundaries.separator.clone(),
+      None => return,
+    };
+    // no leading separator, and collapse consecutive ones (e.g. of nested blocks)
+    if separator.is_empty()
+      || self.plaintext.is_empty()
+      || self.plaintext.ends_with(separator.as_str())
+    {
+      return;
+    }
+    self.runtime.had_whitespace = separator.chars().all(char::is_whitespace);
+    for c in separator.chars() {
+      self.push_char(c);
+      if self.parameters.support_back_mapping {
+        self.back_map.push((node, -1));
+      }
+    }
+  }
+
+  /// Appends a character to the plaintext, recording its byte offset
+  fn push_char(&mut self, c: char) {
+    self.byte_offsets.push(self.plaintext.len());
+    self.plaintext.push(c);
+  }
+
   fn text_node_create(&mut self, node: RoNode) {
-    let offset_start = self.runtime.chars.len();
+    let offset_start = self.byte_offsets.len();
     let mut string = node.get_content();
     let mut offsets: Vec<i32> = if self.parameters.support_back_mapping {
       (0i32..(string.chars().count() as i32)).collect()
@@ -221,14 +333,16 @@ impl DNM {
 
     // string processing steps
     self.normalize_unicode(&mut string, &mut offsets);
-    self.stem_words(&mut string /* , &mut offsets */);
+    self.stem_words(&mut string, &mut offsets);
     if self.parameters.convert_to_lowercase {
       string = string.to_lowercase();
     }
     self.normalize_whitespace(&mut string, &mut offsets);
 
     // push results
-    self.runtime.chars.extend(string.chars());
+    for c in string.chars() {
+      self.push_char(c);
+    }
     if self.parameters.support_back_mapping {
       assert_eq!(string.chars().count(), offsets.len());
       for offset in offsets {
@@ -269,23 +383,57 @@ impl DNM {
   }
 
   fn normalize_unicode(&self, string: &mut String, offsets: &mut Vec<i32>) {
-    if !self.parameters.normalize_unicode {
+    let mode = self.parameters.unicode_normalization_mode();
+    if mode == UnicodeNormalization::None {
       return;
     }
     if !self.parameters.support_back_mapping {
-      *string = unidecode(string);
+      *string = mode.apply(string);
       return;
     }
 
-    // the tricky part: unidecode can replace a character by multiple characters.
-    // We need to maintain the offsets for back mapping
+    // the tricky part: normalization can replace a character by multiple characters, or merge
+    // several. We need to maintain the offsets for back mapping
+    let (new_string, new_offsets) = mode.apply_with_offsets(string, offsets);
+    *string = new_string;
+    *offsets = new_offsets;
+  }
+
+  fn stem_words(&self, string: &mut String, offsets: &mut Vec<i32>) {
+    if !self.parameters.stem_words_full && !self.parameters.stem_words_once {
+      return;
+    }
+    let back_mapping = self.parameters.support_back_mapping;
+
+    // Stem each whitespace-delimited token separately, with or without back-mapping, so that
+    // both produce the same plaintext. Morpha only rewrites word endings, so to maintain the
+    // offsets, the i-th stemmed char is mapped to the i-th original char of the token, and any
+    // surplus chars to the token's last char.
+    let chars: Vec<char> = string.chars().collect();
     let mut new_string = String::new();
     let mut new_offsets: Vec<i32> = Vec::new();
 
-    for (i, co) in string.chars().enumerate() {
-      for cn in unidecode_char(co).chars() {
-        new_string.push(cn);
-        new_offsets.push(offsets[i]);
+    let mut i = 0;
+    while i < chars.len() {
+      if chars[i].is_whitespace() {
+        new_string.push(chars[i]);
+        if back_mapping {
+          new_offsets.push(offsets[i]);
+        }
+        i += 1;
+        continue;
+      }
+      let token_start = i;
+      while i < chars.len() && !chars[i].is_whitespace() {
+        i += 1;
+      }
+      let token: String = chars[token_start..i].iter().collect();
+      let last = i - token_start - 1;
+      for (j, c) in self.stem_str(&token).trim().chars().enumerate() {
+        new_string.push(c);
+        if back_mapping {
+          new_offsets.push(offsets[token_start + cmp::min(j, last)]);
+        }
       }
     }
 
@@ -293,29 +441,28 @@ impl DNM {
     *offsets = new_offsets;
   }
 
-  fn stem_words(&self, string: &mut String /* , offsets : &mut Vec<i32> */) {
-    // TODO: Support back-mapping (using e.g. something like min. edit distance to
-    // map offsets)
-    if self.parameters.support_back_mapping
-      && (self.parameters.stem_words_full || self.parameters.stem_words_once)
-    {
-      panic!("llamapun::dnm: word stemming does not support back-mapping yet");
-    }
+  fn stem_str(&self, string: &str) -> String {
     if self.parameters.stem_words_full {
-      *string = rustmorpha::full_stem(string);
-    } else if self.parameters.stem_words_once {
-      *string = rustmorpha::stem(string);
+      rustmorpha::full_stem(string)
+    } else {
+      rustmorpha::stem(string)
     }
   }
 
   fn intermediate_node_create(&mut self, node: RoNode) {
-    let offset_start = self.runtime.chars.len();
+    let offset_start = self.byte_offsets.len();
     let name: String = node.get_name();
     {
       // Start scope of self.parameters borrow, to allow mutable self borrow for
       // recurse_node_create
       let mut rules = Vec::new();
-      // First class rules, as more specific
+      // Selector rules first, in their given order, as the most specific
+      for (selector, selector_rule) in &self.parameters.special_tag_selector_options {
+        if selector.matches(node) {
+          rules.push(Some(selector_rule));
+        }
+      }
+      // Then class rules, as more specific
       for classname in node.get_class_names() {
         let class_rule = self.parameters.special_tag_class_options.get(&classname);
         rules.push(class_rule);
@@ -19,6 +19,30 @@ pub fn lexematize_math(node: RoNode, context: &mut Context) -> String {
     .unwrap()
     .get_readonly_nodes_as_vec();
 
+  lexemes_from_annotations(&annotations)
+}
+
+/// Map math nodes to their lexemes, without requiring an XPath `Context`.
+/// Suitable for use inside a `SpecialTagsOption::FunctionNormalize` during DNM construction.
+pub fn lexematize_math_node(node: RoNode) -> String {
+  let mut annotations = Vec::new();
+  collect_lexeme_annotations(node, &mut annotations);
+  lexemes_from_annotations(&annotations)
+}
+
+/// Helper function: depth-first, document-ordered search for x-llamapun annotations
+fn collect_lexeme_annotations(node: RoNode, annotations: &mut Vec<RoNode>) {
+  for child in node.get_child_nodes() {
+    if child.get_name() == "annotation"
+      && child.get_property("encoding").as_deref() == Some("application/x-llamapun")
+    {
+      annotations.push(child);
+    }
+    collect_lexeme_annotations(child, annotations);
+  }
+}
+
+fn lexemes_from_annotations(annotations: &[RoNode]) -> String {
   let lexemes: String = annotations
     .iter()
     .map(|anno| {
@@ -0,0 +1,265 @@
+//! The `dnm::offsets` submodule provides a compact index of the byte offsets of the characters
+//! of a DNM's plaintext.
+//!
+//! Instead of a full `usize` per character, the index stores one byte per character, relative to
+//! the start of its block of `BLOCK_SIZE` characters, and a full offset per block. As UTF-8
+//! characters take at most 4 bytes, the relative offsets always fit into a byte.
+//!
+//! `DNMRange` offsets count characters (unicode code points). For consumers counting in other
+//! units, e.g. UTF-16 code units in JavaScript, an `OffsetIndex` converts between characters and
+//! bytes, UTF-16 code units or grapheme clusters.
+use crate::dnm::{DNMRange, DNM};
+use serde::{Deserialize, Serialize};
+use std::iter::FromIterator;
+use std::mem;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Number of characters sharing a full offset
+const BLOCK_SIZE: usize = 32;
+
+/// Compact index of byte offsets, one per plaintext character plus the total length at the end
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ByteOffsets {
+  /// byte offset of the first character of each block
+  blocks: Vec<usize>,
+  /// byte offset of each character, relative to its block
+  deltas: Vec<u8>,
+}
+
+impl ByteOffsets {
+  /// Create an empty index
+  pub fn new() -> Self { ByteOffsets::default() }
+
+  /// Append the byte offset of the next character. Offsets have to be non-decreasing, and
+  /// can't advance by more than 255 bytes within a block (which valid UTF-8 never does).
+  /// Panics otherwise, see `try_push` for a fallible version.
+  pub fn push(&mut self, offset: usize) {
+    self
+      .try_push(offset)
+      .expect("ByteOffsets: offsets must be increasing by at most 255 bytes per block");
+  }
+
+  /// Append the byte offset of the next character, failing (and leaving the index unchanged)
+  /// if it decreases or advances by more than 255 bytes within a block
+  pub fn try_push(&mut self, offset: usize) -> Result<(), String> {
+    let previous = self.len().checked_sub(1).map_or(0, |last| self.at(last));
+    let starts_block = self.deltas.len() % BLOCK_SIZE == 0;
+    let base = if starts_block {
+      offset
+    } else {
+      *self.blocks.last().unwrap()
+    };
+    match offset.checked_sub(base) {
+      Some(delta) if offset >= previous && delta <= usize::from(u8::MAX) => {
+        if starts_block {
+          self.blocks.push(offset);
+        }
+        self.deltas.push(delta as u8);
+        Ok(())
+      },
+      _ => Err(format!(
+        "ByteOffsets: offset {offset} at index {} decreases from {previous}, or advances by \
+         more than 255 bytes within its block",
+        self.len()
+      )),
+    }
+  }
+
+  /// Number of offsets in the index
+  pub fn len(&self) -> usize { self.deltas.len() }
+
+  /// Checks whether the index is empty
+  pub fn is_empty(&self) -> bool { self.deltas.is_empty() }
+
+  /// The `index`-th byte offset, if any
+  pub fn get(&self, index: usize) -> Option<usize> {
+    self
+      .deltas
+      .get(index)
+      .map(|delta| self.blocks[index / BLOCK_SIZE] + usize::from(*delta))
+  }
+
+  /// The `index`-th byte offset. Panics if out of bounds, like slice indexing.
+  pub fn at(&self, index: usize) -> usize {
+    match self.get(index) {
+      Some(offset) => offset,
+      None => panic!(
+        "ByteOffsets: index {index} out of bounds for length {}",
+        self.len()
+      ),
+    }
+  }
+
+  /// Iterate over all byte offsets
+  pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+    self
+      .deltas
+      .iter()
+      .enumerate()
+      .map(move |(index, delta)| self.blocks[index / BLOCK_SIZE] + usize::from(*delta))
+  }
+
+  /// All byte offsets as a `Vec`, the representation of `DNM::byte_offsets` before the compact
+  /// index
+  pub fn to_vec(&self) -> Vec<usize> { self.iter().collect() }
+
+  /// Number of heap-allocated bytes used by the index
+  pub fn heap_size(&self) -> usize {
+    self.blocks.capacity() * mem::size_of::<usize>() + self.deltas.capacity()
+  }
+
+  /// Release unused capacity
+  pub fn shrink_to_fit(&mut self) {
+    self.blocks.shrink_to_fit();
+    self.deltas.shrink_to_fit();
+  }
+}
+
+impl FromIterator<usize> for ByteOffsets {
+  fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+    let mut offsets = ByteOffsets::new();
+    for offset in iter {
+      offsets.push(offset);
+    }
+    offsets
+  }
+}
+
+impl TryFrom<Vec<usize>> for ByteOffsets {
+  type Error = String;
+  fn try_from(offsets: Vec<usize>) -> Result<Self, String> {
+    let mut compact = ByteOffsets::new();
+    for offset in offsets {
+      compact.try_push(offset)?;
+    }
+    compact.shrink_to_fit();
+    Ok(compact)
+  }
+}
+
+impl From<&ByteOffsets> for Vec<usize> {
+  fn from(offsets: &ByteOffsets) -> Self { offsets.to_vec() }
+}
+
+/// A unit for counting text offsets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffsetUnit {
+  /// Unicode code points, i.e. Rust `char`s (the unit of `DNMRange`, and of Python strings)
+  #[default]
+  Char,
+  /// UTF-8 bytes
+  Byte,
+  /// UTF-16 code units (the unit of JavaScript and Java strings)
+  Utf16,
+  /// Extended grapheme clusters, i.e. user-perceived characters
+  Grapheme,
+}
+
+/// Converts the character offsets of a text to and from another `OffsetUnit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetIndex {
+  /// the unit converted to
+  unit: OffsetUnit,
+  /// for each character (and the end of the text), the offset in `unit`. For grapheme clusters,
+  /// characters inside a cluster share the offset of the cluster.
+  offsets: Vec<usize>,
+}
+
+impl OffsetIndex {
+  /// Index the offsets of `text` in `unit`
+  pub fn new(text: &str, unit: OffsetUnit) -> Self {
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    match unit {
+      OffsetUnit::Char => offsets.extend(0..=text.chars().count()),
+      OffsetUnit::Byte => {
+        offsets.extend(text.char_indices().map(|(byte, _)| byte));
+        offsets.push(text.len());
+      },
+      OffsetUnit::Utf16 => {
+        let mut units = 0;
+        for c in text.chars() {
+          offsets.push(units);
+          units += c.len_utf16();
+        }
+        offsets.push(units);
+      },
+      OffsetUnit::Grapheme => {
+        let mut count = 0;
+        for (index, grapheme) in text.graphemes(true).enumerate() {
+          offsets.resize(offsets.len() + grapheme.chars().count(), index);
+          count = index + 1;
+        }
+        offsets.push(count);
+      },
+    }
+    OffsetIndex { unit, offsets }
+  }
+
+  /// The unit converted to
+  pub fn unit(&self) -> OffsetUnit { self.unit }
+
+  /// The length of the text in characters
+  pub fn char_len(&self) -> usize { self.offsets.len() - 1 }
+
+  /// The offset in the index's unit of a character offset. `None` if out of bounds, or inside a
+  /// grapheme cluster.
+  pub fn offset(&self, char_offset: usize) -> Option<usize> {
+    let offset = *self.offsets.get(char_offset)?;
+    if char_offset > 0 && self.offsets[char_offset - 1] == offset {
+      return None;
+    }
+    Some(offset)
+  }
+
+  /// The character offset of an offset in the index's unit. `None` if out of bounds, or inside a
+  /// character (e.g. between the bytes of a character, or the surrogates of a UTF-16 pair).
+  pub fn char_offset(&self, offset: usize) -> Option<usize> {
+    let char_offset = self.offsets.partition_point(|existing| *existing < offset);
+    if self.offsets.get(char_offset) == Some(&offset) {
+      Some(char_offset)
+    } else {
+      None
+    }
+  }
+}
+
+impl DNM {
+  /// An index converting the character offsets of the plaintext to and from `unit`. Building it
+  /// takes a pass over the plaintext, so it is best built once and reused across ranges, e.g. via
+  /// `DNMRange::offsets_with` and `DNMRange::from_offsets`.
+  pub fn offset_index(&self, unit: OffsetUnit) -> OffsetIndex {
+    OffsetIndex::new(&self.plaintext, unit)
+  }
+}
+
+impl<'dnmrange> DNMRange<'dnmrange> {
+  /// The start and end offsets of the range, via an `OffsetIndex` of the DNM's plaintext (to be
+  /// reused across ranges)
+  pub fn offsets_with(&self, index: &OffsetIndex) -> Result<(usize, usize), String> {
+    match (index.offset(self.start), index.offset(self.end)) {
+      (Some(start), Some(end)) => Ok((start, end)),
+      _ => Err(format!(
+        "DNMRange {}..{} has no {:?} offsets",
+        self.start, self.end, index.unit
+      )),
+    }
+  }
+
+  /// The range between the `start` and `end` offsets of `dnm`'s plaintext, via an `OffsetIndex`
+  /// of the plaintext
+  pub fn from_offsets(
+    dnm: &'dnmrange DNM,
+    start: usize,
+    end: usize,
+    index: &OffsetIndex,
+  ) -> Result<DNMRange<'dnmrange>, String> {
+    match (index.char_offset(start), index.char_offset(end)) {
+      (Some(start), Some(end)) if start <= end => Ok(DNMRange { start, end, dnm }),
+      _ => Err(format!(
+        "{start}..{end} is not a range of {:?} offsets of the DNM",
+        index.unit
+      )),
+    }
+  }
+}
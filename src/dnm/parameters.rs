@@ -1,8 +1,12 @@
 //! The `dnm::parameters` submodule provides data structures for customizing
 //! and configuring a DNM's construction and use
 
+use crate::dnm::selector::TagSelector;
+use crate::dnm::unicode::UnicodeNormalization;
 use libxml::readonly::RoNode;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
 use std::fmt;
 use std::sync::Arc;
 
@@ -11,15 +15,11 @@ use std::sync::Arc;
 pub struct RuntimeParseData {
   /// plaintext is currently terminated by some whitespace
   pub had_whitespace: bool,
-  /// plaintext representation as vector of chars (to deal with UTF-8 mess)
-  /// TODO: Use plaintext/byte_offsets directly instead
-  pub chars: Vec<char>,
 }
 impl Default for RuntimeParseData {
   fn default() -> RuntimeParseData {
     RuntimeParseData {
       had_whitespace: true, // skip leading whitespace
-      chars: Vec::new(),
     }
   }
 }
@@ -31,12 +31,21 @@ pub enum SpecialTagsOption {
   Enter,
   /// Normalize tag, replacing it by some token
   Normalize(String),
-  /// Normalize tag, obtain replacement string by function call
-  FunctionNormalize(Arc<fn(RoNode) -> String>),
+  /// Normalize tag, obtain replacement string by function call.
+  /// The closure may capture state (e.g. dictionaries, counters), as long as it is thread-safe.
+  FunctionNormalize(Arc<dyn Fn(RoNode) -> String + Send + Sync>),
   /// Skip tag
   Skip,
 }
 
+impl SpecialTagsOption {
+  /// Convenience constructor for a `FunctionNormalize` rule from any suitable closure
+  pub fn function_normalize<F>(normalizer: F) -> SpecialTagsOption
+  where F: Fn(RoNode) -> String + Send + Sync + 'static {
+    SpecialTagsOption::FunctionNormalize(Arc::new(normalizer))
+  }
+}
+
 impl fmt::Debug for SpecialTagsOption {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     use SpecialTagsOption::*;
@@ -51,6 +60,42 @@ impl fmt::Debug for SpecialTagsOption {
   }
 }
 
+/// Block-level elements which are treated as boundaries in the plaintext, independently of the
+/// whitespace in the source document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockBoundaries {
+  /// Names of the block-level elements
+  pub elements: BTreeSet<String>,
+  /// Separator emitted before and after each block-level element (consecutive separators are
+  /// collapsed, e.g. for nested blocks). It is mapped to the element in the `back_map`.
+  pub separator: String,
+}
+
+impl Default for BlockBoundaries {
+  /// `div`, `p`, `h1`-`h6`, `li`, `figcaption` and `td`, separated by an empty line
+  fn default() -> BlockBoundaries {
+    BlockBoundaries {
+      elements: [
+        "div",
+        "p",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "li",
+        "figcaption",
+        "td",
+      ]
+      .iter()
+      .map(|name| name.to_string())
+      .collect(),
+      separator: "\n\n".to_string(),
+    }
+  }
+}
+
 /// Parameters for the DNM generation
 #[derive(Debug, Clone)]
 pub struct DNMParameters {
@@ -60,13 +105,22 @@ pub struct DNMParameters {
   /// *Remark*: If both a tag name and a tag class match, the tag name rule
   /// will be applied.
   pub special_tag_class_options: HashMap<String, SpecialTagsOption>,
+  /// Ordered selector-keyed rules (e.g. `figcaption span.ltx_tag`, `a[href^='#bib']`).
+  /// They are tried first, in order, and the first matching rule wins over any
+  /// class or tag name rule.
+  pub special_tag_selector_options: Vec<(TagSelector, SpecialTagsOption)>,
   /// merge sequences of whitespaces into a single ' '.
   /// *Doesn't affect tokens*
   pub normalize_white_spaces: bool,
   /// put spaces before and after tokens
   pub wrap_tokens: bool,
-  /// Replace unicode characters by the ascii code representation
+  /// emit separators around block-level elements, e.g. for paragraph-aware sentence splitting
+  pub block_boundaries: Option<BlockBoundaries>,
+  /// Replace unicode characters by the ascii code representation.
+  /// Shorthand for `unicode_normalization: UnicodeNormalization::Ascii`, which it overrides
   pub normalize_unicode: bool,
+  /// How to normalize unicode characters (NFC, NFKC, ASCII transliteration, ...)
+  pub unicode_normalization: UnicodeNormalization,
   /// Apply the morpha stemmer once to the text nodes
   pub stem_words_once: bool,
   /// Apply the morpha stemmer to the text nodes
@@ -84,9 +138,12 @@ impl Default for DNMParameters {
     DNMParameters {
       special_tag_name_options: HashMap::new(),
       special_tag_class_options: HashMap::new(),
+      special_tag_selector_options: Vec::new(),
       normalize_white_spaces: true,
       wrap_tokens: false,
+      block_boundaries: None,
       normalize_unicode: false,
+      unicode_normalization: UnicodeNormalization::None,
       stem_words_once: false,
       stem_words_full: false,
       convert_to_lowercase: false,
@@ -151,26 +208,135 @@ impl DNMParameters {
     }
   }
 
-  /// Prints warnings, if the parameter settings don't make sense.
+  /// `llamapun_normalization`, but discarding math instead of normalizing it to "mathformula"
+  pub fn discard_math() -> DNMParameters {
+    let mut parameters = DNMParameters::llamapun_normalization();
+    parameters
+      .special_tag_name_options
+      .insert("math".to_string(), SpecialTagsOption::Skip);
+    parameters
+      .special_tag_class_options
+      .insert("ltx_equation".to_string(), SpecialTagsOption::Skip);
+    parameters
+      .special_tag_class_options
+      .insert("ltx_equationgroup".to_string(), SpecialTagsOption::Skip);
+    parameters
+  }
+
+  /// For canonical heading text: `discard_math`, also discarding citations and references
+  pub fn headings() -> DNMParameters {
+    let mut parameters = DNMParameters::discard_math();
+    parameters
+      .special_tag_name_options
+      .insert("cite".to_string(), SpecialTagsOption::Skip);
+    parameters
+      .special_tag_class_options
+      .insert("ltx_ref".to_string(), SpecialTagsOption::Skip);
+    parameters
+  }
+
+  /// Get a named preset: "default", "llamapun_normalization", "discard_math" or "headings"
+  pub fn preset(name: &str) -> Result<DNMParameters, String> {
+    match name {
+      "default" => Ok(DNMParameters::default()),
+      "llamapun_normalization" => Ok(DNMParameters::llamapun_normalization()),
+      "discard_math" => Ok(DNMParameters::discard_math()),
+      "headings" => Ok(DNMParameters::headings()),
+      _ => Err(format!("Unknown DNMParameters preset \"{name}\"")),
+    }
+  }
+
+  /// The unicode normalization mode in effect, taking `normalize_unicode` into account
+  pub fn unicode_normalization_mode(&self) -> UnicodeNormalization {
+    if self.normalize_unicode {
+      UnicodeNormalization::Ascii
+    } else {
+      self.unicode_normalization
+    }
+  }
+
+  /// Validates the parameter settings, reporting every conflict found.
   /// Doesn't check for every possible stupidity
-  pub fn check(&self) {
+  pub fn check(&self) -> Result<(), ParameterError> {
+    let mut conflicts = Vec::new();
     if self.stem_words_once && self.stem_words_full {
-      dbg!(
-        "llamapun::dnm: Parameter options stem_words_once\
-         and stem_words_full are both set"
-      );
+      conflicts.push(ParameterConflict::ConflictingStemming);
     }
     if (self.stem_words_once || self.stem_words_full) && self.convert_to_lowercase {
-      dbg!(
-        "llamapun::dnm: Parameter option convert_to_lowercase\
-         is redundant, because stemming converts to lowercase already"
-      );
+      conflicts.push(ParameterConflict::RedundantLowercase);
     }
-    if (self.stem_words_once || self.stem_words_full) && self.support_back_mapping {
-      dbg!(
-        "llamapun::dnm: Parameter option support_back_mapping\
-         does not work in combination with word stemming yet"
-      );
+    if self.normalize_unicode
+      && !matches!(
+        self.unicode_normalization,
+        UnicodeNormalization::None | UnicodeNormalization::Ascii
+      )
+    {
+      conflicts.push(ParameterConflict::ConflictingUnicodeNormalization(
+        self.unicode_normalization,
+      ));
+    }
+    if conflicts.is_empty() {
+      Ok(())
+    } else {
+      Err(ParameterError { conflicts })
     }
   }
 }
+
+/// A conflict between `DNMParameters` settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterConflict {
+  /// `stem_words_once` and `stem_words_full` are both set
+  ConflictingStemming,
+  /// `convert_to_lowercase` is redundant, because stemming converts to lowercase already
+  RedundantLowercase,
+  /// `normalize_unicode` overrides the given `unicode_normalization` mode with ASCII
+  /// transliteration
+  ConflictingUnicodeNormalization(UnicodeNormalization),
+}
+
+impl ParameterConflict {
+  /// Whether the conflict is harmless and only worth a warning. `DNM::try_new` accepts
+  /// parameters whose conflicts are all warnings.
+  pub fn is_warning(&self) -> bool { matches!(self, ParameterConflict::RedundantLowercase) }
+}
+
+impl fmt::Display for ParameterConflict {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    use ParameterConflict::*;
+    match self {
+      ConflictingStemming => write!(f, "stem_words_once and stem_words_full are both set"),
+      RedundantLowercase => write!(
+        f,
+        "convert_to_lowercase is redundant, because stemming converts to lowercase already"
+      ),
+      ConflictingUnicodeNormalization(mode) => write!(
+        f,
+        "normalize_unicode overrides the unicode_normalization mode {mode:?} with ASCII \
+         transliteration"
+      ),
+    }
+  }
+}
+
+/// Invalid `DNMParameters`, with every conflict found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterError {
+  /// The conflicts, in the order they were checked
+  pub conflicts: Vec<ParameterConflict>,
+}
+
+impl fmt::Display for ParameterError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "invalid DNM parameters: ")?;
+    for (index, conflict) in self.conflicts.iter().enumerate() {
+      if index > 0 {
+        write!(f, "; ")?;
+      }
+      write!(f, "{conflict}")?;
+    }
+    Ok(())
+  }
+}
+
+impl Error for ParameterError {}
@@ -3,7 +3,9 @@
 
 use crate::dnm::DNM;
 use libxml::readonly::RoNode;
-use libxml::xpath::Context;
+use std::cmp::{self, Ordering};
+use std::collections::HashSet;
+use std::ptr;
 
 /// Very often we'll talk about substrings of the plaintext - words, sentences,
 /// etc. A `DNMRange` stores start and end point of such a substring and has
@@ -28,10 +30,69 @@ impl<'dnmrange> Clone for DNMRange<'dnmrange> {
   }
 }
 
+/// Ranges are equal when they cover the same offsets of the same `DNM`
+impl<'dnmrange> PartialEq for DNMRange<'dnmrange> {
+  fn eq(&self, other: &DNMRange<'dnmrange>) -> bool {
+    self.start == other.start && self.end == other.end && ptr::eq(self.dnm, other.dnm)
+  }
+}
+impl<'dnmrange> Eq for DNMRange<'dnmrange> {}
+
+/// Ranges are ordered by their start, then by their end offset (ties between different `DNM`s
+/// are broken by address, to keep the order total)
+impl<'dnmrange> Ord for DNMRange<'dnmrange> {
+  fn cmp(&self, other: &DNMRange<'dnmrange>) -> Ordering {
+    let (self_dnm, other_dnm): (*const DNM, *const DNM) = (self.dnm, other.dnm);
+    (self.start, self.end)
+      .cmp(&(other.start, other.end))
+      .then_with(|| self_dnm.cmp(&other_dnm))
+  }
+}
+impl<'dnmrange> PartialOrd for DNMRange<'dnmrange> {
+  fn partial_cmp(&self, other: &DNMRange<'dnmrange>) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+/// Iterator over the distinct DOM nodes a `DNMRange` covers, in order of their first occurrence
+/// in the plaintext: the back-mapped leaves (text nodes and normalized elements), each preceded
+/// by its ancestors below the DNM's root node that weren't yielded yet
+pub struct DNMRangeNodes<'dnmrange> {
+  /// the back-mapping entries of the range
+  walker: std::slice::Iter<'dnmrange, (RoNode, i32)>,
+  /// the root node of the DNM, where the ancestors stop
+  root_node: RoNode,
+  /// a leaf and its new ancestors, deepest first, waiting to be yielded
+  pending: Vec<RoNode>,
+  /// the nodes yielded so far
+  seen: HashSet<usize>,
+}
+
+impl<'dnmrange> Iterator for DNMRangeNodes<'dnmrange> {
+  type Item = RoNode;
+  fn next(&mut self) -> Option<RoNode> {
+    if let Some(node) = self.pending.pop() {
+      return Some(node);
+    }
+    for &(leaf, _) in self.walker.by_ref() {
+      let mut node = Some(leaf);
+      while let Some(current) = node {
+        if current == self.root_node || !self.seen.insert(current.to_hashable()) {
+          break;
+        }
+        self.pending.push(current);
+        node = current.get_parent();
+      }
+      if let Some(node) = self.pending.pop() {
+        return Some(node);
+      }
+    }
+    None
+  }
+}
+
 impl<'dnmrange> DNMRange<'dnmrange> {
   /// Get the plaintext substring corresponding to the range
   pub fn get_plaintext(&self) -> &'dnmrange str {
-    &(self.dnm.plaintext)[self.dnm.byte_offsets[self.start]..self.dnm.byte_offsets[self.end]]
+    &(self.dnm.plaintext)[self.dnm.byte_offsets.at(self.start)..self.dnm.byte_offsets.at(self.end)]
   }
   /// Get the plaintext without trailing white spaces
   pub fn get_plaintext_truncated(&self) -> &'dnmrange str { self.get_plaintext().trim_end() }
@@ -47,14 +108,14 @@ impl<'dnmrange> DNMRange<'dnmrange> {
 
     for c in range_text.chars() {
       if c.is_whitespace() {
-        trimmed_start += c.len_utf8();
+        trimmed_start += 1;
       } else {
         break;
       }
     }
     for c in range_text.chars().rev() {
       if c.is_whitespace() {
-        trimmed_end -= c.len_utf8();
+        trimmed_end -= 1;
       } else {
         break;
       }
@@ -91,12 +152,12 @@ impl<'dnmrange> DNMRange<'dnmrange> {
   ) -> DNMRange<'dnmrange> {
     DNMRange {
       start: self.byte_offset_bisection(
-        self.dnm.byte_offsets[self.start] + rel_start,
+        self.dnm.byte_offsets.at(self.start) + rel_start,
         self.start,
         self.end,
       ),
       end: self.byte_offset_bisection(
-        self.dnm.byte_offsets[self.start] + rel_end - 1,
+        self.dnm.byte_offsets.at(self.start) + rel_end - 1,
         self.start,
         self.end,
       ) + 1,
@@ -113,7 +174,7 @@ impl<'dnmrange> DNMRange<'dnmrange> {
     if lower_char == upper_char {
       return lower_char;
     } else if upper_char == lower_char + 1 {
-      if self.dnm.byte_offsets[upper_char] <= target_byte {
+      if self.dnm.byte_offsets.at(upper_char) <= target_byte {
         return upper_char;
       } else {
         return lower_char;
@@ -121,7 +182,7 @@ impl<'dnmrange> DNMRange<'dnmrange> {
     }
 
     let middle_char = (lower_char + upper_char) / 2;
-    if self.dnm.byte_offsets[middle_char] > target_byte {
+    if self.dnm.byte_offsets.at(middle_char) > target_byte {
       self.byte_offset_bisection(target_byte, lower_char, middle_char)
     } else {
       self.byte_offset_bisection(target_byte, middle_char, upper_char)
@@ -131,6 +192,131 @@ impl<'dnmrange> DNMRange<'dnmrange> {
   /// checks whether the range is empty
   pub fn is_empty(&self) -> bool { self.start == self.end }
 
+  /*
+   * RANGE ALGEBRA
+   */
+
+  /// checks whether both ranges index into the same `DNM`
+  pub fn same_dnm(&self, other: &DNMRange) -> bool { ptr::eq(self.dnm, other.dnm) }
+
+  /// checks whether `other` lies entirely within `self`
+  pub fn contains(&self, other: &DNMRange) -> bool {
+    self.same_dnm(other) && self.start <= other.start && other.end <= self.end
+  }
+
+  /// checks whether the plaintext offset `offset` lies within the range
+  pub fn contains_offset(&self, offset: usize) -> bool { self.start <= offset && offset < self.end }
+
+  /// checks whether the two ranges share at least one character
+  pub fn overlaps(&self, other: &DNMRange) -> bool {
+    self.same_dnm(other) && self.start < other.end && other.start < self.end
+  }
+
+  /// returns the range shared by both ranges, if they overlap
+  pub fn intersect(&self, other: &DNMRange) -> Option<DNMRange<'dnmrange>> {
+    if self.overlaps(other) {
+      Some(DNMRange {
+        start: cmp::max(self.start, other.start),
+        end: cmp::min(self.end, other.end),
+        dnm: self.dnm,
+      })
+    } else {
+      None
+    }
+  }
+
+  /// returns the union of both ranges, if it is itself a range (i.e. they overlap or touch)
+  pub fn union(&self, other: &DNMRange) -> Option<DNMRange<'dnmrange>> {
+    if self.same_dnm(other) && self.start <= other.end && other.start <= self.end {
+      self.cover(other).ok()
+    } else {
+      None
+    }
+  }
+
+  /// returns the smallest range covering both ranges, including any gap between them. Fails if
+  /// the ranges index into different `DNM`s.
+  pub fn cover(&self, other: &DNMRange) -> Result<DNMRange<'dnmrange>, String> {
+    if !self.same_dnm(other) {
+      return Err("DNMRange::cover: ranges index into different DNMs".to_string());
+    }
+    Ok(DNMRange {
+      start: cmp::min(self.start, other.start),
+      end: cmp::max(self.end, other.end),
+      dnm: self.dnm,
+    })
+  }
+
+  /// Returns the range extended at both ends to the boundaries of any word it cuts through
+  pub fn expand_to_word(&self) -> DNMRange<'dnmrange> {
+    let char_count = self.dnm.byte_offsets.len().saturating_sub(1);
+    let is_word_char = |offset: usize| self.char_at(offset).is_some_and(char::is_alphanumeric);
+    let mut start = self.start;
+    if is_word_char(start) {
+      while start > 0 && is_word_char(start - 1) {
+        start -= 1;
+      }
+    }
+    let mut end = self.end;
+    if end > 0 && is_word_char(end - 1) {
+      while end < char_count && is_word_char(end) {
+        end += 1;
+      }
+    }
+    DNMRange {
+      start,
+      end,
+      dnm: self.dnm,
+    }
+  }
+
+  /// Returns the range extended to the sentence(s) it touches, among the `sentences` of its
+  /// `DNM` in document order (as returned by `Segmenter::sentences`), which are computed once
+  /// and reused across ranges. If the range lies between sentences, it is returned unchanged.
+  pub fn expand_to_sentence(&self, sentences: &[DNMRange]) -> DNMRange<'dnmrange> {
+    let probe = DNMRange {
+      start: self.start,
+      end: cmp::max(self.end, self.start + 1),
+      dnm: self.dnm,
+    };
+    let first = sentences.partition_point(|sentence| sentence.end <= probe.start);
+    let mut expanded = self.clone();
+    for sentence in sentences[first..]
+      .iter()
+      .take_while(|sentence| sentence.start < probe.end)
+      .filter(|sentence| sentence.overlaps(&probe))
+    {
+      expanded.start = cmp::min(expanded.start, sentence.start);
+      expanded.end = cmp::max(expanded.end, sentence.end);
+    }
+    expanded
+  }
+
+  /// Get an iterator over the distinct DOM nodes covered by this range, in order of their first
+  /// occurrence: the text nodes and normalized elements of the range, and the elements they are
+  /// nested in (up to the DNM's root node, which is never included). Elements are yielded before
+  /// their descendants, even if the range only covers part of their content. Empty if the DNM
+  /// has no back-mapping support.
+  pub fn nodes(&self) -> DNMRangeNodes<'dnmrange> {
+    let back_map = if self.dnm.parameters.support_back_mapping {
+      &self.dnm.back_map[self.start..self.end]
+    } else {
+      &[]
+    };
+    DNMRangeNodes {
+      walker: back_map.iter(),
+      root_node: self.dnm.root_node,
+      pending: Vec::new(),
+      seen: HashSet::new(),
+    }
+  }
+
+  /// Helper function: the character at a plaintext offset
+  fn char_at(&self, offset: usize) -> Option<char> {
+    let byte_start = self.dnm.byte_offsets.get(offset)?;
+    self.dnm.plaintext[byte_start..].chars().next()
+  }
+
   /*
    * SERIALIZATION CODE
    */
@@ -211,92 +397,19 @@ impl<'dnmrange> DNMRange<'dnmrange> {
       Some(x) => format!("//*[@id=\"{x}\"]"),
     }
   }
-
-  /*
-   * DESERIALIZATION CODE
-   */
-
-  /// deserializes an xpointer into a `DNMRange`. Note that only a very limited subset of xpointers
-  /// is supported. Essentially, you should not use it for deserialization of xpointers generated by
-  /// any other tool. (TODO: Support a wider range of xpointers)
-  pub fn deserialize(
-    string: &str,
-    dnm: &'dnmrange DNM,
-    xpath_context: &Context,
-  ) -> DNMRange<'dnmrange> {
-    assert_eq!(&(string[0..7]), "arange(");
-    assert_eq!(&(string[string.len() - 1..string.len()]), ")");
-
-    let main_comma = 1
-      + string.find("),").unwrap_or_else(|| {
-        string
-          .find("],")
-          .unwrap_or_else(|| panic!("DNMRange::deserialize: Malformed string: \"{string}\""))
-      });
-
-    let start_str = &string[7..main_comma];
-    let end_str = &string[main_comma + 1..string.len() - 1];
-
-    let start = DNMRange::xpointer_to_offset(start_str, dnm, xpath_context);
-    let end = DNMRange::xpointer_to_offset(end_str, dnm, xpath_context);
-
-    DNMRange { start, end, dnm }
-  }
-
-  /// Gets the plaintext offset corresponding to an XPath/string-index'ed XPointer,
-  /// again, does not cover everything!
-  fn xpointer_to_offset(string: &str, dnm: &'dnmrange DNM, xpath_context: &Context) -> usize {
-    if string.len() > 13 && &(string[0..13]) == "string-index(" {
-      let comma = string
-        .find(',')
-        .unwrap_or_else(|| panic!("DNM::deserialize_part: Malformed string: \"{string}\""));
-      let node_str = &string[13..comma];
-      let node_set = xpath_context.evaluate(node_str).unwrap();
-      assert_eq!(node_set.get_number_of_nodes(), 1);
-      let node = node_set.get_readonly_nodes_as_vec()[0];
-      match dnm.get_range_of_node(node) {
-        Ok(range) => {
-          let mut pos = range.start;
-          let offset = &string[comma + 1..string.len() - 1].parse::<i32>().unwrap() - 1;
-          while pos < range.end && dnm.back_map[pos].1 < offset {
-            pos += 1;
-          }
-          pos
-        },
-        Err(_) => get_position_of_lowest_parent(node, dnm),
-      }
-    } else {
-      let node_str = string;
-      let node_set = xpath_context
-        .evaluate(node_str)
-        .unwrap_or_else(|_| panic!("DNMRange::deserialize: Malformed XPath: '{}'", &node_str));
-      assert_eq!(node_set.get_number_of_nodes(), 1);
-      let node = node_set.get_readonly_nodes_as_vec()[0];
-      get_position_of_lowest_parent(node, dnm)
-    }
-  }
 }
 
 /*
- * (DE)?SERIALIZATION HELPER FUNCTIONS
+ * SERIALIZATION HELPER FUNCTIONS
  */
 
-/// Helper function: Gets the start offset of the lowest parent recorded in the
-/// DNM
-fn get_position_of_lowest_parent(node: RoNode, dnm: &DNM) -> usize {
-  match dnm.get_range_of_node(node) {
-    Ok(range) => range.start,
-    Err(_) => get_position_of_lowest_parent(node.get_parent().unwrap(), dnm),
-  }
-}
-
 /// Helper function: Returns the next sibling of a node if it exists
 /// (goes up in the tree if required)
 fn get_next_sibling(root_node: RoNode, node: RoNode) -> Option<RoNode> {
   match node.get_next_sibling() {
     None => {
       if node == root_node {
-        dbg!("DNMRange::serialize: Warning: Can't annotate last node in document properly");
+        log::warn!("DNMRange::serialize: Can't annotate last node in document properly");
         None
       } else {
         get_next_sibling(root_node, node.get_parent().unwrap())
@@ -0,0 +1,272 @@
+//! The `dnm::selector` submodule provides a compact, CSS-like selector syntax for keying
+//! `SpecialTagsOption` rules on more than an element name or a single class.
+//!
+//! Supported syntax:
+//!  - element names (`span`) or the universal selector (`*`)
+//!  - classes (`.ltx_tag`), any number of them
+//!  - attribute conditions: `[href]`, `[href='#x']`, `[href^='#bib']`, `[href$='.pdf']`,
+//!    `[href*='bib']`
+//!  - descendant (`figcaption span`) and child (`figcaption > span`) combinators
+use libxml::readonly::RoNode;
+use libxml::tree::NodeType;
+use std::fmt;
+
+/// A condition on the value of an attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AttributeMatch {
+  /// `[name]`
+  Exists,
+  /// `[name='value']`
+  Equals(String),
+  /// `[name^='value']`
+  Prefix(String),
+  /// `[name$='value']`
+  Suffix(String),
+  /// `[name*='value']`
+  Contains(String),
+}
+
+/// How a compound selector relates to the one on its left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+  /// an ancestor at any depth
+  Descendant,
+  /// the immediate parent
+  Child,
+}
+
+/// A selector for a single element, e.g. `span.ltx_tag[title]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompoundSelector {
+  /// relation to the previous compound, irrelevant for the leftmost one
+  combinator: Combinator,
+  /// element name, `None` for `*`
+  name: Option<String>,
+  /// required class names
+  classes: Vec<String>,
+  /// required attribute conditions
+  attributes: Vec<(String, AttributeMatch)>,
+}
+
+/// A parsed selector that can be tested against DOM nodes during DNM construction
+#[derive(Clone, PartialEq, Eq)]
+pub struct TagSelector {
+  /// the original selector string
+  source: String,
+  /// the compound selectors, outermost first
+  compounds: Vec<CompoundSelector>,
+}
+
+impl fmt::Debug for TagSelector {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "TagSelector({:?})", self.source)
+  }
+}
+
+impl fmt::Display for TagSelector {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.source) }
+}
+
+impl TagSelector {
+  /// Parses a selector string, e.g. `"figcaption span.ltx_tag"` or `"a[href^='#bib']"`
+  pub fn parse(selector: &str) -> Result<TagSelector, String> {
+    let chars: Vec<char> = selector.chars().collect();
+    let mut pos = 0;
+    let mut compounds = Vec::new();
+    let mut combinator = Combinator::Descendant;
+
+    loop {
+      skip_whitespace(&chars, &mut pos);
+      if pos >= chars.len() {
+        break;
+      }
+      if chars[pos] == '>' {
+        if compounds.is_empty() || combinator == Combinator::Child {
+          return Err(format!("Misplaced '>' in selector \"{selector}\""));
+        }
+        combinator = Combinator::Child;
+        pos += 1;
+        continue;
+      }
+      let mut compound =
+        parse_compound(&chars, &mut pos).map_err(|e| format!("{e} in selector \"{selector}\""))?;
+      compound.combinator = combinator;
+      compounds.push(compound);
+      combinator = Combinator::Descendant;
+    }
+
+    if compounds.is_empty() {
+      return Err(format!("Empty selector \"{selector}\""));
+    }
+    if combinator == Combinator::Child {
+      return Err(format!("Dangling '>' in selector \"{selector}\""));
+    }
+    Ok(TagSelector {
+      source: selector.to_string(),
+      compounds,
+    })
+  }
+
+  /// Checks whether `node` is matched by the selector
+  pub fn matches(&self, node: RoNode) -> bool { self.matches_at(node, self.compounds.len() - 1) }
+
+  fn matches_at(&self, node: RoNode, index: usize) -> bool {
+    let compound = &self.compounds[index];
+    if !compound.matches(node) {
+      return false;
+    }
+    if index == 0 {
+      return true;
+    }
+    match compound.combinator {
+      Combinator::Child => match node.get_parent() {
+        Some(parent) => self.matches_at(parent, index - 1),
+        None => false,
+      },
+      Combinator::Descendant => {
+        let mut ancestor = node.get_parent();
+        while let Some(candidate) = ancestor {
+          if self.matches_at(candidate, index - 1) {
+            return true;
+          }
+          ancestor = candidate.get_parent();
+        }
+        false
+      },
+    }
+  }
+}
+
+impl CompoundSelector {
+  fn matches(&self, node: RoNode) -> bool {
+    if node.get_type() != Some(NodeType::ElementNode) {
+      return false;
+    }
+    if let Some(ref name) = self.name {
+      if &node.get_name() != name {
+        return false;
+      }
+    }
+    if !self.classes.is_empty() {
+      let node_classes = node.get_class_names();
+      if !self.classes.iter().all(|c| node_classes.contains(c)) {
+        return false;
+      }
+    }
+    self.attributes.iter().all(
+      |(attr, condition)| match (node.get_property(attr), condition) {
+        (None, _) => false,
+        (Some(_), AttributeMatch::Exists) => true,
+        (Some(v), AttributeMatch::Equals(e)) => &v == e,
+        (Some(v), AttributeMatch::Prefix(p)) => v.starts_with(p.as_str()),
+        (Some(v), AttributeMatch::Suffix(s)) => v.ends_with(s.as_str()),
+        (Some(v), AttributeMatch::Contains(c)) => v.contains(c.as_str()),
+      },
+    )
+  }
+}
+
+/*
+ * PARSING HELPERS
+ */
+
+fn skip_whitespace(chars: &[char], pos: &mut usize) {
+  while *pos < chars.len() && chars[*pos].is_whitespace() {
+    *pos += 1;
+  }
+}
+
+fn is_ident_char(c: char) -> bool { c.is_alphanumeric() || c == '-' || c == '_' || c == ':' }
+
+fn parse_ident(chars: &[char], pos: &mut usize) -> Result<String, String> {
+  let start = *pos;
+  while *pos < chars.len() && is_ident_char(chars[*pos]) {
+    *pos += 1;
+  }
+  if start == *pos {
+    Err(format!("Expected a name at position {start}"))
+  } else {
+    Ok(chars[start..*pos].iter().collect())
+  }
+}
+
+fn parse_compound(chars: &[char], pos: &mut usize) -> Result<CompoundSelector, String> {
+  let mut compound = CompoundSelector {
+    combinator: Combinator::Descendant,
+    name: None,
+    classes: Vec::new(),
+    attributes: Vec::new(),
+  };
+  if chars[*pos] == '*' {
+    *pos += 1;
+  } else if is_ident_char(chars[*pos]) {
+    compound.name = Some(parse_ident(chars, pos)?);
+  }
+
+  while *pos < chars.len() {
+    match chars[*pos] {
+      '.' => {
+        *pos += 1;
+        compound.classes.push(parse_ident(chars, pos)?);
+      },
+      '[' => {
+        *pos += 1;
+        compound.attributes.push(parse_attribute(chars, pos)?);
+      },
+      c if c.is_whitespace() || c == '>' => break,
+      c => return Err(format!("Unexpected character '{c}' at position {}", *pos)),
+    }
+  }
+  Ok(compound)
+}
+
+fn parse_attribute(chars: &[char], pos: &mut usize) -> Result<(String, AttributeMatch), String> {
+  skip_whitespace(chars, pos);
+  let name = parse_ident(chars, pos)?;
+  skip_whitespace(chars, pos);
+  let operator = match chars.get(*pos) {
+    Some(']') => {
+      *pos += 1;
+      return Ok((name, AttributeMatch::Exists));
+    },
+    Some('=') => {
+      *pos += 1;
+      '='
+    },
+    Some(&op) if "^$*".contains(op) && chars.get(*pos + 1) == Some(&'=') => {
+      *pos += 2;
+      op
+    },
+    _ => return Err(format!("Malformed attribute condition for \"{name}\"")),
+  };
+  skip_whitespace(chars, pos);
+  let value = match chars.get(*pos) {
+    Some(&quote) if quote == '\'' || quote == '"' => {
+      *pos += 1;
+      let start = *pos;
+      while *pos < chars.len() && chars[*pos] != quote {
+        *pos += 1;
+      }
+      if *pos >= chars.len() {
+        return Err(format!("Unterminated value for attribute \"{name}\""));
+      }
+      let value: String = chars[start..*pos].iter().collect();
+      *pos += 1;
+      value
+    },
+    _ => parse_ident(chars, pos)?,
+  };
+  skip_whitespace(chars, pos);
+  if chars.get(*pos) != Some(&']') {
+    return Err(format!("Missing ']' for attribute \"{name}\""));
+  }
+  *pos += 1;
+
+  let condition = match operator {
+    '^' => AttributeMatch::Prefix(value),
+    '$' => AttributeMatch::Suffix(value),
+    '*' => AttributeMatch::Contains(value),
+    _ => AttributeMatch::Equals(value),
+  };
+  Ok((name, condition))
+}
@@ -0,0 +1,219 @@
+//! The `dnm::snapshot` submodule provides a serializable form of a `DNM`, which can be
+//! written to disk and reloaded later, skipping the (expensive) DNM construction.
+//!
+//! Nodes are recorded as stable paths of child indices from the DNM's root node, e.g. `"1/0/3"`,
+//! so a snapshot can be reattached to a fresh parse of the same document.
+use crate::dnm::{ByteOffsets, DNMParameters, RuntimeParseData, DNM};
+use libxml::readonly::RoNode;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// A serializable snapshot of a `DNM`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DNMSnapshot {
+  /// The plaintext
+  pub plaintext: String,
+  /// As the plaintext is UTF-8: the byte offsets of the characters
+  pub byte_offsets: Vec<usize>,
+  /// Table of node paths, referenced by index from `node_map` and `back_map`
+  pub node_paths: Vec<String>,
+  /// Maps nodes (as path indices) to plaintext offsets
+  pub node_map: Vec<(usize, usize, usize)>,
+  /// For each plaintext offset, the node (as path index) and the offset in the node
+  pub back_map: Vec<(usize, i32)>,
+}
+
+impl DNMSnapshot {
+  /// Write the snapshot to a JSON file
+  pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, self)?;
+    Ok(())
+  }
+
+  /// Read a snapshot from a JSON file
+  pub fn load(path: &str) -> Result<DNMSnapshot, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+  }
+}
+
+impl DNM {
+  /// Create a serializable snapshot of this DNM. Fails if the DNM refers to nodes outside of
+  /// its root node, which have no path.
+  pub fn to_snapshot(&self) -> Result<DNMSnapshot, Box<dyn Error>> {
+    let referenced: HashSet<usize> = self
+      .node_map
+      .keys()
+      .copied()
+      .chain(self.back_map.iter().map(|(node, _)| node.to_hashable()))
+      .collect();
+    let mut node_paths = Vec::new();
+    let mut path_index = HashMap::new();
+    for (path, node) in referenced_node_paths(self.root_node, &referenced) {
+      path_index.insert(node.to_hashable(), node_paths.len());
+      node_paths.push(path);
+    }
+    let index_of = |hashable: &usize| -> Result<usize, Box<dyn Error>> {
+      path_index
+        .get(hashable)
+        .copied()
+        .ok_or_else(|| "DNM refers to a node outside of its root node".into())
+    };
+
+    let mut node_map = Vec::with_capacity(self.node_map.len());
+    for (hashable, &(start, end)) in &self.node_map {
+      node_map.push((index_of(hashable)?, start, end));
+    }
+    node_map.sort_unstable();
+    let mut back_map = Vec::with_capacity(self.back_map.len());
+    for (node, offset) in &self.back_map {
+      back_map.push((index_of(&node.to_hashable())?, *offset));
+    }
+
+    Ok(DNMSnapshot {
+      plaintext: self.plaintext.clone(),
+      byte_offsets: self.byte_offsets.iter().collect(),
+      node_paths,
+      node_map,
+      back_map,
+    })
+  }
+
+  /// Reattach a snapshot to the root node of the document it was created from.
+  /// The `parameters` should be the ones used when the snapshot was taken.
+  pub fn from_snapshot(
+    snapshot: DNMSnapshot,
+    root_node: RoNode,
+    parameters: DNMParameters,
+  ) -> Result<DNM, Box<dyn Error>> {
+    let byte_offsets = validated_byte_offsets(&snapshot.plaintext, snapshot.byte_offsets)?;
+    let mut nodes = Vec::with_capacity(snapshot.node_paths.len());
+    for path in &snapshot.node_paths {
+      match node_at_path(root_node, path) {
+        Some(node) => nodes.push(node),
+        None => {
+          return Err(
+            format!("DNM snapshot node path \"{path}\" is not present in the document").into(),
+          )
+        },
+      }
+    }
+    let node_for = |index: usize| -> Result<RoNode, Box<dyn Error>> {
+      nodes
+        .get(index)
+        .copied()
+        .ok_or_else(|| format!("DNM snapshot refers to unknown node path index {index}").into())
+    };
+
+    let mut node_map = HashMap::with_capacity(snapshot.node_map.len());
+    for (index, start, end) in snapshot.node_map {
+      node_map.insert(node_for(index)?.to_hashable(), (start, end));
+    }
+    let mut back_map = Vec::with_capacity(snapshot.back_map.len());
+    for (index, offset) in snapshot.back_map {
+      back_map.push((node_for(index)?, offset));
+    }
+
+    Ok(DNM {
+      plaintext: snapshot.plaintext,
+      byte_offsets,
+      parameters,
+      root_node,
+      node_map,
+      runtime: RuntimeParseData::default(),
+      back_map,
+    })
+  }
+
+  /// Load a snapshot without a document, for consumers that only need the plaintext.
+  /// The resulting DNM has no node map and no back-mapping support.
+  pub fn from_snapshot_plaintext(snapshot: DNMSnapshot) -> Result<DNM, Box<dyn Error>> {
+    let byte_offsets = validated_byte_offsets(&snapshot.plaintext, snapshot.byte_offsets)?;
+    Ok(DNM {
+      plaintext: snapshot.plaintext,
+      byte_offsets,
+      parameters: DNMParameters {
+        support_back_mapping: false,
+        ..DNMParameters::default()
+      },
+      ..DNM::default()
+    })
+  }
+}
+
+/// Helper function: Checks that the byte offsets of a snapshot are those of the characters of
+/// its plaintext, followed by the plaintext length, so that slicing the plaintext can't panic
+fn validated_byte_offsets(
+  plaintext: &str,
+  byte_offsets: Vec<usize>,
+) -> Result<ByteOffsets, Box<dyn Error>> {
+  let char_count = plaintext.chars().count();
+  if byte_offsets.len() != char_count + 1 {
+    return Err(
+      format!(
+        "DNM snapshot has {} byte offsets for a plaintext of {char_count} characters",
+        byte_offsets.len()
+      )
+      .into(),
+    );
+  }
+  let expected = plaintext
+    .char_indices()
+    .map(|(offset, _)| offset)
+    .chain(std::iter::once(plaintext.len()));
+  if let Some((index, (offset, _))) = byte_offsets
+    .iter()
+    .zip(expected)
+    .enumerate()
+    .find(|(_, (offset, expected))| **offset != *expected)
+  {
+    return Err(
+      format!(
+        "DNM snapshot byte offset {offset} at index {index} is not the start of a character of \
+         the plaintext"
+      )
+      .into(),
+    );
+  }
+  Ok(ByteOffsets::try_from(byte_offsets)?)
+}
+
+/// Helper function: Lists the `referenced` nodes below (and including) `root`, in document
+/// order, with their paths of child indices relative to `root`
+fn referenced_node_paths(root: RoNode, referenced: &HashSet<usize>) -> Vec<(String, RoNode)> {
+  let mut paths = Vec::with_capacity(referenced.len());
+  collect_node_paths(root, referenced, &mut Vec::new(), &mut paths);
+  paths
+}
+
+/// Helper function: Depth-first step of `referenced_node_paths`
+fn collect_node_paths(
+  node: RoNode,
+  referenced: &HashSet<usize>,
+  indices: &mut Vec<usize>,
+  paths: &mut Vec<(String, RoNode)>,
+) {
+  if referenced.contains(&node.to_hashable()) {
+    let path: Vec<String> = indices.iter().map(usize::to_string).collect();
+    paths.push((path.join("/"), node));
+  }
+  for (index, child) in node.get_child_nodes().into_iter().enumerate() {
+    indices.push(index);
+    collect_node_paths(child, referenced, indices, paths);
+    indices.pop();
+  }
+}
+
+/// Helper function: Finds the node at a path of child indices relative to `root`
+fn node_at_path(root: RoNode, path: &str) -> Option<RoNode> {
+  let mut node = root;
+  for index in path.split('/').filter(|index| !index.is_empty()) {
+    let index: usize = index.parse().ok()?;
+    node = node.get_child_nodes().into_iter().nth(index)?;
+  }
+  Some(node)
+}
@@ -0,0 +1,153 @@
+//! The `dnm::unicode` submodule provides the unicode normalization modes of the DNM.
+//!
+//! All modes can be applied with offset bookkeeping: every character of the normalized string is
+//! mapped to the offset of the original character it stems from, as needed for back-mapping.
+use caseless::Caseless;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization as _;
+use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized};
+use unidecode::{unidecode, unidecode_char};
+
+/// How to normalize the unicode characters of text nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnicodeNormalization {
+  /// Keep the text as it is (default behaviour)
+  #[default]
+  None,
+  /// Canonical composition (NFC), e.g. `e` + combining acute accent becomes `é`
+  Nfc,
+  /// Compatibility composition (NFKC), e.g. ligatures, superscripts and full-width forms are
+  /// replaced by their plain counterparts, but Greek letters and math symbols are kept
+  Nfkc,
+  /// NFKC with full Unicode case folding, for caseless matching, e.g. `ß` becomes `ss` and `ς`
+  /// becomes `σ`
+  NfkcCasefold,
+  /// Transliteration to ASCII via `unidecode` (the behaviour of `normalize_unicode`)
+  Ascii,
+  /// Only repair typographic artefacts: ligatures (`ﬁ`, `ﬂ`, ...) and typographic quotes
+  Repair,
+}
+
+impl UnicodeNormalization {
+  /// Normalize a string
+  pub fn apply(self, string: &str) -> String {
+    match self {
+      UnicodeNormalization::None => string.to_string(),
+      UnicodeNormalization::Nfc => string.nfc().collect(),
+      UnicodeNormalization::Nfkc => string.nfkc().collect(),
+      UnicodeNormalization::NfkcCasefold => nfkc_casefold(string),
+      UnicodeNormalization::Ascii => unidecode(string),
+      UnicodeNormalization::Repair => {
+        let mut repaired = String::with_capacity(string.len());
+        for c in string.chars() {
+          match repair_char(c) {
+            Some(replacement) => repaired.push_str(replacement),
+            None => repaired.push(c),
+          }
+        }
+        repaired
+      },
+    }
+  }
+
+  /// Normalize a string, where `offsets` holds the original offset of each of its characters.
+  /// Returns the normalized string with the offsets of its characters.
+  pub fn apply_with_offsets(self, string: &str, offsets: &[i32]) -> (String, Vec<i32>) {
+    let mut new_string = String::new();
+    let mut new_offsets: Vec<i32> = Vec::new();
+    match self {
+      UnicodeNormalization::None => return (string.to_string(), offsets.to_vec()),
+      // character-wise modes: a character may be replaced by multiple characters
+      UnicodeNormalization::Ascii | UnicodeNormalization::Repair => {
+        for (i, co) in string.chars().enumerate() {
+          let replacement = if self == UnicodeNormalization::Ascii {
+            Some(unidecode_char(co))
+          } else {
+            repair_char(co)
+          };
+          match replacement {
+            Some(replacement) => {
+              for cn in replacement.chars() {
+                new_string.push(cn);
+                new_offsets.push(offsets[i]);
+              }
+            },
+            None => {
+              new_string.push(co);
+              new_offsets.push(offsets[i]);
+            },
+          }
+        }
+      },
+      // composing modes: normalize each cluster of characters between two normalization
+      // boundaries separately, which gives the same result as normalizing the whole string. The
+      // j-th normalized char is mapped to the j-th original char of the cluster, and any surplus
+      // chars to the cluster's last char.
+      UnicodeNormalization::Nfc
+      | UnicodeNormalization::Nfkc
+      | UnicodeNormalization::NfkcCasefold => {
+        let chars: Vec<char> = string.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+          let cluster_start = i;
+          i += 1;
+          while i < chars.len() && !self.is_boundary(chars[i]) {
+            i += 1;
+          }
+          let cluster: String = chars[cluster_start..i].iter().collect();
+          let last = i - cluster_start - 1;
+          for (j, c) in self.apply(&cluster).chars().enumerate() {
+            new_string.push(c);
+            new_offsets.push(offsets[cluster_start + j.min(last)]);
+          }
+        }
+      },
+    }
+    (new_string, new_offsets)
+  }
+
+  /// Helper function: whether normalization never combines `c` with the preceding characters,
+  /// i.e. it is a starter which passes the NFC (or NFKC) quick check. Starters such as U+0CD5,
+  /// U+09BE or Hangul vowel jamo may still compose with the preceding character, and fail it.
+  fn is_boundary(self, c: char) -> bool {
+    if canonical_combining_class(c) != 0 {
+      return false;
+    }
+    let quick_check = if self == UnicodeNormalization::Nfc {
+      is_nfc_quick(std::iter::once(c))
+    } else {
+      is_nfkc_quick(std::iter::once(c))
+    };
+    quick_check == IsNormalized::Yes
+  }
+}
+
+/// Helper function: NFKC_Casefold, as NFKC of the full case folding of the compatibility
+/// decomposition (applying the case folding twice, as some foldings are not closed under
+/// normalization). Unlike the Unicode property, default ignorable characters are kept.
+fn nfkc_casefold(string: &str) -> String {
+  string
+    .nfd()
+    .default_case_fold()
+    .nfkd()
+    .default_case_fold()
+    .nfkc()
+    .collect()
+}
+
+/// Helper function: the replacement of a ligature or typographic quote, if `c` is one
+fn repair_char(c: char) -> Option<&'static str> {
+  Some(match c {
+    '\u{FB00}' => "ff",
+    '\u{FB01}' => "fi",
+    '\u{FB02}' => "fl",
+    '\u{FB03}' => "ffi",
+    '\u{FB04}' => "ffl",
+    '\u{FB05}' | '\u{FB06}' => "st",
+    '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' => "'",
+    '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' => "\"",
+    _ => return None,
+  })
+}
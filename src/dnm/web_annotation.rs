@@ -0,0 +1,548 @@
+//! The `dnm::web_annotation` submodule converts between `DNMRange`s and the selectors of the
+//! [W3C Web Annotation data model](https://www.w3.org/TR/annotation-model/#selectors).
+//!
+//! Selector positions and quotes refer to the *source text* of the DNM's root node (the
+//! concatenation of its text nodes, counted in characters), not to the DNM plaintext.
+//! Re-anchoring goes through `DNM::back_map`, so selectors exported from one DNM can be anchored
+//! in a DNM of the same document built with different normalization parameters.
+use crate::dnm::xpointer::text_point;
+use crate::dnm::{DNMRange, OffsetIndex, OffsetUnit, DNM};
+use libxml::readonly::RoNode;
+use libxml::tree::NodeType;
+use libxml::xpath::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+/// Default number of characters of context recorded in the prefix and suffix of a quote selector
+pub const DEFAULT_QUOTE_CONTEXT: usize = 32;
+
+/// A Web Annotation selector, serializable to the JSON-LD representation of the data model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AnnotationSelector {
+  /// Selects text by quoting it, with some context for disambiguation
+  TextQuoteSelector {
+    /// the quoted text
+    exact: String,
+    /// text immediately preceding the quote
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    prefix: String,
+    /// text immediately following the quote
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    suffix: String,
+  },
+  /// Selects text by its start and end character positions
+  TextPositionSelector {
+    /// position of the first selected character
+    start: usize,
+    /// position after the last selected character
+    end: usize,
+  },
+  /// Selects a node via XPath, optionally refined to a part of its text
+  XPathSelector {
+    /// the XPath expression
+    value: String,
+    /// a selector relative to the selected node
+    #[serde(rename = "refinedBy", default, skip_serializing_if = "Option::is_none")]
+    refined_by: Option<Box<AnnotationSelector>>,
+  },
+}
+
+impl<'dnmrange> DNMRange<'dnmrange> {
+  /// Export as a `TextPositionSelector` over the source text of the DNM's root node
+  pub fn to_text_position_selector(&self) -> Result<AnnotationSelector, Box<dyn Error>> {
+    self.to_text_position_selector_in(OffsetUnit::Char)
+  }
+
+  /// Export as a `TextPositionSelector`, with positions counted in `unit`
+  pub fn to_text_position_selector_in(
+    &self,
+    unit: OffsetUnit,
+  ) -> Result<AnnotationSelector, Box<dyn Error>> {
+    self.to_text_position_selector_with(&self.dnm.source_text(unit))
+  }
+
+  /// Export as a `TextPositionSelector`, via a `SourceText` of the DNM (to be reused across
+  /// ranges), with positions counted in its unit
+  pub fn to_text_position_selector_with(
+    &self,
+    source: &SourceText,
+  ) -> Result<AnnotationSelector, Box<dyn Error>> {
+    let (start, end) = source.extent_of(self)?;
+    let (start, end) = source.to_units(0, start, end)?;
+    Ok(AnnotationSelector::TextPositionSelector { start, end })
+  }
+
+  /// Export as a `TextQuoteSelector`, with up to `context_length` characters of prefix and suffix
+  pub fn to_text_quote_selector(
+    &self,
+    context_length: usize,
+  ) -> Result<AnnotationSelector, Box<dyn Error>> {
+    self.to_text_quote_selector_with(context_length, &self.dnm.source_text(OffsetUnit::Char))
+  }
+
+  /// Export as a `TextQuoteSelector`, via a `SourceText` of the DNM (to be reused across ranges)
+  pub fn to_text_quote_selector_with(
+    &self,
+    context_length: usize,
+    source: &SourceText,
+  ) -> Result<AnnotationSelector, Box<dyn Error>> {
+    let (start, end) = source.extent_of(self)?;
+    Ok(AnnotationSelector::TextQuoteSelector {
+      exact: source.slice(start, end).to_string(),
+      prefix: source
+        .slice(start.saturating_sub(context_length), start)
+        .to_string(),
+      suffix: source
+        .slice(end, (end + context_length).min(source.length))
+        .to_string(),
+    })
+  }
+
+  /// Export as an `XPathSelector` for the lowest element containing the range, refined by a
+  /// `TextPositionSelector` relative to that element
+  pub fn to_xpath_selector(&self) -> Result<AnnotationSelector, Box<dyn Error>> {
+    self.to_xpath_selector_in(OffsetUnit::Char)
+  }
+
+  /// Export as an `XPathSelector`, refined by a `TextPositionSelector` with positions counted in
+  /// `unit`
+  pub fn to_xpath_selector_in(
+    &self,
+    unit: OffsetUnit,
+  ) -> Result<AnnotationSelector, Box<dyn Error>> {
+    self.to_xpath_selector_with(&self.dnm.source_text(unit))
+  }
+
+  /// Export as an `XPathSelector`, via a `SourceText` of the DNM (to be reused across ranges),
+  /// refined by a `TextPositionSelector` with positions counted in its unit
+  pub fn to_xpath_selector_with(
+    &self,
+    source: &SourceText,
+  ) -> Result<AnnotationSelector, Box<dyn Error>> {
+    let (start, end) = source.extent_of(self)?;
+    let container = self.lowest_common_element()?;
+    let (container_start, _) = source.node_extent(container)?;
+    let (start, end) = source.to_units(container_start, start, end)?;
+    Ok(AnnotationSelector::XPathSelector {
+      value: absolute_xpath(container),
+      refined_by: Some(Box::new(AnnotationSelector::TextPositionSelector {
+        start,
+        end,
+      })),
+    })
+  }
+
+  /// Export as all three kinds of selectors, for maximal robustness when re-anchoring
+  pub fn to_annotation_selectors(&self) -> Result<Vec<AnnotationSelector>, Box<dyn Error>> {
+    self.to_annotation_selectors_with(&self.dnm.source_text(OffsetUnit::Char))
+  }
+
+  /// Export as all three kinds of selectors, via a `SourceText` of the DNM (to be reused across
+  /// ranges), with positions counted in its unit
+  pub fn to_annotation_selectors_with(
+    &self,
+    source: &SourceText,
+  ) -> Result<Vec<AnnotationSelector>, Box<dyn Error>> {
+    Ok(vec![
+      self.to_text_quote_selector_with(DEFAULT_QUOTE_CONTEXT, source)?,
+      self.to_text_position_selector_with(source)?,
+      self.to_xpath_selector_with(source)?,
+    ])
+  }
+
+  /// Anchors a Web Annotation selector in `dnm`
+  pub fn from_selector(
+    selector: &AnnotationSelector,
+    dnm: &'dnmrange DNM,
+    xpath_context: &Context,
+  ) -> Result<DNMRange<'dnmrange>, Box<dyn Error>> {
+    DNMRange::from_selector_in(selector, dnm, xpath_context, OffsetUnit::Char)
+  }
+
+  /// Anchors a Web Annotation selector in `dnm`, with the positions of `TextPositionSelector`s
+  /// counted in `unit`
+  pub fn from_selector_in(
+    selector: &AnnotationSelector,
+    dnm: &'dnmrange DNM,
+    xpath_context: &Context,
+    unit: OffsetUnit,
+  ) -> Result<DNMRange<'dnmrange>, Box<dyn Error>> {
+    DNMRange::from_selector_with(selector, dnm, xpath_context, &dnm.source_text(unit))
+  }
+
+  /// Anchors a Web Annotation selector in `dnm`, via a `SourceText` of `dnm` (to be reused
+  /// across selectors), with the positions of `TextPositionSelector`s counted in its unit
+  pub fn from_selector_with(
+    selector: &AnnotationSelector,
+    dnm: &'dnmrange DNM,
+    xpath_context: &Context,
+    source: &SourceText,
+  ) -> Result<DNMRange<'dnmrange>, Box<dyn Error>> {
+    source.check_root(dnm)?;
+    match selector {
+      AnnotationSelector::TextPositionSelector { start, end } => {
+        let (start, end) = source.to_chars(0, *start, *end)?;
+        source.anchor(dnm, start, end)
+      },
+      AnnotationSelector::TextQuoteSelector {
+        exact,
+        prefix,
+        suffix,
+      } => {
+        let start = source
+          .find_quote(exact, prefix, suffix, 0, source.length)
+          .ok_or_else(|| format!("TextQuoteSelector: quote \"{exact}\" not found"))?;
+        source.anchor(dnm, start, start + exact.chars().count())
+      },
+      AnnotationSelector::XPathSelector { value, refined_by } => {
+        let nodes = xpath_context
+          .evaluate(value)
+          .map_err(|_| format!("XPathSelector: invalid XPath expression \"{value}\""))?
+          .get_readonly_nodes_as_vec();
+        if nodes.len() != 1 {
+          return Err(
+            format!(
+              "XPathSelector: \"{value}\" selected {} nodes, expected exactly one",
+              nodes.len()
+            )
+            .into(),
+          );
+        }
+        let node = nodes[0];
+        let (node_start, node_end) = source.node_extent(node)?;
+        match refined_by.as_deref() {
+          None => match dnm.get_range_of_node(node) {
+            Ok(range) => Ok(range),
+            Err(_) => source.anchor(dnm, node_start, node_end),
+          },
+          Some(AnnotationSelector::TextPositionSelector { start, end }) => {
+            let (start, end) = source.to_chars(node_start, *start, *end)?;
+            source.anchor(dnm, start.min(node_end), end.min(node_end))
+          },
+          Some(AnnotationSelector::TextQuoteSelector {
+            exact,
+            prefix,
+            suffix,
+          }) => {
+            let start = source
+              .find_quote(exact, prefix, suffix, node_start, node_end)
+              .ok_or_else(|| format!("TextQuoteSelector: quote \"{exact}\" not found"))?;
+            source.anchor(dnm, start, start + exact.chars().count())
+          },
+          Some(other) => Err(format!("XPathSelector: unsupported refinement {other:?}").into()),
+        }
+      },
+    }
+  }
+
+  /// Helper function: the lowest element containing both ends of the range
+  fn lowest_common_element(&self) -> Result<RoNode, Box<dyn Error>> {
+    if !self.dnm.parameters.support_back_mapping {
+      return Err("DNM did not generate the back_map".into());
+    }
+    let first = self.dnm.back_map[self.start].0;
+    let last = self.dnm.back_map[self.end.max(self.start + 1) - 1].0;
+    let mut first_ancestors = HashSet::new();
+    let mut current = Some(first);
+    while let Some(node) = current {
+      first_ancestors.insert(node.to_hashable());
+      current = node.get_parent();
+    }
+    let mut current = Some(last);
+    while let Some(node) = current {
+      if first_ancestors.contains(&node.to_hashable())
+        && node.get_type() == Some(NodeType::ElementNode)
+      {
+        return Ok(node);
+      }
+      current = node.get_parent();
+    }
+    Err("DNMRange: no common ancestor element".into())
+  }
+}
+
+impl DNM {
+  /// The source text of the DNM's root node, with positions counted in `unit`, for exporting
+  /// and anchoring many selectors without re-indexing the document each time
+  pub fn source_text(&self, unit: OffsetUnit) -> SourceText {
+    SourceText::new(self.root_node, unit)
+  }
+}
+
+/// The source text of a DOM subtree, with the character extents of its nodes, that Web
+/// Annotation selectors refer to. Building it walks the whole subtree, so it is best built once
+/// per DNM (see `DNM::source_text`) and reused across ranges.
+pub struct SourceText {
+  /// the root node of the subtree
+  root: RoNode,
+  /// the concatenated text content
+  text: String,
+  /// number of characters in `text`
+  length: usize,
+  /// byte offset of every character in `text`, plus the total byte length
+  char_bytes: Vec<usize>,
+  /// the non-empty text nodes, with their first character position
+  text_nodes: Vec<(RoNode, usize)>,
+  /// character extents of all nodes, keyed by `RoNode::to_hashable`
+  extents: HashMap<usize, (usize, usize)>,
+  /// the unit of selector positions
+  unit: OffsetUnit,
+  /// the offsets of `text` in `unit`, unless counting characters
+  unit_index: Option<OffsetIndex>,
+}
+
+impl SourceText {
+  /// Index the source text of the subtree of `root`, with selector positions counted in `unit`
+  pub fn new(root: RoNode, unit: OffsetUnit) -> SourceText {
+    let mut source = SourceText {
+      root,
+      text: String::new(),
+      length: 0,
+      char_bytes: Vec::new(),
+      text_nodes: Vec::new(),
+      extents: HashMap::new(),
+      unit,
+      unit_index: None,
+    };
+    source.visit(root);
+    source.char_bytes.push(source.text.len());
+    if unit != OffsetUnit::Char {
+      source.unit_index = Some(OffsetIndex::new(&source.text, unit));
+    }
+    source
+  }
+
+  /// The unit selector positions are counted in
+  pub fn unit(&self) -> OffsetUnit { self.unit }
+
+  /// Helper function: checks that the source text is the one of `dnm`'s root node
+  fn check_root(&self, dnm: &DNM) -> Result<(), Box<dyn Error>> {
+    if self.root == dnm.root_node {
+      Ok(())
+    } else {
+      Err("SourceText: not the source text of the DNM's root node".into())
+    }
+  }
+
+  fn visit(&mut self, node: RoNode) {
+    let start = self.length;
+    if node.is_text_node() {
+      let content = node.get_content();
+      if !content.is_empty() {
+        self.text_nodes.push((node, start));
+      }
+      for (byte, _) in content.char_indices() {
+        self.char_bytes.push(self.text.len() + byte);
+        self.length += 1;
+      }
+      self.text.push_str(&content);
+    } else {
+      for child in node.get_child_nodes() {
+        self.visit(child);
+      }
+    }
+    self
+      .extents
+      .insert(node.to_hashable(), (start, self.length));
+  }
+
+  /// Converts a character extent to the source text's unit, relative to the character position
+  /// `base`
+  fn to_units(
+    &self,
+    base: usize,
+    start: usize,
+    end: usize,
+  ) -> Result<(usize, usize), Box<dyn Error>> {
+    let index = match &self.unit_index {
+      Some(index) => index,
+      None => return Ok((start - base, end - base)),
+    };
+    match (index.offset(base), index.offset(start), index.offset(end)) {
+      (Some(base), Some(start), Some(end)) => Ok((start - base, end - base)),
+      _ => Err(
+        format!(
+          "source text positions {start}..{end} have no {:?} offsets",
+          self.unit
+        )
+        .into(),
+      ),
+    }
+  }
+
+  /// Converts an extent counted in the source text's unit, relative to the character position
+  /// `base`, to characters. The end is capped at the end of the source text.
+  fn to_chars(
+    &self,
+    base: usize,
+    start: usize,
+    end: usize,
+  ) -> Result<(usize, usize), Box<dyn Error>> {
+    let index = match &self.unit_index {
+      Some(index) => index,
+      None => return Ok((base + start, (base + end).min(self.length))),
+    };
+    let base_units = index
+      .offset(base)
+      .ok_or("source text position has no offset")?;
+    let length_units = index.offset(self.length).unwrap_or_default();
+    match (
+      index.char_offset(base_units + start),
+      index.char_offset((base_units + end).min(length_units)),
+    ) {
+      (Some(start), Some(end)) => Ok((start, end)),
+      _ => Err(
+        format!(
+          "{start}..{end} are not {:?} offsets of the source text",
+          self.unit
+        )
+        .into(),
+      ),
+    }
+  }
+
+  fn slice(&self, start: usize, end: usize) -> &str {
+    &self.text[self.char_bytes[start]..self.char_bytes[end]]
+  }
+
+  fn node_extent(&self, node: RoNode) -> Result<(usize, usize), Box<dyn Error>> {
+    self
+      .extents
+      .get(&node.to_hashable())
+      .copied()
+      .ok_or_else(|| "node is not part of the DNM's root node".into())
+  }
+
+  /// The source text extent of a DNM range
+  fn extent_of(&self, range: &DNMRange) -> Result<(usize, usize), Box<dyn Error>> {
+    self.check_root(range.dnm)?;
+    if !range.dnm.parameters.support_back_mapping {
+      return Err("DNM did not generate the back_map".into());
+    }
+    let start = self.position_of(range.dnm, range.start, false)?;
+    let end = if range.end > range.start {
+      self.position_of(range.dnm, range.end - 1, true)?
+    } else {
+      start
+    };
+    Ok((start, end.max(start)))
+  }
+
+  /// The source text position of a plaintext offset, either before or after its character
+  fn position_of(&self, dnm: &DNM, offset: usize, after: bool) -> Result<usize, Box<dyn Error>> {
+    if offset >= dnm.back_map.len() {
+      return Ok(self.length);
+    }
+    let (node, node_offset) = dnm.back_map[offset];
+    let (node_start, node_end) = self.node_extent(node)?;
+    Ok(if node.is_text_node() && node_offset >= 0 {
+      node_start + node_offset as usize + usize::from(after)
+    } else if after {
+      node_end
+    } else {
+      node_start
+    })
+  }
+
+  /// Maps a source text extent back into a `DNMRange`
+  fn anchor<'d>(
+    &self,
+    dnm: &'d DNM,
+    start: usize,
+    end: usize,
+  ) -> Result<DNMRange<'d>, Box<dyn Error>> {
+    let start = self.plaintext_offset(dnm, start)?;
+    let end = self.plaintext_offset(dnm, end)?.max(start);
+    Ok(DNMRange { start, end, dnm })
+  }
+
+  /// Maps a source text position to the plaintext offset of the first character at or after it
+  fn plaintext_offset(&self, dnm: &DNM, position: usize) -> Result<usize, Box<dyn Error>> {
+    let index = self
+      .text_nodes
+      .partition_point(|&(_, start)| start <= position);
+    if position >= self.length || index == 0 {
+      return Ok(if position >= self.length {
+        dnm.byte_offsets.len().saturating_sub(1)
+      } else {
+        0
+      });
+    }
+    let (text_node, node_start) = self.text_nodes[index - 1];
+    Ok(text_point(dnm, text_node, position - node_start)?)
+  }
+
+  /// Finds the occurrence of `exact` within the window of positions `from..to` whose surrounding
+  /// text best agrees with `prefix` and `suffix`
+  fn find_quote(
+    &self,
+    exact: &str,
+    prefix: &str,
+    suffix: &str,
+    from: usize,
+    to: usize,
+  ) -> Option<usize> {
+    if exact.is_empty() {
+      return None;
+    }
+    let window_start = self.char_bytes[from];
+    let window = &self.text[window_start..self.char_bytes[to]];
+    let mut best: Option<(usize, usize)> = None;
+    for (byte, _) in window.match_indices(exact) {
+      let absolute_byte = window_start + byte;
+      let position = match self.char_bytes.binary_search(&absolute_byte) {
+        Ok(position) => position,
+        Err(_) => continue,
+      };
+      let before = &self.text[..absolute_byte];
+      let after = &self.text[absolute_byte + exact.len()..];
+      let score = prefix
+        .chars()
+        .rev()
+        .zip(before.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+        + suffix
+          .chars()
+          .zip(after.chars())
+          .take_while(|(a, b)| a == b)
+          .count();
+      if best.is_none_or(|(_, best_score)| score > best_score) {
+        best = Some((position, score));
+      }
+    }
+    best.map(|(position, _)| position)
+  }
+}
+
+/// Helper function: an absolute XPath for an element, robust to default namespaces
+fn absolute_xpath(node: RoNode) -> String {
+  let mut steps = Vec::new();
+  let mut current = Some(node);
+  while let Some(element) = current {
+    if element.get_type() != Some(NodeType::ElementNode) {
+      break;
+    }
+    let name = element.get_name();
+    let position = 1
+      + element
+        .get_parent()
+        .map(|parent| {
+          parent
+            .get_child_nodes()
+            .into_iter()
+            .take_while(|sibling| *sibling != element)
+            .filter(|sibling| {
+              sibling.get_type() == Some(NodeType::ElementNode) && sibling.get_name() == name
+            })
+            .count()
+        })
+        .unwrap_or(0);
+    steps.push(format!("*[local-name()='{name}'][{position}]"));
+    current = element.get_parent();
+  }
+  steps.reverse();
+  format!("/{}", steps.join("/"))
+}
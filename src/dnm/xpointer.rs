@@ -0,0 +1,495 @@
+//! The `dnm::xpointer` submodule deserializes XPointers into `DNMRange`s.
+//!
+//! Besides the `arange(...)` pointers created by `DNMRange::serialize`, the common forms of the
+//! XPointer framework are supported:
+//!  - shorthand pointers (`#S1.p2`) and the `element()` scheme (`element(S1/2/1)`,
+//!    `element(/1/2)`)
+//!  - the `xmlns()` scheme for binding namespace prefixes used by later parts. The bindings are
+//!    registered on the caller's XPath `Context` and remain there after deserialization.
+//!  - the `xpointer()` scheme, with XPath locations, `string-range()`, `point()`,
+//!    `start-point()`, `end-point()` and `range-to()`
+//!
+//! Multiple scheme parts may be given, separated by whitespace; the first one that resolves wins.
+use crate::dnm::{DNMRange, DNM};
+use libxml::readonly::RoNode;
+use libxml::tree::NodeType;
+use libxml::xpath::Context;
+use std::error::Error;
+use std::fmt;
+
+/// Describes why an XPointer could not be deserialized
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XPointerError {
+  /// The pointer is not syntactically well-formed
+  Malformed(String),
+  /// The pointer uses a scheme or function that is not supported
+  Unsupported(String),
+  /// An XPath expression could not be evaluated
+  InvalidXPath(String),
+  /// An XPath expression did not select exactly one node
+  NodeCount {
+    /// the offending expression
+    xpath: String,
+    /// the number of nodes selected
+    count: usize,
+  },
+  /// A `string-range()` did not find its string
+  StringNotFound(String),
+  /// The location has no counterpart in the DNM, e.g. it lies outside of the DNM's root node
+  NotInDNM(String),
+  /// Character-level pointers require a DNM with back-mapping support
+  NoBackMapping,
+}
+
+impl fmt::Display for XPointerError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    use XPointerError::*;
+    match self {
+      Malformed(p) => write!(f, "malformed XPointer: \"{p}\""),
+      Unsupported(p) => write!(f, "unsupported XPointer scheme or function: \"{p}\""),
+      InvalidXPath(x) => write!(f, "invalid XPath expression: \"{x}\""),
+      NodeCount { xpath, count } => write!(
+        f,
+        "XPath expression \"{xpath}\" selected {count} nodes, expected exactly one"
+      ),
+      StringNotFound(s) => write!(f, "string-range: string \"{s}\" not found"),
+      NotInDNM(p) => write!(f, "XPointer location \"{p}\" is not covered by the DNM"),
+      NoBackMapping => write!(f, "DNM did not generate the back_map"),
+    }
+  }
+}
+
+impl Error for XPointerError {}
+
+/// A resolved location, as a pair of plaintext offsets (equal for points)
+type Location = (usize, usize);
+
+impl<'dnmrange> DNMRange<'dnmrange> {
+  /// deserializes an xpointer into a `DNMRange`, see the `dnm::xpointer` module documentation for
+  /// the supported forms.
+  ///
+  /// Side effect: the namespace prefixes bound by `xmlns()` parts are registered on
+  /// `xpath_context` and stay registered for later evaluations. Use a dedicated `Context` for
+  /// pointers whose bindings must not leak into other XPath queries.
+  pub fn deserialize(
+    string: &str,
+    dnm: &'dnmrange DNM,
+    xpath_context: &Context,
+  ) -> Result<DNMRange<'dnmrange>, XPointerError> {
+    let pointer = string.trim();
+    let pointer = pointer.strip_prefix('#').unwrap_or(pointer);
+    let resolver = Resolver { dnm, xpath_context };
+
+    let mut last_error = XPointerError::Malformed(string.to_string());
+    for part in split_scheme_parts(pointer)? {
+      match resolver.part(part) {
+        Ok(Some((start, end))) => return Ok(DNMRange { start, end, dnm }),
+        Ok(None) => continue,
+        Err(e) => last_error = e,
+      }
+    }
+    Err(last_error)
+  }
+}
+
+/// Resolves the parts of a pointer against a DNM and its document
+struct Resolver<'r> {
+  dnm: &'r DNM,
+  xpath_context: &'r Context,
+}
+
+impl<'r> Resolver<'r> {
+  /// Resolves a scheme part, `None` for parts that only modify the (shared) context (`xmlns()`)
+  fn part(&self, part: &str) -> Result<Option<Location>, XPointerError> {
+    if let Some(binding) = call_args(part, "xmlns") {
+      let (prefix, uri) = binding
+        .split_once('=')
+        .ok_or_else(|| XPointerError::Malformed(part.to_string()))?;
+      self
+        .xpath_context
+        .register_namespace(prefix.trim(), uri.trim())
+        .map_err(|_| XPointerError::Malformed(part.to_string()))?;
+      Ok(None)
+    } else if let Some(child_sequence) = call_args(part, "element") {
+      Ok(Some(
+        self.node_location(self.element_scheme(child_sequence)?)?,
+      ))
+    } else if let Some(expr) = call_args(part, "xpointer") {
+      self.expr(expr).map(Some)
+    } else if let Some(points) = call_args(part, "arange") {
+      // llamapun's own format: the end point is the first position after the range
+      match split_top_level(points).as_slice() {
+        [from, to] => Ok(Some((self.expr(from)?.0, self.expr(to)?.0))),
+        _ => Err(XPointerError::Malformed(part.to_string())),
+      }
+    } else if is_scheme_call(part) && !is_location_function(part) {
+      Err(XPointerError::Unsupported(part.to_string()))
+    } else {
+      self.expr(part).map(Some)
+    }
+  }
+
+  /// Resolves an expression of the `xpointer()` scheme
+  fn expr(&self, expr: &str) -> Result<Location, XPointerError> {
+    let expr = expr.trim();
+    if expr.ends_with(')') {
+      if let Some(index) = find_top_level(expr, "/range-to(") {
+        let from = self.expr(&expr[..index])?;
+        let to = self.expr(&expr[index + "/range-to(".len()..expr.len() - 1])?;
+        return Ok((from.0, to.1));
+      }
+    }
+    self.location(expr)
+  }
+
+  /// Resolves a single location (node, point or range)
+  fn location(&self, expr: &str) -> Result<Location, XPointerError> {
+    if let Some(args) = call_args(expr, "string-index") {
+      match split_top_level(args).as_slice() {
+        [xpath, index] => {
+          let node = self.evaluate_node(xpath)?;
+          let point = self.point_in_node(node, parse_position(index, expr)?)?;
+          Ok((point, point))
+        },
+        _ => Err(XPointerError::Malformed(expr.to_string())),
+      }
+    } else if let Some(args) = call_args(expr, "string-range") {
+      self.string_range(args, expr)
+    } else if let Some(inner) = call_args(expr, "start-point") {
+      let (start, _) = self.expr(inner)?;
+      Ok((start, start))
+    } else if let Some(inner) = call_args(expr, "end-point") {
+      let (_, end) = self.expr(inner)?;
+      Ok((end, end))
+    } else if let Some(point_index) = expr.rfind("/point()[").filter(|_| expr.ends_with(']')) {
+      // points are numbered from 1, the first one preceding the first character
+      let node = self.evaluate_node(&expr[..point_index])?;
+      let position = &expr[point_index + "/point()[".len()..expr.len() - 1];
+      let point = self.point_in_node(node, parse_position(position, expr)?)?;
+      Ok((point, point))
+    } else if !expr.is_empty() && expr.chars().all(is_ncname_char) {
+      self.node_location(self.id_node(expr)?)
+    } else {
+      self.node_location(self.evaluate_node(expr)?)
+    }
+  }
+
+  /// `string-range(location, "string", position?, length?)`
+  fn string_range(&self, args: &str, expr: &str) -> Result<Location, XPointerError> {
+    let args = split_top_level(args);
+    if args.len() < 2 || args.len() > 4 {
+      return Err(XPointerError::Malformed(expr.to_string()));
+    }
+    let node = self.evaluate_node(args[0])?;
+    let needle = unquote(args[1]).ok_or_else(|| XPointerError::Malformed(expr.to_string()))?;
+    let position = match args.get(2) {
+      Some(position) => parse_position(position, expr)?,
+      None => 0,
+    };
+    let length = match args.get(3) {
+      Some(length) => length
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| XPointerError::Malformed(expr.to_string()))?,
+      None => needle.chars().count().saturating_sub(position),
+    };
+
+    let content = node.get_content();
+    let found = content
+      .find(needle)
+      .ok_or_else(|| XPointerError::StringNotFound(needle.to_string()))?;
+    let start = content[..found].chars().count() + position;
+    Ok((
+      self.point_in_node(node, start)?,
+      self.point_in_node(node, start + length)?,
+    ))
+  }
+
+  /// The `element()` scheme: an optional id, followed by a sequence of element child numbers
+  fn element_scheme(&self, child_sequence: &str) -> Result<RoNode, XPointerError> {
+    let mut steps = child_sequence.trim().split('/');
+    let mut node = match steps.next() {
+      Some("") => {
+        // an absolute sequence starts at the document element, which must be "/1"
+        if steps.next() != Some("1") {
+          return Err(XPointerError::NotInDNM(child_sequence.to_string()));
+        }
+        self.evaluate_node("/*")?
+      },
+      Some(id) => self.id_node(id)?,
+      None => return Err(XPointerError::Malformed(child_sequence.to_string())),
+    };
+    for step in steps {
+      let number = step
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| XPointerError::Malformed(child_sequence.to_string()))?;
+      node = node
+        .get_child_nodes()
+        .into_iter()
+        .filter(|child| child.get_type() == Some(NodeType::ElementNode))
+        .nth(number - 1)
+        .ok_or_else(|| XPointerError::NotInDNM(child_sequence.to_string()))?;
+    }
+    Ok(node)
+  }
+
+  /// Evaluates an XPath expression that must select exactly one node
+  fn evaluate_node(&self, xpath: &str) -> Result<RoNode, XPointerError> {
+    let xpath = xpath.trim();
+    let nodes = self
+      .xpath_context
+      .evaluate(xpath)
+      .map_err(|_| XPointerError::InvalidXPath(xpath.to_string()))?
+      .get_readonly_nodes_as_vec();
+    if nodes.len() == 1 {
+      Ok(nodes[0])
+    } else {
+      Err(XPointerError::NodeCount {
+        xpath: xpath.to_string(),
+        count: nodes.len(),
+      })
+    }
+  }
+
+  /// Gets the element with the given (xml:)id
+  fn id_node(&self, id: &str) -> Result<RoNode, XPointerError> {
+    self.evaluate_node(&format!("//*[@id='{id}' or @xml:id='{id}']"))
+  }
+
+  /// The range of a node, or the start of its lowest parent recorded in the DNM
+  fn node_location(&self, node: RoNode) -> Result<Location, XPointerError> {
+    match self.dnm.get_range_of_node(node) {
+      Ok(range) => Ok((range.start, range.end)),
+      Err(_) => {
+        let point = self.lowest_parent_start(node)?;
+        Ok((point, point))
+      },
+    }
+  }
+
+  /// Maps a character offset into the string-value of a node to a plaintext offset
+  fn point_in_node(&self, node: RoNode, offset: usize) -> Result<usize, XPointerError> {
+    if node.is_text_node() {
+      text_point(self.dnm, node, offset)
+    } else {
+      let mut text_nodes = Vec::new();
+      collect_text_nodes(node, &mut text_nodes);
+      let mut remaining = offset;
+      for text_node in text_nodes {
+        let length = text_node.get_content().chars().count();
+        if remaining < length {
+          return text_point(self.dnm, text_node, remaining);
+        }
+        remaining -= length;
+      }
+      // at (or past) the end of the node
+      Ok(self.node_location(node)?.1)
+    }
+  }
+
+  /// Gets the start offset of the lowest parent recorded in the DNM
+  fn lowest_parent_start(&self, node: RoNode) -> Result<usize, XPointerError> {
+    lowest_parent_start(self.dnm, node)
+  }
+}
+
+/*
+ * TEXT NODE MAPPING
+ */
+
+/// Maps a character offset inside a text node to a plaintext offset of the DNM
+pub(crate) fn text_point(
+  dnm: &DNM,
+  text_node: RoNode,
+  offset: usize,
+) -> Result<usize, XPointerError> {
+  match dnm.get_range_of_node(text_node) {
+    Ok(range) => {
+      if !dnm.parameters.support_back_mapping {
+        return Err(XPointerError::NoBackMapping);
+      }
+      let mut pos = range.start;
+      while pos < range.end && i64::from(dnm.back_map[pos].1) < offset as i64 {
+        pos += 1;
+      }
+      Ok(pos)
+    },
+    Err(_) => lowest_parent_start(dnm, text_node),
+  }
+}
+
+/// Gets the start offset of the lowest parent recorded in the DNM
+pub(crate) fn lowest_parent_start(dnm: &DNM, node: RoNode) -> Result<usize, XPointerError> {
+  let mut current = node;
+  loop {
+    if let Ok(range) = dnm.get_range_of_node(current) {
+      return Ok(range.start);
+    }
+    current = current
+      .get_parent()
+      .ok_or_else(|| XPointerError::NotInDNM(node.get_name()))?;
+  }
+}
+
+/// Helper function: collects the descendant text nodes, in document order
+fn collect_text_nodes(node: RoNode, text_nodes: &mut Vec<RoNode>) {
+  for child in node.get_child_nodes() {
+    if child.is_text_node() {
+      text_nodes.push(child);
+    } else {
+      collect_text_nodes(child, text_nodes);
+    }
+  }
+}
+
+/*
+ * PARSING HELPERS
+ */
+
+fn is_ncname_char(c: char) -> bool { c.is_alphanumeric() || c == '_' || c == '-' || c == '.' }
+
+/// Parses a 1-based position into a 0-based offset
+fn parse_position(position: &str, expr: &str) -> Result<usize, XPointerError> {
+  match position.trim().parse::<usize>() {
+    Ok(n) if n > 0 => Ok(n - 1),
+    _ => Err(XPointerError::Malformed(expr.to_string())),
+  }
+}
+
+/// Strips the quotes of a string literal
+fn unquote(literal: &str) -> Option<&str> {
+  let literal = literal.trim();
+  let quote = literal.chars().next()?;
+  if (quote == '"' || quote == '\'') && literal.len() >= 2 && literal.ends_with(quote) {
+    Some(&literal[1..literal.len() - 1])
+  } else {
+    None
+  }
+}
+
+/// Finds the byte index of the closing bracket matching the opening one at `open`,
+/// skipping nested brackets and quoted strings
+fn matching_bracket(string: &str, open: usize) -> Option<usize> {
+  let mut depth = 0;
+  let mut quote: Option<char> = None;
+  for (index, c) in string[open..].char_indices() {
+    match (quote, c) {
+      (Some(q), c) if c == q => quote = None,
+      (Some(_), _) => {},
+      (None, '"') | (None, '\'') => quote = Some(c),
+      (None, '(') | (None, '[') => depth += 1,
+      (None, ')') | (None, ']') => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(open + index);
+        }
+      },
+      _ => {},
+    }
+  }
+  None
+}
+
+/// Finds `pattern` outside of brackets and quoted strings
+fn find_top_level(string: &str, pattern: &str) -> Option<usize> {
+  let mut depth = 0;
+  let mut quote: Option<char> = None;
+  for (index, c) in string.char_indices() {
+    if quote.is_none() && depth == 0 && string[index..].starts_with(pattern) {
+      return Some(index);
+    }
+    match (quote, c) {
+      (Some(q), c) if c == q => quote = None,
+      (Some(_), _) => {},
+      (None, '"') | (None, '\'') => quote = Some(c),
+      (None, '(') | (None, '[') => depth += 1,
+      (None, ')') | (None, ']') => depth -= 1,
+      _ => {},
+    }
+  }
+  None
+}
+
+/// Splits function arguments at the top-level commas
+fn split_top_level(string: &str) -> Vec<&str> {
+  let mut parts = Vec::new();
+  let mut rest = string;
+  while let Some(comma) = find_top_level(rest, ",") {
+    parts.push(rest[..comma].trim());
+    rest = &rest[comma + 1..];
+  }
+  parts.push(rest.trim());
+  parts
+}
+
+/// The arguments of `name(...)`, if `expr` is exactly a call of `name`
+fn call_args<'e>(expr: &'e str, name: &str) -> Option<&'e str> {
+  let expr = expr.trim();
+  let after_name = expr.strip_prefix(name)?;
+  if after_name.starts_with('(') && matching_bracket(expr, name.len()) == Some(expr.len() - 1) {
+    Some(&expr[name.len() + 1..expr.len() - 1])
+  } else {
+    None
+  }
+}
+
+/// Checks whether `expr` has the shape `scheme(...)`
+fn is_scheme_call(expr: &str) -> bool {
+  match expr.find('(') {
+    Some(open) => {
+      open > 0
+        && expr[..open].chars().all(is_ncname_char)
+        && matching_bracket(expr, open) == Some(expr.len() - 1)
+    },
+    None => false,
+  }
+}
+
+/// Checks whether `expr` starts with one of the location functions allowed as a bare pointer
+fn is_location_function(expr: &str) -> bool {
+  [
+    "string-index(",
+    "string-range(",
+    "start-point(",
+    "end-point(",
+    "id(",
+  ]
+  .iter()
+  .any(|function| expr.starts_with(function))
+}
+
+/// Splits a pointer into its scheme parts. A pointer that doesn't consist of scheme parts
+/// (e.g. a bare XPath) is returned as a single part.
+fn split_scheme_parts(pointer: &str) -> Result<Vec<&str>, XPointerError> {
+  let mut parts = Vec::new();
+  let mut rest = pointer.trim();
+  while !rest.is_empty() {
+    let scheme_end = rest
+      .find('(')
+      .filter(|&open| open > 0 && rest[..open].chars().all(is_ncname_char))
+      .and_then(|open| matching_bracket(rest, open));
+    match scheme_end {
+      Some(close)
+        if rest[close + 1..].is_empty() || rest[close + 1..].starts_with(char::is_whitespace) =>
+      {
+        parts.push(&rest[..=close]);
+        rest = rest[close + 1..].trim_start();
+      },
+      _ => {
+        if parts.is_empty() {
+          parts.push(rest);
+          break;
+        } else {
+          return Err(XPointerError::Malformed(pointer.to_string()));
+        }
+      },
+    }
+  }
+  if parts.is_empty() {
+    Err(XPointerError::Malformed(pointer.to_string()))
+  } else {
+    Ok(parts)
+  }
+}
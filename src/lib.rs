@@ -27,10 +27,12 @@ extern crate walkdir;
 pub mod util;
 pub mod ams;
 pub mod data;
+pub mod dedup;
 pub mod dnm;
 pub mod ngrams;
 pub mod parallel_data;
 pub mod patterns;
+pub mod punkt;
 pub mod stopwords;
 pub mod tokenizer;
 
@@ -2,7 +2,10 @@
 //! including parallel I/O in walking a corpus
 //! as well as DOM primitives that allow parallel iterators on XPath results, etc
 use crate::dnm::{DNMParameters, DNMRange, DNM};
+use crate::tokenizer::token::Token;
+use crate::tokenizer::{RangeIter, Segmenter};
 use libxml::readonly::RoNode;
+use std::iter;
 use std::vec::IntoIter;
 
 /* ---- Containers ----- */
@@ -27,6 +30,8 @@ pub struct ItemDNMRange<'s> {
   pub range: DNMRange<'s>,
   /// The document containing this sentence
   pub document: &'s Document<'s>,
+  /// The segmenter chosen for the DNM of the range, see `Segmenter::for_dnm`
+  pub segmenter: &'s dyn Segmenter,
 }
 
 /* ---- Iterators ----- */
@@ -43,7 +48,9 @@ pub struct RoNodeIterator<'iter> {
 /// A generic iterator over DNMRanges with their associated document (e.g. for sentences)
 pub struct DNMRangeIterator<'iter> {
   /// The walker over the sentence ranges
-  walker: IntoIter<DNMRange<'iter>>,
+  walker: RangeIter<'iter>,
+  /// The segmenter chosen for the DNM of the ranges
+  segmenter: &'iter dyn Segmenter,
   /// A reference to the document we are working on
   pub document: &'iter Document<'iter>,
 }
@@ -71,11 +78,17 @@ pub trait XPathFilteredIterator<'p> {
   fn to_sentences(&'p self) -> Vec<DNMRange<'p>>;
   /// the owner document being selected over
   fn get_document(&'p self) -> &'p Document;
+  /// the segmenter for the resulting selection, the corpus segmenter unless implemented per DNM
+  fn segmenter(&'p self) -> &'p dyn Segmenter { self.get_document().corpus.tokenizer.as_ref() }
+  /// a lazy iterator over the sentences for the resulting selection, collecting `to_sentences`
+  /// unless implemented lazily
+  fn sentence_iter(&'p self) -> RangeIter<'p> { Box::new(self.to_sentences().into_iter()) }
 
   /// Get an iterator over the sentences in this paragraph
   fn iter(&'p mut self) -> DNMRangeIterator<'p> {
     DNMRangeIterator {
-      walker: self.to_sentences().into_iter(),
+      walker: self.sentence_iter(),
+      segmenter: self.segmenter(),
       document: self.get_document(),
     }
   }
@@ -83,8 +96,17 @@ pub trait XPathFilteredIterator<'p> {
 
 impl<'p> XPathFilteredIterator<'p> for ItemDNM<'p> {
   fn get_document(&'p self) -> &Document { self.document }
-  fn to_sentences(&'p self) -> Vec<DNMRange<'p>> {
-    self.document.corpus.tokenizer.sentences(&self.dnm)
+  fn segmenter(&'p self) -> &'p dyn Segmenter { self.document.corpus.tokenizer.for_dnm(&self.dnm) }
+  fn to_sentences(&'p self) -> Vec<DNMRange<'p>> { self.segmenter().sentences(&self.dnm) }
+  fn sentence_iter(&'p self) -> RangeIter<'p> { self.segmenter().sentence_iter(&self.dnm) }
+  fn iter(&'p mut self) -> DNMRangeIterator<'p> {
+    // choose the segmenter once, for both the sentences and their words
+    let segmenter = self.segmenter();
+    DNMRangeIterator {
+      walker: segmenter.sentence_iter(&self.dnm),
+      segmenter,
+      document: self.document,
+    }
   }
 }
 
@@ -98,6 +120,7 @@ impl<'iter> Iterator for DNMRangeIterator<'iter> {
         Some(ItemDNMRange {
           range,
           document: self.document,
+          segmenter: self.segmenter,
         })
       }
     } else {
@@ -109,47 +132,59 @@ impl<'iter> Iterator for DNMRangeIterator<'iter> {
 impl<'s> ItemDNMRange<'s> {
   /// Get an iterator over the words (using rudimentary heuristics)
   pub fn word_iter(&'s mut self) -> DNMRangeIterator<'s> {
-    let tokenizer = &self.document.corpus.tokenizer;
-    let words = tokenizer.words(&self.range);
     DNMRangeIterator {
-      walker: words.into_iter(),
+      walker: self.segmenter.word_iter(&self.range),
+      segmenter: self.segmenter,
       document: self.document,
     }
   }
   /// Get an iterator over the words and punctuation (using rudimentary heuristics)
   pub fn word_and_punct_iter(&'s mut self) -> DNMRangeIterator<'s> {
-    let tokenizer = &self.document.corpus.tokenizer;
-    let words = tokenizer.words_and_punct(&self.range);
     DNMRangeIterator {
-      walker: words.into_iter(),
+      walker: self.segmenter.words_and_punct_iter(&self.range),
+      segmenter: self.segmenter,
       document: self.document,
     }
   }
+  /// Get an iterator over the typed tokens (words, punctuation, math, citations, ...)
+  pub fn token_iter(&'s mut self) -> IntoIter<Token<'s>> {
+    self.segmenter.tokens(&self.range).into_iter()
+  }
 }
 
 impl<'s> ItemDNM<'s> {
   /// Get an iterator over the words (using rudimentary heuristics)
   pub fn word_iter(&'s mut self) -> DNMRangeIterator<'s> {
-    let tokenizer = &self.document.corpus.tokenizer;
-    let words = match self.dnm.get_range() {
-      Ok(range) => tokenizer.words(&range),
-      _ => Vec::new(),
+    let segmenter = self.segmenter();
+    let words: RangeIter<'s> = match self.dnm.get_range() {
+      Ok(range) => segmenter.word_iter(&range),
+      _ => Box::new(iter::empty()),
     };
     DNMRangeIterator {
-      walker: words.into_iter(),
+      walker: words,
+      segmenter,
       document: self.document,
     }
   }
   /// Get an iterator over the words and punctuation (using rudimentary heuristics)
   pub fn word_and_punct_iter(&'s mut self) -> DNMRangeIterator<'s> {
-    let tokenizer = &self.document.corpus.tokenizer;
-    let words = match self.dnm.get_range() {
-      Ok(range) => tokenizer.words_and_punct(&range),
-      _ => Vec::new(),
+    let segmenter = self.segmenter();
+    let words: RangeIter<'s> = match self.dnm.get_range() {
+      Ok(range) => segmenter.words_and_punct_iter(&range),
+      _ => Box::new(iter::empty()),
     };
     DNMRangeIterator {
-      walker: words.into_iter(),
+      walker: words,
+      segmenter,
       document: self.document,
     }
   }
+  /// Get an iterator over the typed tokens (words, punctuation, math, citations, ...)
+  pub fn token_iter(&'s mut self) -> IntoIter<Token<'s>> {
+    let tokens = match self.dnm.get_range() {
+      Ok(range) => self.segmenter().tokens(&range),
+      _ => Vec::new(),
+    };
+    tokens.into_iter()
+  }
 }
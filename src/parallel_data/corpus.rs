@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 use super::document::Document;
 use crate::dnm::DNMParameters;
-use crate::tokenizer::Tokenizer;
+use crate::tokenizer::{Segmenter, Tokenizer};
 
 use libxml::parser::Parser;
 
@@ -17,8 +17,8 @@ pub struct Corpus {
   pub xml_parser: Parser,
   /// document HTML5 parser
   pub html_parser: Parser,
-  /// `DNM`-aware sentence and word tokenizer
-  pub tokenizer: Tokenizer,
+  /// `DNM`-aware sentence and word segmenter, the rule-based `Tokenizer` by default
+  pub tokenizer: Box<dyn Segmenter>,
   /// Default setting for `DNM` generation
   pub dnm_parameters: DNMParameters,
   /// Extension of corpus files (for specially tailored resources such as DLMF's .html5)
@@ -31,7 +31,7 @@ impl Default for Corpus {
     Corpus {
       extension: None,
       path: ".".to_string(),
-      tokenizer: Tokenizer::default(),
+      tokenizer: Box::new(Tokenizer::default()),
       xml_parser: Parser::default(),
       html_parser: Parser::default_html(),
       dnm_parameters: DNMParameters::llamapun_normalization(),
@@ -48,15 +48,19 @@ impl Corpus {
     }
   }
 
-  /// Get a parallel iterator over the documents, returning a single report catalog
-  pub fn catalog_with_parallel_walk<F>(&self, closure: F) -> HashMap<String, u64>
-  where F: Fn(Document) -> HashMap<String, u64> + Send + Sync {
+  /// Use an alternative sentence and word segmenter for the document iterators
+  pub fn set_segmenter<S: Segmenter + 'static>(&mut self, segmenter: S) {
+    self.tokenizer = Box::new(segmenter);
+  }
+
+  /// Helper function: the enumerated paths of the corpus documents, selected by extension
+  fn walk_paths(&self) -> impl Iterator<Item = (usize, String)> + Send + '_ {
     ParWalkDir::new(self.path.clone())
       .num_threads(rayon::current_num_threads())
       .skip_hidden(true)
       .sort(false)
       .into_iter()
-      .filter_map(|each| {
+      .filter_map(move |each| {
         if let Ok(entry) = each {
           let file_name = entry.file_name.to_str().unwrap_or("");
           let selected = if let Some(ref extension) = self.extension {
@@ -75,18 +79,24 @@ impl Corpus {
         None
       })
       .enumerate()
+  }
+
+  /// Helper function: load the `index`-th document of a walk, reporting progress for `walk`
+  fn load_document(&self, index: usize, path: String, walk: &str) -> Document<'_> {
+    let document = Document::new(path, self).unwrap();
+    if index % 1000 == 0 && index > 0 {
+      println!("-- {walk} now processing document {:?}", 1 + index);
+    }
+    document
+  }
+
+  /// Get a parallel iterator over the documents, returning a single report catalog
+  pub fn catalog_with_parallel_walk<F>(&self, closure: F) -> HashMap<String, u64>
+  where F: Fn(Document) -> HashMap<String, u64> + Send + Sync {
+    self
+      .walk_paths()
       .par_bridge()
-      .map(|each| {
-        let (index, path) = each;
-        let document = Document::new(path, self).unwrap();
-        if index % 1000 == 0 && index > 0 {
-          println!(
-            "-- catalog_with_parallel_walk now processing document {:?}",
-            1 + index
-          );
-        }
-        closure(document)
-      })
+      .map(|(index, path)| closure(self.load_document(index, path, "catalog_with_parallel_walk")))
       .reduce(HashMap::new, |mut map1, map2| {
         for (k, v) in map2 {
           let entry = map1.entry(k).or_insert(0);
@@ -99,42 +109,10 @@ impl Corpus {
   /// Get a parallel iterator over the documents, returning a pair of report catalogs
     pub fn catalogs_with_parallel_walk<F>(&self, closure: F) -> (HashMap<String, u64>,HashMap<String, u64>)
   where F: Fn(Document) -> (HashMap<String, u64>,HashMap<String, u64>) + Send + Sync {
-    ParWalkDir::new(self.path.clone())
-      .num_threads(rayon::current_num_threads())
-      .skip_hidden(true)
-      .sort(false)
-      .into_iter()
-      .filter_map(|each| {
-        if let Ok(entry) = each {
-          let file_name = entry.file_name.to_str().unwrap_or("");
-          let selected = if let Some(ref extension) = self.extension {
-            file_name.ends_with(extension)
-          } else {
-            file_name.ends_with(".html") || file_name.ends_with(".xhtml")
-          };
-          if selected {
-            let path = entry.path().to_str().unwrap_or("").to_owned();
-            if !path.is_empty() {
-              return Some(path);
-            }
-          }
-        }
-        // all other cases
-        None
-      })
-      .enumerate()
+    self
+      .walk_paths()
       .par_bridge()
-      .map(|each| {
-        let (index, path) = each;
-        let document = Document::new(path, self).unwrap();
-        if index % 1000 == 0 && index > 0 {
-          println!(
-            "-- catalog_with_parallel_walk now processing document {:?}",
-            1 + index
-          );
-        }
-        closure(document)
-      })
+      .map(|(index, path)| closure(self.load_document(index, path, "catalog_with_parallel_walk")))
       .reduce(|| (HashMap::new(),HashMap::new()), |(mut map11, mut map12), (map21,map22)| {
         for (k, v) in map21 {
           let entry = map11.entry(k).or_insert(0);
@@ -147,4 +125,34 @@ impl Corpus {
         (map11,map12)
       })
   }
+
+  /// Get a parallel iterator over the documents, collecting one result per document
+  pub fn map_with_parallel_walk<T: Send, F>(&self, closure: F) -> Vec<T>
+  where F: Fn(Document) -> T + Send + Sync {
+    self
+      .walk_paths()
+      .par_bridge()
+      .map(|(index, path)| closure(self.load_document(index, path, "map_with_parallel_walk")))
+      .collect()
+  }
+
+  /// Get a parallel iterator over the documents, combining the per-document results with
+  /// `reduce`, starting from `identity`
+  pub fn reduce_with_parallel_walk<T: Send, I, F, R>(
+    &self,
+    identity: I,
+    closure: F,
+    reduce: R,
+  ) -> T
+  where
+    I: Fn() -> T + Send + Sync,
+    F: Fn(Document) -> T + Send + Sync,
+    R: Fn(T, T) -> T + Send + Sync,
+  {
+    self
+      .walk_paths()
+      .par_bridge()
+      .map(|(index, path)| closure(self.load_document(index, path, "reduce_with_parallel_walk")))
+      .reduce(identity, reduce)
+  }
 }
@@ -143,10 +143,11 @@ impl<'d> Document<'d> {
         self.dnm = Some(DNM::new(root, self.corpus.dnm_parameters.clone()));
       }
     }
-    let tokenizer = &self.corpus.tokenizer;
-    let sentences = tokenizer.sentences(self.dnm.as_ref().unwrap());
+    let dnm = self.dnm.as_ref().unwrap();
+    let segmenter = self.corpus.tokenizer.for_dnm(dnm);
     DNMRangeIterator {
-      walker: sentences.into_iter(),
+      walker: segmenter.sentence_iter(dnm),
+      segmenter,
       document: self,
     }
   }
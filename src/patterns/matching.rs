@@ -207,6 +207,47 @@ pub fn match_sentence<'t>(
   Ok(matches)
 }
 
+/// adds the markers of matches to the `"patterns"` layer of an annotation store.
+/// Each annotation records the `"marker"` name, its comma-separated `"tags"`, whether it marks
+/// `"text"` or `"math"` (as `"kind"`), and the index of the top-level `"match"` it belongs to.
+/// Fails without changing the store if a marked math node is not part of the DNM.
+pub fn annotate_matches(
+  matches: &[Match],
+  dnm: &DNM,
+  store: &mut AnnotationStore,
+) -> Result<(), String> {
+  let mut annotations = Vec::new();
+  for (match_index, m) in matches.iter().enumerate() {
+    for marker in m.get_marker_list() {
+      let (start, end, kind, marker) = match marker {
+        MarkerEnum::Text(text_marker) => (
+          text_marker.range.start,
+          text_marker.range.end,
+          "text",
+          text_marker.marker,
+        ),
+        MarkerEnum::Math(math_marker) => {
+          let range = dnm
+            .get_range_of_node(math_marker.node)
+            .map_err(|_| format!("Marked math node not in DNM: {}", math_marker.marker.name))?;
+          (range.start, range.end, "math", math_marker.marker)
+        },
+      };
+      annotations.push(
+        Annotation::new(start, end)
+          .with_attribute("marker", marker.name)
+          .with_attribute("tags", marker.tags.join(","))
+          .with_attribute("kind", kind)
+          .with_attribute("match", match_index),
+      );
+    }
+  }
+  for annotation in annotations {
+    store.add(PATTERN_LAYER, annotation);
+  }
+  Ok(())
+}
+
 fn match_seq<'t>(
   pf: &PatternFile,
   rule: &SequencePattern,
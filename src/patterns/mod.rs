@@ -4,5 +4,5 @@ mod matching;
 mod rules;
 mod utils;
 
-pub use self::matching::{match_sentence, Match};
+pub use self::matching::{annotate_matches, match_sentence, Match};
 pub use self::rules::{MarkerEnum, MathMarker, PatternFile, PatternMarker, TextMarker};
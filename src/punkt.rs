@@ -0,0 +1,349 @@
+//! A Punkt-style unsupervised sentence boundary detector, after Kiss & Strunk (2006),
+//! "Unsupervised Multilingual Sentence Boundary Detection".
+//!
+//! A `PunktTrainer` collects token statistics from plain texts, `DNM`s or a whole
+//! `parallel_data::Corpus`, and learns a `PunktModel` of
+//!  - abbreviations: types strongly associated with a final period (e.g. "thm", "eq", "i.e"),
+//!  - collocations: pairs of a period-final number or initial and a following type, which don't
+//!    span a sentence boundary (e.g. "3. theorem" in "see Section 3. Theorem 2 states"),
+//!  - sentence starters: types frequently following a sentence boundary (e.g. "let", "we").
+//!
+//! Models are saved as JSON, and split `DNM`s into sentences as a drop-in `Segmenter`.
+use crate::dnm::{DNMRange, DNM};
+use crate::parallel_data::Corpus;
+use crate::tokenizer::{RangeIter, Segmenter, Tokenizer};
+use crate::util::data_helpers::whitespace_words;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// The type standing in for all numbers
+const NUMBER_TYPE: &str = "##number##";
+/// Minimal score of abbreviations
+const ABBREVIATION_THRESHOLD: f64 = 0.3;
+/// Minimal log-likelihood of collocations
+const COLLOCATION_THRESHOLD: f64 = 7.88;
+/// Minimal log-likelihood of sentence starters
+const SENTENCE_STARTER_THRESHOLD: f64 = 30.0;
+
+lazy_static! {
+  static ref WORD_TOKENIZER: Tokenizer = Tokenizer::default();
+}
+
+/// Helper function: whether a character opens a bracket or quote
+fn is_opening(c: char) -> bool { matches!(c, '(' | '[' | '{' | '"' | '\'' | '‘' | '“') }
+
+/// Helper function: whether a character closes a bracket or quote
+fn is_closing(c: char) -> bool { matches!(c, ')' | ']' | '}' | '"' | '\'' | '’' | '”') }
+
+/// Helper function: the Punkt type of a token, i.e. its lowercased text without surrounding
+/// brackets, quotes and final period, with numbers collapsed into one type. Also returns whether
+/// the token ends with a period.
+fn token_type(token: &str) -> (String, bool) {
+  let stripped = token.trim_end_matches(is_closing);
+  let period_final = stripped.ends_with('.');
+  let stripped = stripped
+    .strip_suffix('.')
+    .unwrap_or(stripped)
+    .trim_start_matches(is_opening);
+  let is_number = stripped.starts_with(|c: char| c.is_ascii_digit())
+    && stripped
+      .chars()
+      .all(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-'));
+  if is_number {
+    (NUMBER_TYPE.to_string(), period_final)
+  } else {
+    (stripped.to_lowercase(), period_final)
+  }
+}
+
+/// Helper function: the first alphanumeric character of a token
+fn first_alphanumeric(token: &str) -> Option<char> { token.chars().find(|c| c.is_alphanumeric()) }
+
+/// Helper function: whether a type has letters, as needed for abbreviations and sentence starters
+fn is_wordlike(word_type: &str) -> bool {
+  word_type != NUMBER_TYPE && word_type.chars().any(char::is_alphabetic)
+}
+
+/// Helper function: log-likelihood of a type being an abbreviation, i.e. of `count_ab` of its
+/// `count_a` occurrences being period-final, when `count_b` of `n` tokens are period-final
+fn dunning_log_likelihood(count_a: f64, count_b: f64, count_ab: f64, n: f64) -> f64 {
+  let p1 = count_b / n;
+  let p2 = 0.99;
+  let null_hypothesis = count_ab * p1.ln() + (count_a - count_ab) * (1.0 - p1).ln();
+  let alternative_hypothesis = count_ab * p2.ln() + (count_a - count_ab) * (1.0 - p2).ln();
+  -2.0 * (null_hypothesis - alternative_hypothesis)
+}
+
+/// Helper function: log-likelihood of types `a` and `b` occurring together, with counts as in
+/// `dunning_log_likelihood`
+fn collocation_log_likelihood(count_a: f64, count_b: f64, count_ab: f64, n: f64) -> f64 {
+  let ln = |x: f64| x.max(f64::MIN_POSITIVE).ln();
+  let p = count_b / n;
+  let p1 = count_ab / count_a;
+  let p2 = (count_b - count_ab) / (n - count_a);
+  let summand1 = count_ab * ln(p) + (count_a - count_ab) * ln(1.0 - p);
+  let summand2 = (count_b - count_ab) * ln(p) + (n - count_a - count_b + count_ab) * ln(1.0 - p);
+  let summand3 = if count_a == count_ab {
+    0.0
+  } else {
+    count_ab * ln(p1) + (count_a - count_ab) * ln(1.0 - p1)
+  };
+  let summand4 = if count_b == count_ab {
+    0.0
+  } else {
+    (count_b - count_ab) * ln(p2) + (n - count_a - count_b + count_ab) * ln(1.0 - p2)
+  };
+  -2.0 * (summand1 + summand2 - summand3 - summand4)
+}
+
+/// Collects the token statistics a `PunktModel` is learned from
+#[derive(Debug, Clone, Default)]
+pub struct PunktTrainer {
+  /// number of tokens
+  token_count: usize,
+  /// number of period-final tokens
+  period_count: usize,
+  /// occurrences of each type
+  type_counts: HashMap<String, usize>,
+  /// period-final occurrences of each type
+  period_type_counts: HashMap<String, usize>,
+  /// occurrences of each type after each period-final type
+  follower_counts: HashMap<(String, String), usize>,
+}
+
+impl PunktTrainer {
+  /// Create a trainer without statistics
+  pub fn new() -> Self { PunktTrainer::default() }
+
+  /// Number of tokens trained on
+  pub fn token_count(&self) -> usize { self.token_count }
+
+  /// Collect the statistics of a plain text
+  pub fn train_text(&mut self, text: &str) {
+    let mut previous: Option<String> = None;
+    for (token, _, _) in whitespace_words(text) {
+      let (word_type, period_final) = token_type(token);
+      self.token_count += 1;
+      if let Some(previous_type) = previous.take() {
+        *self
+          .follower_counts
+          .entry((previous_type, word_type.clone()))
+          .or_insert(0) += 1;
+      }
+      if period_final {
+        self.period_count += 1;
+        *self
+          .period_type_counts
+          .entry(word_type.clone())
+          .or_insert(0) += 1;
+        previous = Some(word_type.clone());
+      }
+      *self.type_counts.entry(word_type).or_insert(0) += 1;
+    }
+  }
+
+  /// Collect the statistics of the plaintext of a `DNM`
+  pub fn train_dnm(&mut self, dnm: &DNM) { self.train_text(&dnm.plaintext) }
+
+  /// Collect the statistics of the paragraphs of a corpus, in a parallel walk
+  pub fn train_corpus(&mut self, corpus: &Corpus) {
+    let trained = corpus.reduce_with_parallel_walk(
+      PunktTrainer::new,
+      |document| {
+        let mut trainer = PunktTrainer::new();
+        for paragraph in document.paragraph_iter() {
+          trainer.train_dnm(&paragraph.dnm);
+        }
+        trainer
+      },
+      PunktTrainer::merge,
+    );
+    *self = std::mem::take(self).merge(trained);
+  }
+
+  /// Combine the statistics of two trainers
+  pub fn merge(mut self, other: PunktTrainer) -> PunktTrainer {
+    self.token_count += other.token_count;
+    self.period_count += other.period_count;
+    for (word_type, count) in other.type_counts {
+      *self.type_counts.entry(word_type).or_insert(0) += count;
+    }
+    for (word_type, count) in other.period_type_counts {
+      *self.period_type_counts.entry(word_type).or_insert(0) += count;
+    }
+    for (pair, count) in other.follower_counts {
+      *self.follower_counts.entry(pair).or_insert(0) += count;
+    }
+    self
+  }
+
+  /// Learn a model from the collected statistics
+  pub fn finalize(&self) -> PunktModel {
+    let mut model = PunktModel::default();
+    if self.token_count == 0 || self.period_count == 0 {
+      return model;
+    }
+    let n = self.token_count as f64;
+
+    for (word_type, &period_count) in &self.period_type_counts {
+      if !is_wordlike(word_type) {
+        continue;
+      }
+      let count = self.type_counts[word_type];
+      let periods = word_type.matches('.').count() + 1;
+      let non_periods = word_type.chars().count() + 1 - periods;
+      let log_likelihood = dunning_log_likelihood(
+        count as f64,
+        self.period_count as f64,
+        period_count as f64,
+        n,
+      );
+      let length_factor = (-(non_periods as f64)).exp();
+      let penalty = (non_periods as f64).powi(-((count - period_count) as i32));
+      if log_likelihood * length_factor * periods as f64 * penalty >= ABBREVIATION_THRESHOLD {
+        model.abbreviations.insert(word_type.clone());
+      }
+    }
+
+    let mut break_count = 0;
+    let mut starter_counts: HashMap<&str, usize> = HashMap::new();
+    for (word_type, &period_count) in &self.period_type_counts {
+      if !model.abbreviations.contains(word_type) {
+        break_count += period_count;
+      }
+    }
+    for ((previous, next), &count) in &self.follower_counts {
+      if !model.abbreviations.contains(previous) {
+        *starter_counts.entry(next).or_insert(0) += count;
+      }
+      // numbers and initials followed by a type they are not separated from
+      let is_initial = previous.chars().count() == 1 && is_wordlike(previous);
+      if (previous == NUMBER_TYPE || is_initial) && next.chars().any(char::is_alphanumeric) {
+        let previous_count = self.type_counts[previous] as f64;
+        let next_count = self.type_counts[next] as f64;
+        let log_likelihood =
+          collocation_log_likelihood(previous_count, next_count, count as f64, n);
+        if log_likelihood >= COLLOCATION_THRESHOLD && n / previous_count > next_count / count as f64
+        {
+          model.collocations.insert((previous.clone(), next.clone()));
+        }
+      }
+    }
+    if break_count > 0 {
+      for (next, count) in starter_counts {
+        if !is_wordlike(next) {
+          continue;
+        }
+        let next_count = self.type_counts[next] as f64;
+        let log_likelihood =
+          collocation_log_likelihood(break_count as f64, next_count, count as f64, n);
+        if log_likelihood >= SENTENCE_STARTER_THRESHOLD
+          && n / break_count as f64 > next_count / count as f64
+        {
+          model.sentence_starters.insert(next.to_string());
+        }
+      }
+    }
+    model
+  }
+}
+
+/// A trained Punkt model, splitting texts into sentences
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PunktModel {
+  /// Types followed by a period which doesn't end a sentence, unless a sentence starter follows
+  pub abbreviations: BTreeSet<String>,
+  /// Pairs of period-final and following types which don't span a sentence boundary
+  pub collocations: BTreeSet<(String, String)>,
+  /// Types frequently starting a sentence
+  pub sentence_starters: BTreeSet<String>,
+}
+
+impl PunktModel {
+  /// Write the model to a JSON file
+  pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(writer, self)?;
+    Ok(())
+  }
+
+  /// Read a model from a JSON file
+  pub fn load(path: &str) -> Result<PunktModel, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+  }
+
+  /// Whether a sentence ends with `token`, given the `next` token
+  pub fn is_sentence_break(&self, token: &str, next: Option<&str>) -> bool {
+    let stripped = token.trim_end_matches(is_closing);
+    let next = match next {
+      Some(next) => next,
+      None => return stripped.ends_with(['.', '?', '!']),
+    };
+    let next_lowercase = first_alphanumeric(next).is_some_and(char::is_lowercase);
+    if stripped.ends_with(['?', '!']) {
+      return !next_lowercase;
+    }
+    if !stripped.ends_with('.') {
+      return false;
+    }
+    let (word_type, _) = token_type(token);
+    let (next_type, _) = token_type(next);
+    if self.abbreviations.contains(&word_type) {
+      // an abbreviation ends a sentence only if a sentence starter follows
+      let next_uppercase = first_alphanumeric(next).is_some_and(char::is_uppercase);
+      next_uppercase && self.sentence_starters.contains(&next_type)
+    } else {
+      !next_lowercase && !self.collocations.contains(&(word_type, next_type))
+    }
+  }
+
+  /// Split a text into the character offsets of its sentences
+  pub fn sentence_offsets(&self, text: &str) -> Vec<(usize, usize)> {
+    let tokens = whitespace_words(text);
+    let mut sentences = Vec::new();
+    let mut start = match tokens.first() {
+      Some(&(_, start, _)) => start,
+      None => return sentences,
+    };
+    for (index, &(token, _, end)) in tokens.iter().enumerate() {
+      let next = tokens.get(index + 1);
+      if self.is_sentence_break(token, next.map(|next| next.0)) || next.is_none() {
+        sentences.push((start, end));
+        if let Some(next) = next {
+          start = next.1;
+        }
+      }
+    }
+    sentences
+  }
+
+  /// Split the plaintext of a `DNM` into sentences
+  pub fn sentences<'a>(&self, dnm: &'a DNM) -> Vec<DNMRange<'a>> {
+    self
+      .sentence_offsets(&dnm.plaintext)
+      .into_iter()
+      .map(|(start, end)| DNMRange { start, end, dnm }.trim())
+      .filter(|range| !range.is_empty())
+      .collect()
+  }
+}
+
+/// Sentences are split by the model, words as by the default `Tokenizer`
+impl Segmenter for PunktModel {
+  fn sentences<'a>(&self, dnm: &'a DNM) -> Vec<DNMRange<'a>> { PunktModel::sentences(self, dnm) }
+  fn words<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> { WORD_TOKENIZER.words(range) }
+  fn words_and_punct<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> {
+    WORD_TOKENIZER.words_and_punct(range)
+  }
+  fn for_dnm(&self, _dnm: &DNM) -> &dyn Segmenter { self }
+  fn word_iter<'b>(&'b self, range: &DNMRange<'b>) -> RangeIter<'b> {
+    Box::new(WORD_TOKENIZER.word_iter(range))
+  }
+  fn words_and_punct_iter<'b>(&'b self, range: &DNMRange<'b>) -> RangeIter<'b> {
+    Box::new(WORD_TOKENIZER.words_and_punct_iter(range))
+  }
+}
@@ -2,8 +2,275 @@
 //! Stopwords are words frequent words like "the", "it", "then", which would add too much noise
 //! to certain statistical methods
 use std::collections::HashSet;
+use whatlang::Lang;
 
-/// Load a set of stopwords
+/// French stopwords
+const FRENCH: &[&str] = &[
+  "a",
+  "afin",
+  "ai",
+  "ainsi",
+  "alors",
+  "au",
+  "aucun",
+  "aucune",
+  "auquel",
+  "aussi",
+  "autre",
+  "autres",
+  "aux",
+  "avant",
+  "avec",
+  "avoir",
+  "c",
+  "car",
+  "ce",
+  "ceci",
+  "cela",
+  "celle",
+  "celles",
+  "celui",
+  "cependant",
+  "ces",
+  "cet",
+  "cette",
+  "ceux",
+  "chaque",
+  "comme",
+  "d",
+  "dans",
+  "de",
+  "depuis",
+  "des",
+  "donc",
+  "dont",
+  "du",
+  "elle",
+  "elles",
+  "en",
+  "encore",
+  "entre",
+  "est",
+  "et",
+  "été",
+  "être",
+  "eux",
+  "il",
+  "ils",
+  "j",
+  "je",
+  "l",
+  "la",
+  "laquelle",
+  "le",
+  "lequel",
+  "les",
+  "lesquels",
+  "leur",
+  "leurs",
+  "lorsque",
+  "lui",
+  "m",
+  "mais",
+  "me",
+  "même",
+  "mêmes",
+  "n",
+  "ne",
+  "ni",
+  "nous",
+  "on",
+  "ont",
+  "or",
+  "ou",
+  "où",
+  "par",
+  "parce",
+  "pas",
+  "peu",
+  "peut",
+  "plus",
+  "pour",
+  "pourquoi",
+  "puis",
+  "qu",
+  "quand",
+  "que",
+  "quel",
+  "quelle",
+  "quelles",
+  "quels",
+  "qui",
+  "s",
+  "sa",
+  "sans",
+  "se",
+  "selon",
+  "ses",
+  "si",
+  "sinon",
+  "soit",
+  "son",
+  "sont",
+  "sous",
+  "sur",
+  "t",
+  "ta",
+  "te",
+  "tel",
+  "telle",
+  "tels",
+  "tes",
+  "toi",
+  "ton",
+  "tous",
+  "tout",
+  "toute",
+  "toutes",
+  "très",
+  "tu",
+  "un",
+  "une",
+  "vers",
+  "vos",
+  "votre",
+  "vous",
+  "y",
+];
+
+/// German stopwords
+const GERMAN: &[&str] = &[
+  "aber", "alle", "allem", "allen", "aller", "alles", "als", "also", "am", "an", "ander", "andere",
+  "anderen", "auch", "auf", "aus", "bei", "bis", "bzw", "da", "dabei", "damit", "dann", "das",
+  "dass", "dem", "den", "denn", "der", "des", "dessen", "die", "dies", "diese", "diesem", "diesen",
+  "dieser", "dieses", "doch", "dort", "durch", "ein", "eine", "einem", "einen", "einer", "eines",
+  "er", "es", "etwa", "für", "gegen", "hier", "hat", "haben", "ich", "ihr", "ihre", "im", "in",
+  "indem", "ist", "jede", "jedem", "jeden", "jeder", "jedes", "jedoch", "kann", "kein", "keine",
+  "man", "mit", "muss", "nach", "nicht", "noch", "nun", "nur", "ob", "oder", "ohne", "sehr", "sei",
+  "seine", "seinem", "seinen", "seiner", "sich", "sie", "sind", "so", "sodass", "somit", "sowie",
+  "über", "um", "und", "uns", "unter", "von", "vor", "war", "waren", "was", "weil", "welche",
+  "welchem", "welchen", "welcher", "wenn", "werden", "wie", "wir", "wird", "wo", "wobei", "zu",
+  "zum", "zur", "zwischen",
+];
+
+/// Spanish stopwords
+const SPANISH: &[&str] = &[
+  "a", "al", "algo", "algunas", "algunos", "ante", "antes", "así", "aunque", "bajo", "cada",
+  "como", "con", "cual", "cuales", "cuando", "de", "del", "desde", "donde", "dos", "e", "el",
+  "ella", "ellas", "ellos", "en", "entonces", "entre", "era", "es", "esa", "esas", "ese", "eso",
+  "esos", "esta", "está", "están", "estas", "este", "esto", "estos", "fue", "ha", "hay", "la",
+  "las", "le", "les", "lo", "los", "más", "mismo", "muy", "ni", "no", "nos", "o", "otra", "otras",
+  "otro", "otros", "para", "pero", "por", "porque", "pues", "que", "qué", "se", "sea", "según",
+  "ser", "si", "sí", "sin", "sino", "sobre", "son", "su", "sus", "también", "tanto", "todo",
+  "todos", "tras", "u", "un", "una", "uno", "unos", "y", "ya",
+];
+
+/// Russian stopwords
+const RUSSIAN: &[&str] = &[
+  "а",
+  "без",
+  "более",
+  "бы",
+  "был",
+  "была",
+  "были",
+  "было",
+  "быть",
+  "в",
+  "вам",
+  "вас",
+  "весь",
+  "во",
+  "вот",
+  "все",
+  "всех",
+  "вы",
+  "где",
+  "да",
+  "для",
+  "до",
+  "его",
+  "ее",
+  "её",
+  "если",
+  "есть",
+  "еще",
+  "ещё",
+  "же",
+  "за",
+  "здесь",
+  "и",
+  "из",
+  "или",
+  "им",
+  "их",
+  "к",
+  "как",
+  "когда",
+  "которая",
+  "которые",
+  "который",
+  "кроме",
+  "ли",
+  "между",
+  "меня",
+  "мы",
+  "на",
+  "над",
+  "не",
+  "него",
+  "нет",
+  "ни",
+  "но",
+  "о",
+  "об",
+  "однако",
+  "он",
+  "она",
+  "они",
+  "оно",
+  "от",
+  "по",
+  "под",
+  "при",
+  "с",
+  "со",
+  "так",
+  "также",
+  "такой",
+  "там",
+  "то",
+  "того",
+  "тоже",
+  "только",
+  "том",
+  "у",
+  "уже",
+  "чем",
+  "через",
+  "что",
+  "чтобы",
+  "это",
+  "этого",
+  "этой",
+  "этом",
+  "эти",
+  "этот",
+];
+
+/// Load the set of stopwords of a language, or `None` if the language is not supported
+pub fn load_language<'a>(lang: Lang) -> Option<HashSet<&'a str>> {
+  match lang {
+    Lang::Eng => Some(load()),
+    Lang::Fra => Some(FRENCH.iter().cloned().collect()),
+    Lang::Deu => Some(GERMAN.iter().cloned().collect()),
+    Lang::Spa => Some(SPANISH.iter().cloned().collect()),
+    Lang::Rus => Some(RUSSIAN.iter().cloned().collect()),
+    _ => None,
+  }
+}
+
+/// Load a set of (English) stopwords
 /// Annoyingly, `HashSet`s are not allowed as static variables in Rust (yet?)
 pub fn load<'a>() -> HashSet<&'a str> {
   [
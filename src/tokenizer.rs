@@ -1,14 +1,17 @@
 //! Provides functionality for tokenizing sentences and words
-use crate::dnm::{DNMRange, DNM};
+use crate::dnm::{Annotation, AnnotationStore, DNMRange, DNM, SENTENCE_LAYER, WORD_LAYER};
 use crate::stopwords;
-use std::cmp;
+use crate::tokenizer::token::{typed_tokens, Token};
 use std::collections::vec_deque::*;
 use std::collections::HashSet;
-use std::iter::Peekable;
-use std::str::Chars;
+use std::str::{CharIndices, Chars};
 
 use regex::Regex;
 
+pub mod evaluation;
+pub mod language;
+pub mod token;
+
 /// Stores auxiliary resources required by the tokenizer so that they need to be initialized only
 /// once
 pub struct Tokenizer {
@@ -16,16 +19,78 @@ pub struct Tokenizer {
   pub stopwords: HashSet<&'static str>,
   /// regular expression for abbreviations
   pub abbreviations: Regex,
+  /// elided words, which keep their apostrophe in `words_and_punct` (e.g. "l" in "l'équation")
+  pub elisions: HashSet<&'static str>,
+  /// contracted word endings, which keep their apostrophe in `words_and_punct` (e.g. "t" in
+  /// "isn't")
+  pub contractions: HashSet<&'static str>,
 }
 impl Default for Tokenizer {
   fn default() -> Tokenizer {
     Tokenizer {
       stopwords : stopwords::load(),
       abbreviations : Regex::new(r"^(?:C(?:[ft]|o(?:n[jn]|lo?|rp)?|a(?:l(?:if)?|pt)|mdr|p?l|res)|M(?:[dst]|a(?:[jnry]|ss)|i(?:ch|nn|ss)|o(?:nt)?|ex?|rs?)|A(?:r(?:[ck]|iz)|l(?:t?a)?|ttys?|ssn|dm|pr|ug|ve)|c(?:o(?:rp|l)?|(?:ap)?t|mdr|p?l|res|f)|S(?:e(?:ns?|pt?|c)|(?:up|g)?t|ask|r)|s(?:e(?:ns?|pt?|c)|(?:up|g)?t|r)|a(?:ttys?|ssn|dm|pr|rc|ug|ve|l)|P(?:enna?|-a.s|de?|lz?|rof|a)|D(?:e(?:[cfl]|p?t)|ist|ak|r)|I(?:[as]|n[cd]|da?|.e|ll)|F(?:e[bd]|w?y|ig|la|t)|O(?:k(?:la)?|[cn]t|re)|d(?:e(?:p?t|c)|ist|r)|E(?:xpy?|.g|sp|tc|qs?)|R(?:e(?:ps?|sp|v)|d)|T(?:e(?:nn|x)|ce|hm)|e(?:xpy?|.g|sp|tc|qs?)|m(?:[st]|a[jry]|rs?)|r(?:e(?:ps?|sp|v)|d)|N(?:e(?:br?|v)|ov?)|W(?:isc?|ash|yo?)|f(?:w?y|eb|ig|t)|p(?:de?|lz?|rof)|J(?:u[ln]|an|r)|U(?:SAFA|niv|t)|j(?:u[ln]|an|r)|K(?:ans?|en|y)|B(?:lv?d|ros)|b(?:lv?d|ros)|G(?:en|ov|a)|L(?:td?|a)|g(?:en|ov)|i(?:.e|nc)|l(?:td?|a)|[Hh]wa?y|V[ast]|Que|nov?|univ|Yuk|oct|tce|vs)\s?$").unwrap(),
+      elisions : HashSet::new(),
+      contractions : ["t", "s", "un", "th", "ll", "d", "ve", "il", "re", "m"].iter().cloned().collect(),
     }
   }
 }
 
+/// A `DNM`-aware segmentation into sentences and words, as used by the corpus iterators.
+/// `Tokenizer` is the default, rule-based implementation.
+pub trait Segmenter: Send + Sync {
+  /// gets the sentences from a dnm
+  fn sentences<'a>(&self, dnm: &'a DNM) -> Vec<DNMRange<'a>>;
+  /// returns the words of a range, usually a sentence
+  fn words<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>>;
+  /// returns the words and punctuation of a range, usually a sentence
+  fn words_and_punct<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>>;
+  /// returns the segmenter for the ranges of a dnm, e.g. the profile of the dnm's language.
+  /// Segmenting many ranges of the same dnm should go through it, so that it is chosen once.
+  fn for_dnm(&self, dnm: &DNM) -> &dyn Segmenter;
+  /// returns the typed tokens of a range, usually a sentence: its words and punctuation, split
+  /// and typed by the normalized elements they cover (see `tokenizer::token`)
+  fn tokens<'b>(&self, range: &DNMRange<'b>) -> Vec<Token<'b>> {
+    typed_tokens(range, self.words_and_punct(range))
+  }
+  /// returns a lazy iterator over the sentences of a dnm, collecting `sentences` unless
+  /// implemented lazily
+  fn sentence_iter<'a>(&'a self, dnm: &'a DNM) -> RangeIter<'a> {
+    Box::new(self.sentences(dnm).into_iter())
+  }
+  /// returns a lazy iterator over the words of a range, collecting `words` unless implemented
+  /// lazily
+  fn word_iter<'b>(&'b self, range: &DNMRange<'b>) -> RangeIter<'b> {
+    Box::new(self.words(range).into_iter())
+  }
+  /// returns a lazy iterator over the words and punctuation of a range, collecting
+  /// `words_and_punct` unless implemented lazily
+  fn words_and_punct_iter<'b>(&'b self, range: &DNMRange<'b>) -> RangeIter<'b> {
+    Box::new(self.words_and_punct(range).into_iter())
+  }
+}
+
+/// A boxed lazy iterator over ranges, as returned by the iterators of a `Segmenter`
+pub type RangeIter<'a> = Box<dyn Iterator<Item = DNMRange<'a>> + 'a>;
+
+impl Segmenter for Tokenizer {
+  fn sentences<'a>(&self, dnm: &'a DNM) -> Vec<DNMRange<'a>> { Tokenizer::sentences(self, dnm) }
+  fn words<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> { Tokenizer::words(self, range) }
+  fn words_and_punct<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> {
+    Tokenizer::words_and_punct(self, range)
+  }
+  fn for_dnm(&self, _dnm: &DNM) -> &dyn Segmenter { self }
+  fn sentence_iter<'a>(&'a self, dnm: &'a DNM) -> RangeIter<'a> {
+    Box::new(Tokenizer::sentence_iter(self, dnm))
+  }
+  fn word_iter<'b>(&'b self, range: &DNMRange<'b>) -> RangeIter<'b> {
+    Box::new(Tokenizer::word_iter(self, range))
+  }
+  fn words_and_punct_iter<'b>(&'b self, range: &DNMRange<'b>) -> RangeIter<'b> {
+    Box::new(Tokenizer::words_and_punct_iter(self, range))
+  }
+}
+
 // fn is_alphabetic_and_uppercase(c_opt: Option<&char>) -> bool {
 //   if let Some(c) = c_opt {
 //     c.is_alphabetic() && c.is_uppercase()
@@ -34,10 +99,18 @@ impl Default for Tokenizer {
 //   }
 // }
 
+/// Size of the window of characters left of a candidate sentence boundary: the longest
+/// abbreviation is 6 characters, but "mathformula" is 11, so max string + 1
+const WINDOW_SIZE: usize = 12;
+/// Maximal length of the word following a candidate sentence boundary, in characters
+const MAX_NEXT_WORD: usize = 20;
+/// Maximal length of an elision, in bytes once lowercased
+const MAX_ELISION: usize = 32;
+
 /// detects a wordlike sequence with *any* uppercase char, such as "foobaR"
-fn wordlike_with_upper_next(peekable: Peekable<Chars>) -> bool {
+fn wordlike_with_upper_next(chars: Chars) -> bool {
   let mut detected = false;
-  for char in peekable {
+  for char in chars {
     if !char.is_alphabetic() {
       break;
     }
@@ -49,320 +122,509 @@ fn wordlike_with_upper_next(peekable: Peekable<Chars>) -> bool {
   detected
 }
 
+/// Helper function: lowercases a word into a stack buffer, `None` if it doesn't fit
+fn lowercase_into<'buf>(word: &str, buffer: &'buf mut [u8]) -> Option<&'buf str> {
+  let mut length = 0;
+  for c in word.chars().flat_map(char::to_lowercase) {
+    if length + c.len_utf8() > buffer.len() {
+      return None;
+    }
+    length += c.encode_utf8(&mut buffer[length..]).len();
+  }
+  std::str::from_utf8(&buffer[..length]).ok()
+}
+
 impl Tokenizer {
   fn abbreviation_check(&self, left_window: &VecDeque<char>) -> bool {
-    // Check for abbreviations:
-    // Longest abbreviation is 6 characters, but mathformula is 11, so take window
-    // of window_size chars to the left (allow for a space before dot)
-    let lw_string: String = left_window.clone().into_iter().collect();
-    let lw_str: &str = &lw_string;
-    let lw_opt = lw_str.trim().split(|c: char| !c.is_alphabetic()).last();
-    let lw_word: &str = match lw_opt {
-      None => lw_str,
-      Some(w) => w,
-    };
+    // Check for abbreviations, in the last alphabetic word of the window to the left (allowing
+    // for a space before the dot). The word is copied into a stack buffer, as the window is a
+    // ring.
+    let mut buffer = [0u8; 4 * WINDOW_SIZE];
+    let mut word_start = buffer.len();
+    let word_chars = left_window
+      .iter()
+      .rev()
+      .skip_while(|c| c.is_whitespace())
+      .take_while(|c| c.is_alphabetic());
+    for c in word_chars {
+      word_start -= c.len_utf8();
+      c.encode_utf8(&mut buffer[word_start..]);
+    }
+    let lw_word = std::str::from_utf8(&buffer[word_start..]).unwrap_or("");
     // Don't consider single letters followed by a punctuation sign an end of a
     // sentence, Also "a.m." and "p.m." shouldn't get split
-    ((lw_word.len() == 1) && (lw_word != "I")) || self.abbreviations.is_match(lw_word)
+    ((lw_word.chars().count() == 1) && (lw_word != "I")) || self.abbreviations.is_match(lw_word)
+  }
+
+  /// checks whether a word of at most `MAX_NEXT_WORD` characters is a stopword, in any case
+  fn is_stopword(&self, word: &str) -> bool {
+    let mut buffer = [0u8; 3 * 4 * MAX_NEXT_WORD];
+    lowercase_into(word, &mut buffer).is_some_and(|lc| self.stopwords.contains(lc))
+  }
+
+  /// checks whether a word is an elision, in any case
+  fn is_elision(&self, word: &str) -> bool {
+    if self.elisions.is_empty() {
+      return false;
+    }
+    let mut buffer = [0u8; MAX_ELISION];
+    lowercase_into(word, &mut buffer).is_some_and(|lc| self.elisions.contains(lc))
+  }
+
+  /// returns a lazy iterator over the sentences of a dnm, which finds each sentence on demand
+  /// with a bounded window of lookbehind
+  pub fn sentence_iter<'t, 'a>(&'t self, dnm: &'a DNM) -> SentenceIter<'t, 'a> {
+    SentenceIter {
+      tokenizer: self,
+      dnm,
+      chars: dnm.plaintext.chars(),
+      start: 0,
+      end: 0,
+      left_window: VecDeque::with_capacity(WINDOW_SIZE),
+      done: false,
+    }
   }
 
-  // TODO: Reduce complexity, this tokenization pass is terribly overengineered
   /// gets the sentences from a dnm
-  #[allow(clippy::cognitive_complexity)]
   pub fn sentences<'a>(&self, dnm: &'a DNM) -> Vec<DNMRange<'a>> {
-    let text = &dnm.plaintext;
-    let mut sentences: Vec<DNMRange<'a>> = Vec::new();
-    let mut text_iterator = text.chars().peekable();
-    let mut start = 0;
-    let mut end = 0;
-    let window_size = 12; // size of max string + 1
-    let mut left_window: VecDeque<char> = VecDeque::with_capacity(window_size);
-
-    while let Some(sentence_char) = text_iterator.next() {
-      // Bookkeep the end position
-      end += sentence_char.len_utf8();
-
-      match sentence_char {
-        '.' | ':' => {
-          // Baseline condition - only split when we have a following word-ish string with an
-          // uppercase letter Get next non-space, non-quote character
-          while text_iterator.peek().unwrap_or(&'.').is_whitespace()
-            || text_iterator.peek() == Some(&'\'')
-          {
-            let space_char = text_iterator.next().unwrap();
-            end += space_char.len_utf8();
-          }
-          if text_iterator.peek().is_none() {
-            break;
-          }
-          // Uppercase next?
-          if wordlike_with_upper_next(text_iterator.clone()) {
-            // Ok, uppercase, but is it a stopword? If so, we must ALWAYS break the
-            // sentence:
-            let (next_word_string, next_word_length) = next_word_with_length(&mut text_iterator);
-            let next_word_lc = next_word_string.to_lowercase();
+    self.sentence_iter(dnm).collect()
+  }
+
+  /// returns a lazy iterator over the words of a sentence, see `words`
+  pub fn word_iter<'t, 'b>(&'t self, sentence_range: &DNMRange<'b>) -> WordIter<'t, 'b> {
+    let text = sentence_range.get_plaintext();
+    WordIter {
+      tokenizer: self,
+      range: sentence_range.clone(),
+      text,
+      chars: text.char_indices(),
+      start: 0,
+    }
+  }
+
+  /// returns the words of a sentence using simple heuristics
+  pub fn words<'b>(&self, sentence_range: &DNMRange<'b>) -> Vec<DNMRange<'b>> {
+    self.word_iter(sentence_range).collect()
+  }
+
+  /// adds the sentences of a dnm to the `"sentences"` layer of an annotation store, returning
+  /// them
+  pub fn annotate_sentences<'a>(
+    &self,
+    dnm: &'a DNM,
+    store: &mut AnnotationStore,
+  ) -> Vec<DNMRange<'a>> {
+    let sentences = self.sentences(dnm);
+    let layer = store.layer_mut(SENTENCE_LAYER);
+    for sentence in &sentences {
+      layer.insert(Annotation::from_range(sentence));
+    }
+    sentences
+  }
+
+  /// adds the words of a sentence to the `"words"` layer of an annotation store, each with the
+  /// index of the word in its sentence as the `"index"` attribute, returning them
+  pub fn annotate_words<'b>(
+    &self,
+    sentence_range: &DNMRange<'b>,
+    store: &mut AnnotationStore,
+  ) -> Vec<DNMRange<'b>> {
+    let words = self.words(sentence_range);
+    let layer = store.layer_mut(WORD_LAYER);
+    for (index, word) in words.iter().enumerate() {
+      layer.insert(Annotation::from_range(word).with_attribute("index", index));
+    }
+    words
+  }
+
+  /// returns a lazy iterator over the words and punctuation of a sentence, see `words_and_punct`
+  pub fn words_and_punct_iter<'t, 'b>(&'t self, range: &DNMRange<'b>) -> WordAndPunctIter<'t, 'b> {
+    let text = range.get_plaintext();
+    WordAndPunctIter {
+      tokenizer: self,
+      range: range.clone(),
+      text,
+      chars: text.chars(),
+      start: 0,
+      end: 0,
+      apostrophe_flag: false,
+      pending: [(0, 0); 3],
+      pending_len: 0,
+      pending_index: 0,
+      done: false,
+    }
+  }
+
+  /// returns the words and punctuation of a sentence, using simple heuristics
+  pub fn words_and_punct<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> {
+    self.words_and_punct_iter(range).collect()
+  }
+}
+
+/// A lazy iterator over the sentences of a `DNM`, see `Tokenizer::sentence_iter`
+pub struct SentenceIter<'t, 'a> {
+  tokenizer: &'t Tokenizer,
+  dnm: &'a DNM,
+  /// the plaintext left to segment
+  chars: Chars<'a>,
+  /// start of the current sentence, in characters
+  start: usize,
+  /// characters consumed so far
+  end: usize,
+  /// the last characters of the current sentence
+  left_window: VecDeque<char>,
+  done: bool,
+}
+
+impl<'a> SentenceIter<'_, 'a> {
+  fn peek(&self) -> Option<char> { self.chars.clone().next() }
+
+  /// adds a character to the left window, dropping the oldest one when full
+  fn remember(&mut self, c: char) {
+    self.left_window.push_back(c);
+    if self.left_window.len() >= WINDOW_SIZE {
+      self.left_window.pop_front();
+    }
+  }
+
+  /// ends the current sentence at `end`, where the next one starts, and resets the left window
+  fn cut(&mut self, end: usize) -> DNMRange<'a> {
+    self.left_window.clear();
+    let sentence = DNMRange {
+      start: self.start,
+      end,
+      dnm: self.dnm,
+    }
+    .trim();
+    self.start = end;
+    sentence
+  }
+
+  /// Consumes the next word, where only alphabetic characters are accepted, and a max length of
+  /// `MAX_NEXT_WORD` characters is imposed. Returns the word with its length in characters.
+  fn next_word(&mut self) -> (&'a str, usize) {
+    let rest = self.chars.as_str();
+    let mut next_word_length = 0;
+    let mut byte_length = 0;
+    for word_char in rest.chars() {
+      if next_word_length >= MAX_NEXT_WORD || !word_char.is_alphabetic() {
+        break;
+      }
+      next_word_length += 1;
+      byte_length += word_char.len_utf8();
+    }
+    self.chars = rest[byte_length..].chars();
+    (&rest[..byte_length], next_word_length)
+  }
+
+  // TODO: Reduce complexity, this tokenization pass is terribly overengineered
+  /// consumes a character, returning a sentence if it ends one
+  #[allow(clippy::cognitive_complexity)]
+  fn step(&mut self, sentence_char: char) -> Option<DNMRange<'a>> {
+    // Bookkeep the end position, in characters
+    self.end += 1;
+
+    match sentence_char {
+      '.' | ':' => {
+        // Baseline condition - only split when we have a following word-ish string with an
+        // uppercase letter Get next non-space, non-quote character
+        while self.peek().is_some_and(|c| c.is_whitespace() || c == '\'') {
+          self.chars.next();
+          self.end += 1;
+        }
+        // at the end of the text, the last sentence is completed by `next`
+        let next_char = self.peek()?;
+        // Uppercase next?
+        if wordlike_with_upper_next(self.chars.clone()) {
+          // Ok, uppercase, but is it a stopword? If so, we must ALWAYS break the
+          // sentence:
+          let (next_word, next_word_length) = self.next_word();
+          let sentence = if self.tokenizer.is_stopword(next_word) {
             // Always break the sentence when we see a stopword
-            if self.stopwords.contains(next_word_lc.as_str()) {
-              // Reset the left window
-              left_window = VecDeque::with_capacity(window_size);
-              // New sentence
-              sentences.push(DNMRange { start, end, dnm }.trim());
-              start = end;
-            } else {
-              // Regular word case.
-              if self.abbreviation_check(&left_window) {
-                left_window.push_back('.');
-                if left_window.len() >= window_size {
-                  left_window.pop_front();
-                }
-              }
-              //TODO: Handle dot-dot-dot "..."
-              else {
-                // Not a special case, break the sentence
-                // Reset the left window
-                left_window = VecDeque::with_capacity(window_size);
-                // New sentence
-                sentences.push(DNMRange { start, end, dnm }.trim());
-                start = end;
-              }
-              // We consumed the next word, so make sure we reflect that in either case:
-              for next_word_char in next_word_string.chars() {
-                left_window.push_back(next_word_char);
-                if left_window.len() >= window_size {
-                  left_window.pop_front();
-                }
-              }
-            }
-            end += next_word_length;
+            Some(self.cut(self.end))
           } else {
-            // lowercase and non-alphanum characters
-            match text_iterator.peek() {
-              Some(&'*') | Some(&'"') | Some(&'(') => {
-                // Reset the left window
-                left_window = VecDeque::with_capacity(window_size);
-                // New sentence
-                sentences.push(DNMRange { start, end, dnm }.trim());
-                start = end;
-              },
-              Some(&c) => {
-                if sentence_char == '.' && c.is_alphabetic() {
-                  let (next_word_string, next_word_length) =
-                    next_word_with_length(&mut text_iterator);
-                  // TODO: Maybe extend to more lowercase stopwords here? unclear...
-                  if next_word_string.to_lowercase().starts_with("mathformula")
-                    && !self.abbreviation_check(&left_window)
-                  {
-                    // Reset the left window
-                    left_window = VecDeque::with_capacity(window_size);
-                    // New sentence
-                    sentences.push(DNMRange { start, end, dnm }.trim());
-                    start = end;
-                  } else {
-                    left_window.push_back('.');
-                    if left_window.len() >= window_size {
-                      left_window.pop_front();
-                    }
-                  }
-                  // We consumed the next word, so make sure we reflect that in either case:
-                  for next_word_char in next_word_string.chars() {
-                    left_window.push_back(next_word_char);
-                    if left_window.len() >= window_size {
-                      left_window.pop_front();
-                    }
-                  }
-                  end += next_word_length;
-                } else {
-                  left_window.push_back('.');
-                  if left_window.len() >= window_size {
-                    left_window.pop_front();
-                  }
-                }
-              },
-              None => {
-                left_window.push_back('.');
-                if left_window.len() >= window_size {
-                  left_window.pop_front();
-                }
-              },
-            }
-          }
-        },
-        '?' | '!' => {
-          if !is_bounded(left_window.back(), text_iterator.peek()) {
-            // Reset the left window
-            left_window = VecDeque::with_capacity(window_size);
-            // New sentence
-            sentences.push(DNMRange { start, end, dnm }.trim());
-            start = end;
-          }
-        },
-        // TODO:
-        // Some('\u{2022}'),Some('*') => { // bullet point for itemize
-        // Some('\u{220e}') => { // QED symbol
-        '\n' => {
-          // newline
-          if let Some(&'\n') = text_iterator.peek() {
-            // second newline
-            // Get next non-space character
-            while text_iterator.peek().unwrap_or(&'.').is_whitespace() {
-              let space_char = text_iterator.next().unwrap();
-              end += space_char.len_utf8();
+            // Regular word case.
+            let sentence = if self.tokenizer.abbreviation_check(&self.left_window) {
+              self.remember('.');
+              None
             }
-            if text_iterator.peek().is_none() {
-              break;
+            //TODO: Handle dot-dot-dot "..."
+            else {
+              // Not a special case, break the sentence
+              Some(self.cut(self.end))
+            };
+            // We consumed the next word, so make sure we reflect that in either case:
+            for next_word_char in next_word.chars() {
+              self.remember(next_word_char);
             }
-            // Get the next word
-            let (next_word_string, next_word_length) = next_word_with_length(&mut text_iterator);
-            // Sentence-break, UNLESS a "mathformula" or a "lowercase word" follows, or a
-            // non-alpha char
-            if next_word_string.is_empty()
-              || next_word_string.starts_with("mathformula")
-              || next_word_string.chars().next().unwrap().is_lowercase()
-            {
-              // We consumed the next word, add it to the left window
-              for next_word_char in next_word_string.chars() {
-                left_window.push_back(next_word_char);
-                if left_window.len() >= window_size {
-                  left_window.pop_front();
-                }
+            sentence
+          };
+          self.end += next_word_length;
+          sentence
+        } else {
+          // lowercase and non-alphanum characters
+          match next_char {
+            '*' | '"' | '(' => Some(self.cut(self.end)),
+            c if sentence_char == '.' && c.is_alphabetic() => {
+              let (next_word, next_word_length) = self.next_word();
+              // TODO: Maybe extend to more lowercase stopwords here? unclear...
+              let mathformula_next = next_word
+                .chars()
+                .flat_map(char::to_lowercase)
+                .take(11)
+                .eq("mathformula".chars());
+              let sentence =
+                if mathformula_next && !self.tokenizer.abbreviation_check(&self.left_window) {
+                  Some(self.cut(self.end))
+                } else {
+                  self.remember('.');
+                  None
+                };
+              // We consumed the next word, so make sure we reflect that in either case:
+              for next_word_char in next_word.chars() {
+                self.remember(next_word_char);
               }
-            } else {
-              // Sentence-break found:
-              left_window = VecDeque::with_capacity(window_size);
-              sentences.push(DNMRange { start, end, dnm }.trim());
-              start = end;
-            }
-
-            // We consumed the next word, so make sure we reflect that in either case:
-            end += next_word_length;
+              self.end += next_word_length;
+              sentence
+            },
+            _ => {
+              self.remember('.');
+              None
+            },
           }
-        },
-        other_char => {
-          // "mathformula\nCapitalized" case is a sentence break (but never
-          // "mathformula\nmathformula")
-          if other_char.is_uppercase() {
-            let lw_string: String = left_window.iter().collect();
-            if lw_string.starts_with("mathformula") {
-              // Sentence-break found, but exclude the current letter from the end:
-              left_window = VecDeque::with_capacity(window_size);
-              sentences.push(
-                DNMRange {
-                  start,
-                  end: end - other_char.len_utf8(),
-                  dnm,
-                }
-                .trim(),
-              );
-              start = end - other_char.len_utf8();
-            }
+        }
+      },
+      '?' | '!' => {
+        if is_bounded(self.left_window.back(), self.peek().as_ref()) {
+          None
+        } else {
+          Some(self.cut(self.end))
+        }
+      },
+      // TODO:
+      // Some('\u{2022}'),Some('*') => { // bullet point for itemize
+      // Some('\u{220e}') => { // QED symbol
+      '\n' => {
+        // newline
+        if self.peek() != Some('\n') {
+          return None;
+        }
+        // second newline
+        // Get next non-space character
+        while self.peek().is_some_and(char::is_whitespace) {
+          self.chars.next();
+          self.end += 1;
+        }
+        self.peek()?;
+        // Get the next word
+        let (next_word, next_word_length) = self.next_word();
+        // Sentence-break, UNLESS a "mathformula" or a "lowercase word" follows, or a
+        // non-alpha char
+        let sentence = if next_word.is_empty()
+          || next_word.starts_with("mathformula")
+          || next_word.chars().next().is_some_and(char::is_lowercase)
+        {
+          // We consumed the next word, add it to the left window
+          for next_word_char in next_word.chars() {
+            self.remember(next_word_char);
           }
-          // Increment the left window
-          left_window.push_back(other_char);
-          if left_window.len() >= window_size {
-            left_window.pop_front();
+          None
+        } else {
+          // Sentence-break found:
+          Some(self.cut(self.end))
+        };
+        // We consumed the next word, so make sure we reflect that in either case:
+        self.end += next_word_length;
+        sentence
+      },
+      other_char => {
+        // "mathformula\nCapitalized" case is a sentence break (but never
+        // "mathformula\nmathformula")
+        let sentence = if other_char.is_uppercase()
+          && self
+            .left_window
+            .iter()
+            .copied()
+            .take(11)
+            .eq("mathformula".chars())
+        {
+          // Sentence-break found, but exclude the current letter from the end:
+          Some(self.cut(self.end - 1))
+        } else {
+          None
+        };
+        // Increment the left window
+        self.remember(other_char);
+        sentence
+      },
+    }
+  }
+}
+
+impl<'a> Iterator for SentenceIter<'_, 'a> {
+  type Item = DNMRange<'a>;
+  fn next(&mut self) -> Option<DNMRange<'a>> {
+    while !self.done {
+      let sentence = match self.chars.next() {
+        Some(sentence_char) => self.step(sentence_char),
+        None => {
+          self.done = true;
+          if self.left_window.iter().any(|c| c.is_alphabetic()) {
+            Some(self.cut(self.end))
+          } else {
+            None
           }
         },
+      };
+      // Filter out edge cases that return empty ranges
+      if let Some(sentence) = sentence.filter(|range| range.start < range.end) {
+        return Some(sentence);
       }
     }
-
-    end = cmp::min(end, text.chars().count());
-    let last_left_window: String = left_window.into_iter().collect();
-    if last_left_window.find(char::is_alphabetic).is_some() {
-      sentences.push(DNMRange { start, end, dnm }.trim());
-    }
-
-    // Filter out edge cases that return empty ranges
-    sentences
-      .into_iter()
-      .filter(|range| range.start < range.end)
-      .collect()
+    None
   }
+}
 
-  /// returns the words of a sentence using simple heuristics
-  pub fn words<'b>(&'b self, sentence_range: &DNMRange<'b>) -> Vec<DNMRange> {
-    let mut text_iterator = sentence_range.get_plaintext().chars().peekable();
-    let mut start = 0usize;
-    let mut end = 0usize;
-    let mut result: Vec<DNMRange> = Vec::new();
-    while let Some(c) = text_iterator.next() {
-      end += c.len_utf8();
-      if !c.is_alphanumeric() {
-        if c == '\'' || c == '’' {
-          if let Some(peeked) = text_iterator.peek() {
-            if peeked == &'s' {
-              continue;
-            }
-          }
-        }
-        if start < end - c.len_utf8() {
-          result.push(sentence_range.get_subrange(start, end - c.len_utf8()));
+/// A lazy iterator over the words of a sentence, see `Tokenizer::word_iter`
+pub struct WordIter<'t, 'b> {
+  tokenizer: &'t Tokenizer,
+  range: DNMRange<'b>,
+  text: &'b str,
+  chars: CharIndices<'b>,
+  /// start of the current word, in bytes
+  start: usize,
+}
+
+impl<'b> Iterator for WordIter<'_, 'b> {
+  type Item = DNMRange<'b>;
+  fn next(&mut self) -> Option<DNMRange<'b>> {
+    while let Some((offset, c)) = self.chars.next() {
+      if c.is_alphanumeric() {
+        continue;
+      }
+      if (c == '\'' || c == '’') && self.tokenizer.contractions.contains("s") {
+        // possessive "'s", unless an elision precedes, as in "l'espace"
+        let peeked = self.chars.clone().next().map(|(_, peeked)| peeked);
+        if peeked == Some('s') && !self.tokenizer.is_elision(&self.text[self.start..offset]) {
+          continue;
         }
-        start = end;
+      }
+      let start = self.start;
+      self.start = offset + c.len_utf8();
+      if start < offset {
+        return Some(self.range.get_subrange_from_byte_offsets(start, offset));
       }
     }
-    if start < end {
-      result.push(sentence_range.get_subrange(start, end));
+    if self.start < self.text.len() {
+      let start = self.start;
+      self.start = self.text.len();
+      Some(self.range.get_subrange_from_byte_offsets(start, self.start))
+    } else {
+      None
     }
-    result
   }
+}
 
-  /// returns the words and punctuation of a sentence, using simple heuristics
-  #[allow(unused_assignments)]
-  pub fn words_and_punct<'b>(&'b self, range: &DNMRange<'b>) -> Vec<DNMRange> {
-    let range_text = range.get_plaintext();
-    let text_iterator = range_text.chars();
-    let mut start = 0usize;
-    let mut end = 0usize;
-    let mut result: Vec<DNMRange> = Vec::new();
-    let mut apostrophe_flag = false;
-    macro_rules! complete_word {
-      () => {
-        if start < end {
-          if apostrophe_flag {
-            match &range_text[start + 1..end] {
-              // Handle closed set of apostrophe cases, detach from all other cases
-              "t" | "s" | "un" | "th" | "ll" | "d" | "ve" | "il" | "re" | "m" => {},
-              _ => {
-                result.push(range.get_subrange(start, start + 1));
-                start += 1;
-              },
-            }
-          }
-          result.push(range.get_subrange(start, end));
-          apostrophe_flag = false;
-          start = end;
+/// A lazy iterator over the words and punctuation of a sentence, see
+/// `Tokenizer::words_and_punct_iter`
+pub struct WordAndPunctIter<'t, 'b> {
+  tokenizer: &'t Tokenizer,
+  range: DNMRange<'b>,
+  text: &'b str,
+  chars: Chars<'b>,
+  /// start of the current word, in bytes
+  start: usize,
+  /// end of the current word, in bytes
+  end: usize,
+  apostrophe_flag: bool,
+  /// byte offsets of the words completed by the last character, at most three
+  pending: [(usize, usize); 3],
+  pending_len: usize,
+  pending_index: usize,
+  done: bool,
+}
+
+impl<'b> WordAndPunctIter<'_, 'b> {
+  fn complete_word(&mut self) {
+    if self.start < self.end {
+      if self.apostrophe_flag {
+        // Handle closed set of apostrophe cases, detach from all other cases
+        let apostrophe_length = self.text[self.start..]
+          .chars()
+          .next()
+          .map_or(1, char::len_utf8);
+        let ending = &self.text[self.start + apostrophe_length..self.end];
+        if !self.tokenizer.contractions.contains(ending) {
+          self.pending[self.pending_len] = (self.start, self.start + apostrophe_length);
+          self.pending_len += 1;
+          self.start += apostrophe_length;
         }
-      };
+      }
+      self.pending[self.pending_len] = (self.start, self.end);
+      self.pending_len += 1;
+      self.apostrophe_flag = false;
+      self.start = self.end;
     }
+  }
 
-    for c in text_iterator {
-      // letters, numbers can accumulate
-      if c.is_alphanumeric() {
-        end += c.len_utf8();
-      } else {
-        // everything else completes a word and starts a new one
-        complete_word!();
-        // except that whitepace can be skipped over
-        if c.is_whitespace() {
-          end += c.len_utf8();
-          start = end;
-        }
-        // non-alphanum chars are standalone words EXCEPT when connectors such as apostrophes
-        else {
-          end += c.len_utf8();
-          if c == '\'' || c == '’' {
-            apostrophe_flag = true;
-          } else {
-            // standalone char word case
-            complete_word!();
-          }
-        }
+  fn step(&mut self, c: char) {
+    // letters, numbers can accumulate
+    if c.is_alphanumeric() {
+      self.end += c.len_utf8();
+      return;
+    }
+    // elisions keep their apostrophe, e.g. "l'" in "l'équation"
+    if (c == '\'' || c == '’')
+      && !self.apostrophe_flag
+      && self.start < self.end
+      && self.tokenizer.is_elision(&self.text[self.start..self.end])
+    {
+      self.end += c.len_utf8();
+      self.complete_word();
+      return;
+    }
+    // everything else completes a word and starts a new one
+    self.complete_word();
+    self.end += c.len_utf8();
+    // except that whitepace can be skipped over
+    if c.is_whitespace() {
+      self.start = self.end;
+    }
+    // non-alphanum chars are standalone words EXCEPT when connectors such as apostrophes
+    else if c == '\'' || c == '’' {
+      self.apostrophe_flag = true;
+    } else {
+      // standalone char word case
+      self.complete_word();
+    }
+  }
+}
+
+impl<'b> Iterator for WordAndPunctIter<'_, 'b> {
+  type Item = DNMRange<'b>;
+  fn next(&mut self) -> Option<DNMRange<'b>> {
+    loop {
+      if self.pending_index < self.pending_len {
+        let (start, end) = self.pending[self.pending_index];
+        self.pending_index += 1;
+        return Some(self.range.get_subrange_from_byte_offsets(start, end));
+      }
+      if self.done {
+        return None;
+      }
+      self.pending_len = 0;
+      self.pending_index = 0;
+      match self.chars.next() {
+        Some(c) => self.step(c),
+        None => {
+          self.done = true;
+          self.complete_word();
+        },
       }
     }
-    complete_word!();
-    result
   }
 }
+
 /// checks whether two characters are matching brackets or quotation marks
 fn is_bounded<'a>(left: Option<&'a char>, right: Option<&'a char>) -> bool {
   let pair = [left, right];
@@ -375,18 +637,3 @@ fn is_bounded<'a>(left: Option<&'a char>, right: Option<&'a char>) -> bool {
       | [Some(&'"'), Some(&'"')]
   )
 }
-
-/// Obtains the next word from the `Peekable<Chars>` iterator, where only
-/// alphabetic characters are accepted, and a max length of 20 is imposed
-fn next_word_with_length(text_iterator: &mut Peekable<Chars>) -> (String, usize) {
-  let mut next_word_length = 0;
-  let mut next_word: Vec<char> = Vec::new();
-  while next_word_length < 20 && text_iterator.peek().unwrap_or(&'.').is_alphabetic() {
-    let word_char = text_iterator.next().unwrap();
-    next_word.push(word_char);
-    next_word_length += word_char.len_utf8();
-  }
-
-  let next_word: String = next_word.into_iter().collect();
-  (next_word, next_word_length)
-}
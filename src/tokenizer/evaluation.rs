@@ -0,0 +1,349 @@
+//! The `tokenizer::evaluation` submodule measures a `Segmenter` against gold sentence boundaries.
+//!
+//! Gold data is kept as JSON, listing for each document the paragraphs (by `id`) and their
+//! sentences, given either as XPointers (see `dnm::xpointer`) or as character offsets into the
+//! paragraph's `DNM`:
+//!
+//! ```json
+//! [{"path": "1311.0066.xhtml",
+//!   "paragraphs": [{"id": "S1.p1",
+//!                   "sentences": ["xpointer(string-range(//*[@id='S1.p1'],\"The study\"))",
+//!                                 {"start": 88, "end": 229}]}]}]
+//! ```
+//!
+//! Each paragraph is segmented in its own `DNM`, as in the corpus iterators. A boundary is the end
+//! of a (trimmed) sentence; precision, recall and F1 are computed over the boundaries of each
+//! document, and the mismatched ones are reported with their context.
+use crate::dnm::{DNMParameters, DNMRange, OffsetUnit, DNM};
+use crate::tokenizer::Segmenter;
+use libxml::parser::Parser;
+use libxml::xpath::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Number of characters shown on each side of a mismatched boundary
+const CONTEXT_CHARS: usize = 30;
+
+/// A gold sentence of a paragraph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GoldSentence {
+  /// An XPointer to the sentence, resolved against the document
+  XPointer(String),
+  /// Offsets into the plaintext of the paragraph's `DNM`
+  Offsets {
+    /// start offset of the sentence
+    start: usize,
+    /// end offset of the sentence
+    end: usize,
+  },
+}
+
+/// The gold sentences of a paragraph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoldParagraph {
+  /// The `id` attribute of the paragraph element
+  pub id: String,
+  /// The sentences, in document order
+  pub sentences: Vec<GoldSentence>,
+}
+
+/// The gold sentences of a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoldDocument {
+  /// Path to the document, relative to the gold file when loaded with `load_gold`
+  pub path: String,
+  /// The unit of the `Offsets` sentences
+  #[serde(default)]
+  pub unit: OffsetUnit,
+  /// The annotated paragraphs
+  pub paragraphs: Vec<GoldParagraph>,
+}
+
+/// Read gold documents from a JSON file, resolving their paths against the file's directory
+pub fn load_gold(path: &str) -> Result<Vec<GoldDocument>, Box<dyn Error>> {
+  let reader = BufReader::new(File::open(path)?);
+  let mut documents: Vec<GoldDocument> = serde_json::from_reader(reader)?;
+  let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+  for document in &mut documents {
+    let document_path = directory.join(&document.path);
+    document.path = document_path
+      .to_str()
+      .ok_or("Gold document path is not valid UTF-8")?
+      .to_string();
+  }
+  Ok(documents)
+}
+
+/// Counts of matched and mismatched boundaries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Scores {
+  /// boundaries in both the gold data and the segmentation
+  pub true_positives: usize,
+  /// boundaries only in the segmentation
+  pub false_positives: usize,
+  /// boundaries only in the gold data
+  pub false_negatives: usize,
+}
+
+impl Scores {
+  /// The fraction of predicted boundaries which are correct (1 if there are none)
+  pub fn precision(&self) -> f64 {
+    ratio(
+      self.true_positives,
+      self.true_positives + self.false_positives,
+    )
+  }
+  /// The fraction of gold boundaries which were predicted (1 if there are none)
+  pub fn recall(&self) -> f64 {
+    ratio(
+      self.true_positives,
+      self.true_positives + self.false_negatives,
+    )
+  }
+  /// The harmonic mean of precision and recall
+  pub fn f1(&self) -> f64 {
+    let (precision, recall) = (self.precision(), self.recall());
+    if precision + recall == 0.0 {
+      0.0
+    } else {
+      2.0 * precision * recall / (precision + recall)
+    }
+  }
+  /// Add the counts of `other`
+  pub fn add(&mut self, other: &Scores) {
+    self.true_positives += other.true_positives;
+    self.false_positives += other.false_positives;
+    self.false_negatives += other.false_negatives;
+  }
+}
+
+/// Helper function: a ratio of counts, 1 for an empty denominator
+fn ratio(count: usize, total: usize) -> f64 {
+  if total == 0 {
+    1.0
+  } else {
+    count as f64 / total as f64
+  }
+}
+
+/// Whether a mismatched boundary was missed or is spurious
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchKind {
+  /// a gold boundary which was not predicted
+  Missed,
+  /// a predicted boundary which is not in the gold data
+  Spurious,
+}
+
+/// A boundary on which the segmentation and the gold data disagree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundaryMismatch {
+  /// The `id` of the paragraph
+  pub paragraph: String,
+  /// The character offset of the boundary in the paragraph's `DNM`
+  pub offset: usize,
+  /// Missed or spurious
+  pub kind: MismatchKind,
+  /// The text around the boundary, which is marked with `|`
+  pub context: String,
+}
+
+impl fmt::Display for BoundaryMismatch {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let sign = match self.kind {
+      MismatchKind::Missed => '-',
+      MismatchKind::Spurious => '+',
+    };
+    write!(
+      f,
+      "{} {}@{}: {}",
+      sign, self.paragraph, self.offset, self.context
+    )
+  }
+}
+
+/// The evaluation of a single document
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentEvaluation {
+  /// Path to the document
+  pub path: String,
+  /// The boundary counts of all gold paragraphs
+  pub scores: Scores,
+  /// The mismatched boundaries, in document order
+  pub mismatches: Vec<BoundaryMismatch>,
+}
+
+/// The evaluation of a segmenter over gold documents
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Evaluation {
+  /// The per-document evaluations
+  pub documents: Vec<DocumentEvaluation>,
+}
+
+impl Evaluation {
+  /// The boundary counts over all documents (micro-averaged scores)
+  pub fn total(&self) -> Scores {
+    let mut total = Scores::default();
+    for document in &self.documents {
+      total.add(&document.scores);
+    }
+    total
+  }
+}
+
+/// A report of the scores per document and in total, followed by the mismatched boundaries,
+/// `-` marking missed and `+` spurious ones
+impl fmt::Display for Evaluation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let line = |f: &mut fmt::Formatter, name: &str, scores: &Scores| {
+      writeln!(
+        f,
+        "{}\tP={:.4}\tR={:.4}\tF1={:.4}",
+        name,
+        scores.precision(),
+        scores.recall(),
+        scores.f1()
+      )
+    };
+    for document in &self.documents {
+      line(f, &document.path, &document.scores)?;
+    }
+    line(f, "total", &self.total())?;
+    for document in &self.documents {
+      if !document.mismatches.is_empty() {
+        writeln!(f, "--- {}", document.path)?;
+      }
+      for mismatch in &document.mismatches {
+        writeln!(f, "{mismatch}")?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Compare the sentences of `segmenter` for a paragraph `DNM` with the `gold` sentences, adding
+/// the mismatches to `mismatches`
+pub fn evaluate_dnm(
+  segmenter: &dyn Segmenter,
+  dnm: &DNM,
+  paragraph: &str,
+  gold: &[DNMRange],
+  mismatches: &mut Vec<BoundaryMismatch>,
+) -> Scores {
+  let boundaries = |ranges: &[DNMRange]| -> BTreeSet<usize> {
+    ranges
+      .iter()
+      .map(DNMRange::trim)
+      .filter(|range| !range.is_empty())
+      .map(|range| range.end)
+      .collect()
+  };
+  let gold = boundaries(gold);
+  let predicted = boundaries(&segmenter.sentences(dnm));
+
+  let mut scores = Scores::default();
+  let mut offsets: Vec<(usize, MismatchKind)> = Vec::new();
+  for offset in gold.union(&predicted) {
+    match (gold.contains(offset), predicted.contains(offset)) {
+      (true, true) => scores.true_positives += 1,
+      (true, false) => {
+        scores.false_negatives += 1;
+        offsets.push((*offset, MismatchKind::Missed));
+      },
+      (false, _) => {
+        scores.false_positives += 1;
+        offsets.push((*offset, MismatchKind::Spurious));
+      },
+    }
+  }
+  for (offset, kind) in offsets {
+    mismatches.push(BoundaryMismatch {
+      paragraph: paragraph.to_string(),
+      offset,
+      kind,
+      context: boundary_context(dnm, offset),
+    });
+  }
+  scores
+}
+
+/// Helper function: the text around a boundary, on a single line
+fn boundary_context(dnm: &DNM, offset: usize) -> String {
+  let char_count = dnm.byte_offsets.len() - 1;
+  let before = DNMRange {
+    start: offset.saturating_sub(CONTEXT_CHARS),
+    end: offset,
+    dnm,
+  };
+  let after = DNMRange {
+    start: offset,
+    end: (offset + CONTEXT_CHARS).min(char_count),
+    dnm,
+  };
+  let context = format!("{}|{}", before.get_plaintext(), after.get_plaintext());
+  context.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Evaluate `segmenter` over the `gold` documents, building the paragraph `DNM`s with
+/// `parameters`
+pub fn evaluate(
+  segmenter: &dyn Segmenter,
+  gold: &[GoldDocument],
+  parameters: &DNMParameters,
+) -> Result<Evaluation, Box<dyn Error>> {
+  let mut evaluation = Evaluation::default();
+  for gold_document in gold {
+    let parser = if gold_document.path.ends_with(".xhtml") || gold_document.path.ends_with(".xml") {
+      Parser::default()
+    } else {
+      Parser::default_html()
+    };
+    let document = parser
+      .parse_file(&gold_document.path)
+      .map_err(|e| format!("Failed to parse {}: {:?}", gold_document.path, e))?;
+    let xpath_context = Context::new(&document).map_err(|_| "Failed to create an XPath context")?;
+
+    let mut scores = Scores::default();
+    let mut mismatches = Vec::new();
+    for gold_paragraph in &gold_document.paragraphs {
+      let nodes = xpath_context
+        .evaluate(&format!("//*[@id='{}']", gold_paragraph.id))
+        .map_err(|_| format!("Invalid paragraph id \"{}\"", gold_paragraph.id))?
+        .get_readonly_nodes_as_vec();
+      let node = nodes
+        .first()
+        .ok_or_else(|| format!("Paragraph \"{}\" not found", gold_paragraph.id))?;
+      let dnm = DNM::new(*node, parameters.clone());
+
+      let offset_index = dnm.offset_index(gold_document.unit);
+      let mut sentences = Vec::with_capacity(gold_paragraph.sentences.len());
+      for sentence in &gold_paragraph.sentences {
+        sentences.push(match sentence {
+          GoldSentence::XPointer(pointer) => DNMRange::deserialize(pointer, &dnm, &xpath_context)?,
+          GoldSentence::Offsets { start, end } => {
+            DNMRange::from_offsets(&dnm, *start, *end, &offset_index)?
+          },
+        });
+      }
+      scores.add(&evaluate_dnm(
+        segmenter,
+        &dnm,
+        &gold_paragraph.id,
+        &sentences,
+        &mut mismatches,
+      ));
+    }
+    evaluation.documents.push(DocumentEvaluation {
+      path: gold_document.path.clone(),
+      scores,
+      mismatches,
+    });
+  }
+  Ok(evaluation)
+}
@@ -0,0 +1,179 @@
+//! The `tokenizer::language` submodule provides `Tokenizer` profiles for the main languages of
+//! arXiv besides English, with their own stopwords, abbreviations, elisions and contractions.
+//!
+//! A `MultilingualTokenizer` holds a profile per language, and picks one for each `DNM` via
+//! `whatlang` detection, falling back to English when the language is unsupported or detection is
+//! unreliable.
+use crate::dnm::{DNMRange, DNM};
+use crate::stopwords;
+use crate::tokenizer::{RangeIter, Segmenter, Tokenizer};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use whatlang::{Detector, Lang, Script};
+
+/// The languages with a tokenizer profile
+pub const SUPPORTED_LANGUAGES: [Lang; 5] = [Lang::Eng, Lang::Fra, Lang::Deu, Lang::Rus, Lang::Spa];
+
+/// Abbreviations common to all non-English profiles, mostly from mathematical writing
+const COMMON_ABBREVIATIONS: &[&str] = &[
+  "Fig", "fig", "Eq", "eq", "Eqs", "eqs", "Thm", "Prop", "Lem", "Cor", "Def", "Ref", "Refs", "Sec",
+  "Tab", "vol", "Vol", "no", "No", "pp", "ed", "eds", "etc", "al", "cf", "resp", "vs", "Dr",
+  "Prof",
+];
+
+/// French abbreviations
+const FRENCH_ABBREVIATIONS: &[&str] = &[
+  "Éq", "éq", "Déf", "Rem", "Rém", "Ex", "ex", "p", "chap", "éd", "env", "M", "MM", "Mme", "Mlle",
+  "Pr", "av", "apr", "sq", "sqq", "hyp", "prop", "th", "thm", "déf", "lem", "cor", "cf",
+];
+
+/// German abbreviations
+const GERMAN_ABBREVIATIONS: &[&str] = &[
+  "Abb", "Bsp", "bzw", "ca", "evtl", "ggf", "Gl", "Hrsg", "inkl", "Kap", "Nr", "sog", "usw", "vgl",
+  "Vgl", "Bd", "Bem", "Def", "Lem", "Kor", "Abschn", "bzgl", "gem", "insb", "Anm", "Aufl", "Hr",
+  "Fr",
+];
+
+/// Spanish abbreviations
+const SPANISH_ABBREVIATIONS: &[&str] = &[
+  "Ec", "ec", "ej", "Ej", "pág", "págs", "Sr", "Sra", "Srta", "Dra", "Teor", "Obs", "cap", "núm",
+  "aprox", "Cap", "Sec", "Def", "Prop", "Cor", "etc",
+];
+
+/// Russian abbreviations
+const RUSSIAN_ABBREVIATIONS: &[&str] = &[
+  "см", "Рис", "рис", "гл", "стр", "напр", "др", "пр", "гг", "табл", "разд", "ср", "теор", "опр",
+  "ур", "Ур", "им", "акад", "проф", "доц", "сб", "изд", "англ", "т", "г",
+];
+
+/// French elisions
+const FRENCH_ELISIONS: &[&str] = &[
+  "c", "d", "j", "l", "m", "n", "s", "t", "qu", "jusqu", "lorsqu", "puisqu", "quoiqu",
+];
+
+/// Helper function: an abbreviation regular expression, as for English in `Tokenizer::default`
+fn abbreviation_regex(abbreviations: &[&[&str]]) -> Regex {
+  let alternatives: Vec<String> = abbreviations
+    .iter()
+    .flat_map(|list| list.iter())
+    .map(|abbreviation| regex::escape(abbreviation))
+    .collect();
+  Regex::new(&format!(r"^(?:{})\s?$", alternatives.join("|"))).unwrap()
+}
+
+/// The script a supported language is written in
+pub fn script(lang: Lang) -> Option<Script> {
+  match lang {
+    Lang::Eng | Lang::Fra | Lang::Deu | Lang::Spa => Some(Script::Latin),
+    Lang::Rus => Some(Script::Cyrillic),
+    _ => None,
+  }
+}
+
+/// Helper function: a text without the placeholder words of llamapun's normalization, which would
+/// mislead language detection
+pub(crate) fn detectable_text(text: &str) -> String {
+  text
+    .replace("mathformula", " ")
+    .replace("CitationElement", " ")
+    .replace("REF", " ")
+}
+
+/// Detects the language of a text among `languages`, ignoring the placeholder words of llamapun's
+/// normalization. `None` if the detection is unreliable.
+pub fn detect_language(text: &str, languages: &[Lang]) -> Option<Lang> {
+  Detector::with_allowlist(languages.to_vec())
+    .detect(detectable_text(text).trim())
+    .filter(|info| info.is_reliable())
+    .map(|info| info.lang())
+}
+
+impl Tokenizer {
+  /// The tokenizer profile of a language, or `None` if the language is not supported (see
+  /// `SUPPORTED_LANGUAGES`)
+  pub fn for_language(lang: Lang) -> Option<Tokenizer> {
+    let (abbreviations, elisions, contractions): (&[&str], &[&str], &[&str]) = match lang {
+      Lang::Eng => return Some(Tokenizer::default()),
+      Lang::Fra => (FRENCH_ABBREVIATIONS, FRENCH_ELISIONS, &[]),
+      Lang::Deu => (GERMAN_ABBREVIATIONS, &[], &["s"]),
+      Lang::Spa => (SPANISH_ABBREVIATIONS, &[], &[]),
+      Lang::Rus => (RUSSIAN_ABBREVIATIONS, &[], &[]),
+      _ => return None,
+    };
+    Some(Tokenizer {
+      stopwords: stopwords::load_language(lang)?,
+      abbreviations: abbreviation_regex(&[COMMON_ABBREVIATIONS, abbreviations]),
+      elisions: elisions.iter().cloned().collect(),
+      contractions: contractions.iter().cloned().collect(),
+    })
+  }
+}
+
+/// A `Segmenter` choosing the tokenizer profile by the detected language of each `DNM`
+pub struct MultilingualTokenizer {
+  /// The tokenizer profiles, by language
+  pub profiles: HashMap<Lang, Tokenizer>,
+  /// The language used when detection fails, which must have a profile
+  pub fallback: Lang,
+}
+
+impl Default for MultilingualTokenizer {
+  fn default() -> MultilingualTokenizer { MultilingualTokenizer::new(&SUPPORTED_LANGUAGES) }
+}
+
+impl MultilingualTokenizer {
+  /// Create a tokenizer with the profiles of `languages`, falling back to English, which is
+  /// always included. Unsupported languages are skipped.
+  pub fn new(languages: &[Lang]) -> MultilingualTokenizer {
+    let mut profiles = HashMap::new();
+    let languages: HashSet<Lang> = languages.iter().cloned().chain(Some(Lang::Eng)).collect();
+    for lang in languages {
+      if let Some(tokenizer) = Tokenizer::for_language(lang) {
+        profiles.insert(lang, tokenizer);
+      }
+    }
+    MultilingualTokenizer {
+      profiles,
+      fallback: Lang::Eng,
+    }
+  }
+
+  /// The language of a text among the profiles, or the fallback
+  pub fn detect(&self, text: &str) -> Lang {
+    let languages: Vec<Lang> = self.profiles.keys().cloned().collect();
+    detect_language(text, &languages).unwrap_or(self.fallback)
+  }
+
+  /// The tokenizer profile for a text
+  pub fn profile(&self, text: &str) -> &Tokenizer { &self.profiles[&self.detect(text)] }
+
+  /// The tokenizer profile for a `DNM`, by the language of its entire plaintext. Detection
+  /// reads the whole plaintext, so the profile is best obtained once per `DNM` (as the corpus
+  /// iterators do via `Segmenter::for_dnm`), rather than for each of its sentences.
+  pub fn profile_for(&self, dnm: &DNM) -> &Tokenizer { self.profile(&dnm.plaintext) }
+}
+
+/// Words are tokenized with the profile of the range's entire `DNM`, as single sentences are often
+/// too short for a reliable detection. Each call detects the language anew, see `for_dnm` for
+/// segmenting many ranges of a `DNM`.
+impl Segmenter for MultilingualTokenizer {
+  fn sentences<'a>(&self, dnm: &'a DNM) -> Vec<DNMRange<'a>> {
+    self.profile_for(dnm).sentences(dnm)
+  }
+  fn words<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> {
+    self.profile_for(range.dnm).words(range)
+  }
+  fn words_and_punct<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> {
+    self.profile_for(range.dnm).words_and_punct(range)
+  }
+  fn for_dnm(&self, dnm: &DNM) -> &dyn Segmenter { self.profile_for(dnm) }
+  fn sentence_iter<'a>(&'a self, dnm: &'a DNM) -> RangeIter<'a> {
+    Box::new(self.profile_for(dnm).sentence_iter(dnm))
+  }
+  fn word_iter<'b>(&'b self, range: &DNMRange<'b>) -> RangeIter<'b> {
+    Box::new(self.profile_for(range.dnm).word_iter(range))
+  }
+  fn words_and_punct_iter<'b>(&'b self, range: &DNMRange<'b>) -> RangeIter<'b> {
+    Box::new(self.profile_for(range.dnm).words_and_punct_iter(range))
+  }
+}
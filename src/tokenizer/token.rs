@@ -0,0 +1,240 @@
+//! The `tokenizer::token` submodule assigns types to the words and punctuation of a range.
+//!
+//! Tokens covering a normalized element (e.g. `<math>` replaced by "mathformula") are recognized
+//! via the `DNM` back-mapping, so that they are typed by their origin rather than by their text,
+//! and are split from adjacent text, as in "mathformula-dimensional" or "mathformulath" for
+//! `$k$-dimensional` and `$k$th`. Without back-mapping, llamapun's default normalization tokens
+//! ("mathformula", "CitationElement", "REF") are recognized by their text instead.
+use crate::dnm::DNMRange;
+use libxml::readonly::RoNode;
+use libxml::tree::NodeType;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+  /// Integers, floats, subfigure numbers
+  pub(crate) static ref IS_NUMERIC: Regex =
+    Regex::new(r"^-?(?:\d+)(?:[a-k]|(?:\.\d+(?:[eE][+-]?\d+)?))?$").unwrap();
+  static ref URL: Regex = Regex::new(r#"(?:\b(?:https?|ftp)://|\bwww\.)[^\s<>"]+"#).unwrap();
+}
+
+/// The type of a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+  /// a word, possibly with an apostrophe, e.g. "isn't" or "l'"
+  Word,
+  /// a numeric literal, e.g. "42", "3.14" or "2a"
+  Number,
+  /// a formula, normalized from `<math>` or an equation
+  Math,
+  /// a citation, normalized from `<cite>`
+  Citation,
+  /// a reference to another part of the document, e.g. normalized from `ltx_ref`
+  Reference,
+  /// a punctuation mark
+  Punctuation,
+  /// a URL
+  Url,
+  /// any other symbol, e.g. "+" or "€"
+  Symbol,
+}
+
+/// A typed range of a tokenized text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'t> {
+  /// The range of the token
+  pub range: DNMRange<'t>,
+  /// The type of the token
+  pub token_type: TokenType,
+}
+
+impl<'t> Token<'t> {
+  /// Splits a range by the normalized elements it covers, and types each part
+  pub fn classify(range: &DNMRange<'t>) -> Vec<Token<'t>> {
+    let dnm = range.dnm;
+    if !dnm.parameters.support_back_mapping || range.end > dnm.back_map.len() {
+      return vec![Token {
+        range: range.clone(),
+        token_type: text_token_type(range.get_plaintext(), true),
+      }];
+    }
+    let mut tokens = Vec::new();
+    let mut start = range.start;
+    let mut current = element_origin(dnm.back_map[start]);
+    for offset in range.start + 1..range.end {
+      let origin = element_origin(dnm.back_map[offset]);
+      if origin.map(|o| o.0) != current.map(|c| c.0) {
+        push_part(&mut tokens, range, start, offset, current);
+        start = offset;
+        current = origin;
+      }
+    }
+    push_part(&mut tokens, range, start, range.end, current);
+    tokens
+  }
+}
+
+/// Helper function: adds the part of a range between two offsets, if it isn't whitespace
+fn push_part<'t>(
+  tokens: &mut Vec<Token<'t>>,
+  range: &DNMRange<'t>,
+  start: usize,
+  end: usize,
+  origin: Option<(RoNode, TokenType)>,
+) {
+  let part = DNMRange {
+    start,
+    end,
+    dnm: range.dnm,
+  }
+  .trim();
+  if part.is_empty() {
+    return;
+  }
+  let token_type = match origin {
+    Some((_, token_type)) => token_type,
+    None => text_token_type(part.get_plaintext(), false),
+  };
+  tokens.push(Token {
+    range: part,
+    token_type,
+  });
+}
+
+/// Helper function: the normalized element a plaintext offset stems from, if it is typed
+fn element_origin((node, offset): (RoNode, i32)) -> Option<(RoNode, TokenType)> {
+  if offset >= 0 || node.get_type() != Some(NodeType::ElementNode) {
+    return None;
+  }
+  element_token_type(node).map(|token_type| (node, token_type))
+}
+
+/// The type of the tokens normalized from an element, or `None` for elements without a typed
+/// normalization
+pub fn element_token_type(node: RoNode) -> Option<TokenType> {
+  let name = node.get_name();
+  let class = node.get_property("class").unwrap_or_default();
+  let has_class = |wanted: &[&str]| class.split_whitespace().any(|c| wanted.contains(&c));
+  if name == "math" || has_class(&["ltx_Math", "ltx_equation", "ltx_equationgroup"]) {
+    Some(TokenType::Math)
+  } else if name == "cite" || has_class(&["ltx_cite"]) {
+    Some(TokenType::Citation)
+  } else if has_class(&["ltx_url"]) {
+    Some(TokenType::Url)
+  } else if name == "a" || has_class(&["ltx_ref"]) {
+    Some(TokenType::Reference)
+  } else {
+    None
+  }
+}
+
+/// The type of a token by its text. With `normalized`, llamapun's default normalization tokens
+/// are recognized as math, citations and references.
+pub fn text_token_type(text: &str, normalized: bool) -> TokenType {
+  match text {
+    "mathformula" if normalized => TokenType::Math,
+    "CitationElement" if normalized => TokenType::Citation,
+    "REF" if normalized => TokenType::Reference,
+    _ if IS_NUMERIC.is_match(text) => TokenType::Number,
+    _ if URL.is_match(text) => TokenType::Url,
+    _ if text.chars().any(char::is_alphanumeric) => TokenType::Word,
+    _ => match text.chars().next() {
+      Some(c) if is_punctuation(c) => TokenType::Punctuation,
+      _ => TokenType::Symbol,
+    },
+  }
+}
+
+/// Helper function: whether a character is a punctuation mark, rather than a symbol
+fn is_punctuation(c: char) -> bool {
+  (c.is_ascii_punctuation() && !"$+<=>^`|~".contains(c))
+    || matches!(
+      c,
+      '‘'
+        | '’'
+        | '“'
+        | '”'
+        | '«'
+        | '»'
+        | '–'
+        | '—'
+        | '…'
+        | '¿'
+        | '¡'
+        | '·'
+        | '„'
+        | '‹'
+        | '›'
+    )
+}
+
+/// Types the words and punctuation of `range`, as split by a segmenter: parts are split further
+/// by the normalized elements they cover, URLs are joined into a single token, as are decimal
+/// numbers
+pub fn typed_tokens<'t>(
+  range: &DNMRange<'t>,
+  words_and_punct: Vec<DNMRange<'t>>,
+) -> Vec<Token<'t>> {
+  let text = range.get_plaintext();
+  let urls: Vec<DNMRange<'t>> = URL
+    .find_iter(text)
+    .map(|url| {
+      // trailing punctuation belongs to the sentence
+      let url_text = url
+        .as_str()
+        .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}', '\'']);
+      range.get_subrange_from_byte_offsets(url.start(), url.start() + url_text.len())
+    })
+    .collect();
+
+  let mut tokens: Vec<Token<'t>> = Vec::new();
+  let mut urls = urls.into_iter().peekable();
+  for part in words_and_punct {
+    while let Some(url) = urls.next_if(|url| url.start <= part.start) {
+      tokens.push(Token {
+        range: url,
+        token_type: TokenType::Url,
+      });
+    }
+    // parts of a URL are covered by its token
+    let in_url = tokens
+      .last()
+      .is_some_and(|last| last.token_type == TokenType::Url && last.range.end > part.start);
+    if in_url {
+      continue;
+    }
+    for token in Token::classify(&part) {
+      // join decimal numbers, which are split at their point
+      if token.token_type == TokenType::Number {
+        if let [.., number, point] = tokens.as_slice() {
+          if number.token_type == TokenType::Number
+            && point.range.get_plaintext() == "."
+            && number.range.end == point.range.start
+            && point.range.end == token.range.start
+          {
+            let joined = DNMRange {
+              start: number.range.start,
+              end: token.range.end,
+              dnm: range.dnm,
+            };
+            if IS_NUMERIC.is_match(joined.get_plaintext()) {
+              tokens.truncate(tokens.len() - 2);
+              tokens.push(Token {
+                range: joined,
+                token_type: TokenType::Number,
+              });
+              continue;
+            }
+          }
+        }
+      }
+      tokens.push(token);
+    }
+  }
+  tokens.extend(urls.map(|url| Token {
+    range: url,
+    token_type: TokenType::Url,
+  }));
+  tokens
+}
@@ -7,16 +7,15 @@ use libxml::readonly::RoNode;
 use libxml::xpath::Context;
 use regex::Regex;
 use std::error::Error;
-use whatlang::{detect, Lang, Script};
+use whatlang::{detect, Lang};
 
 use crate::dnm;
 use crate::dnm::{DNMParameters, DNMRange, DNM};
-use crate::tokenizer::Tokenizer;
+use crate::tokenizer::language;
+use crate::tokenizer::token::{Token, TokenType, IS_NUMERIC};
+use crate::tokenizer::Segmenter;
 
-// Integers, floats, subfigure numbers
 lazy_static! {
-  static ref IS_NUMERIC: Regex =
-    Regex::new(r"^-?(?:\d+)(?:[a-k]|(?:\.\d+(?:[eE][+-]?\d+)?))?$").unwrap();
   static ref IS_NUM: Regex = Regex::new(r"\s*NUM\s*").unwrap();
   static ref ROMAN_NUMERAL: Regex = Regex::new(r"(^|\s)[xiv]*(\s|$)").unwrap();
   static ref SINGLE_LEAD_LETTER: Regex = Regex::new(r"(^|\s)[abcdefghijklmnop](\s|$)").unwrap();
@@ -77,7 +76,8 @@ pub fn ams_normalize_word_range(
 
   // Note: the formula and citation counts are an approximate lower bound, as
   // sometimes they are not cleanly tokenized, e.g. $k$-dimensional
-  // will be the word string "mathformula-dimensional"
+  // will be the word string "mathformula-dimensional". Typed tokens, split via the
+  // back-mapping, avoid this (see `ams_normalize_token`)
   if word_string.contains("mathformula") {
     if options.discard_math {
       word_string = String::new();
@@ -93,12 +93,52 @@ pub fn ams_normalize_word_range(
   Ok(word_string)
 }
 
+/// Normalization of typed tokens for the "AMS paragraph classification" experiment, as in
+/// `ams_normalize_word_range`, but relying on the token types rather than on the token text.
+/// URLs become "url".
+pub fn ams_normalize_token(
+  token: &Token,
+  context: &mut Context,
+  options: LexicalOptions,
+) -> Result<String, Box<dyn Error>> {
+  match token.token_type {
+    TokenType::Math if options.discard_math => Ok(String::new()),
+    TokenType::Math => Ok(dnm::node::lexematize_math(token.range.get_node(), context)),
+    TokenType::Citation => Ok(String::from("citationelement")),
+    TokenType::Number => Ok(String::from("NUM")),
+    TokenType::Url => Ok(String::from("url")),
+    _ => ams_normalize_word_range(&token.range, context, options),
+  }
+}
+
+/// The whitespace-separated words of a text, with their start and end char offsets
+pub(crate) fn whitespace_words(text: &str) -> Vec<(&str, usize, usize)> {
+  let mut words = Vec::new();
+  let mut word_start: Option<(usize, usize)> = None;
+  let mut char_offset = 0;
+  for (byte_offset, c) in text.char_indices() {
+    match (c.is_whitespace(), word_start) {
+      (true, Some((byte_start, char_start))) => {
+        words.push((&text[byte_start..byte_offset], char_start, char_offset));
+        word_start = None;
+      },
+      (false, None) => word_start = Some((byte_offset, char_offset)),
+      _ => {},
+    }
+    char_offset += 1;
+  }
+  if let Some((byte_start, char_start)) = word_start {
+    words.push((&text[byte_start..], char_start, char_offset));
+  }
+  words
+}
+
 /// Provides a string for a given heading node, using DNM-enabled word-tokenization
 /// TODO: This is a low-level auxiliary function, we may need to build more user-facing interfaces
 /// if it becomes more widely useful
 pub fn heading_from_node_aux(
   node: RoNode,
-  tokenizer: &Tokenizer,
+  tokenizer: &dyn Segmenter,
   context: &mut Context,
 ) -> Option<String> {
   let heading_dnm = DNM::new(node, DNMParameters::llamapun_normalization());
@@ -270,15 +310,18 @@ pub fn normalize_heading_title(heading: &str) -> String {
 // "spectroscopic analysis"  => "analysis",
 
 /// Check if the given DNM contains valid English+Latin content
-pub fn invalid_for_english_latin(dnm: &DNM) -> bool {
-  let detectable_with_spaces = dnm
-    .plaintext
-    .replace("mathformula", " ")
-    .replace("CitationElement", " ")
-    .replace("REF", " ");
+pub fn invalid_for_english_latin(dnm: &DNM) -> bool { invalid_for_languages(dnm, &[Lang::Eng]) }
+
+/// Check if the given DNM contains valid content in one of `languages`, written in the script of
+/// one of them (see `tokenizer::language::script`)
+pub fn invalid_for_languages(dnm: &DNM, languages: &[Lang]) -> bool {
+  let detectable_with_spaces = language::detectable_text(&dnm.plaintext);
   let detectable = detectable_with_spaces.trim();
   if let Some(info) = detect(detectable) {
-    info.script() != Script::Latin || (info.lang() != Lang::Eng && info.confidence() > 0.93)
+    let valid_script = languages
+      .iter()
+      .any(|lang| language::script(*lang) == Some(info.script()));
+    !valid_script || (!languages.contains(&info.lang()) && info.confidence() > 0.93)
   } else {
     false
   }
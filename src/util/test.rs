@@ -1,4 +1,7 @@
 //! Test utilities for llamapun's crate
+use crate::dnm::{DNMRange, DNM};
+use crate::tokenizer::Segmenter;
+use crate::util::data_helpers::whitespace_words;
 use lazy_static::lazy_static;
 use walkdir::WalkDir;
 
@@ -27,3 +30,25 @@ lazy_static! { // preload a list of the resources we have for testing, for easy
       .to_string())
     .collect();
 }
+
+/// A segmenter treating every paragraph as a single sentence, of whitespace-separated words,
+/// e.g. for testing custom segmenters and as a baseline of sentence tokenization
+pub struct ParagraphSegmenter;
+
+impl Segmenter for ParagraphSegmenter {
+  fn sentences<'a>(&self, dnm: &'a DNM) -> Vec<DNMRange<'a>> {
+    dnm
+      .get_range()
+      .map(|range| range.trim())
+      .into_iter()
+      .collect()
+  }
+  fn words<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> {
+    whitespace_words(range.get_plaintext())
+      .into_iter()
+      .map(|(_, start, end)| range.get_subrange(start, end))
+      .collect()
+  }
+  fn words_and_punct<'b>(&self, range: &DNMRange<'b>) -> Vec<DNMRange<'b>> { self.words(range) }
+  fn for_dnm(&self, _dnm: &DNM) -> &dyn Segmenter { self }
+}
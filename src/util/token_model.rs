@@ -2,8 +2,8 @@
 use crate::dnm;
 use crate::dnm::SpecialTagsOption;
 use crate::parallel_data::*;
+use crate::tokenizer::token::TokenType;
 use libxml::xpath::Context;
-use regex::Regex;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
@@ -30,8 +30,6 @@ pub fn extract(
   )));
   let space = ' ';
   let linebreak = '\n';
-  // Integers, floats, subfigure numbers
-  let is_numeric = Regex::new(r"^-?(?:\d+)(?:[a-k]|(?:\.\d+(?:[eE][+-]?\d+)?))?$").unwrap();
 
   let mut corpus = Corpus::new(corpus_path);
   if discard_math {
@@ -68,41 +66,39 @@ pub fn extract(
       paragraph_count += 1;
       let mut paragraph_buffer = String::new();
       let mut invalid_paragraph = false;
-      'words: for word in paragraph.word_and_punct_iter() {
-        if !word.range.is_empty() {
-          let word_string = word.range.get_plaintext().to_lowercase();
-          if word_string.len() > MAX_WORD_LENGTH {
-            // Using a more aggressive normalization, large words tend to be conversion
-            // errors with lost whitespace - drop the entire paragraph when this occurs.
-            overflow_count += 1;
-            invalid_paragraph = true;
-            break 'words;
-          }
-          let mut word_str: &str = &word_string;
-          // Note: the formula and citation counts are an approximate lower bound, as
-          // sometimes they are not cleanly tokenized, e.g. $k$-dimensional
-          // will be the word string "mathformula-dimensional"
-          let lexeme_str: String;
-          if word_string.contains("mathformula") {
+      'words: for token in paragraph.token_iter() {
+        let word_string = token.range.get_plaintext().to_lowercase();
+        if token.token_type != TokenType::Url && word_string.len() > MAX_WORD_LENGTH {
+          // Using a more aggressive normalization, large words tend to be conversion
+          // errors with lost whitespace - drop the entire paragraph when this occurs.
+          overflow_count += 1;
+          invalid_paragraph = true;
+          break 'words;
+        }
+        let mut word_str: &str = &word_string;
+        let lexeme_str: String;
+        match token.token_type {
+          TokenType::Math => {
             if !discard_math {
-              lexeme_str = dnm::node::lexematize_math(word.range.get_node(), &mut context);
+              lexeme_str = dnm::node::lexematize_math(token.range.get_node(), &mut context);
             } else {
               lexeme_str = String::new();
             }
             word_str = &lexeme_str;
             formula_count += 1;
-          } else if word_string.contains("citationelement") {
+          },
+          TokenType::Citation => {
             word_str = "citationelement";
             citation_count += 1;
-          } else if is_numeric.is_match(&word_string) {
+          },
+          TokenType::Number => {
             num_count += 1;
             word_str = "NUM";
-          } else {
-            word_count += 1;
-          }
-          paragraph_buffer.push_str(word_str);
-          paragraph_buffer.push(space);
+          },
+          _ => word_count += 1,
         }
+        paragraph_buffer.push_str(word_str);
+        paragraph_buffer.push(space);
       }
       // if valid paragraph, print to the token model file
       if !invalid_paragraph {
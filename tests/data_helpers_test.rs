@@ -0,0 +1,45 @@
+use libxml::xpath::Context;
+use llamapun::dnm::{DNMRange, DNM};
+use llamapun::util::data_helpers::{ams_normalize_word_range, LexicalOptions};
+
+#[test]
+/// Test the AMS normalization of untyped words, which matches on their lowercased text
+fn test_ams_normalize_word_range() {
+  let (doc, dnm) = DNM::from_str(
+    "2A mathformulath citationelements citationelement CitationElement Theorem",
+    None,
+  )
+  .unwrap();
+  let mut context = Context::new(&doc).unwrap();
+  let mut start = 0;
+  let mut normalized = Vec::new();
+  for word in dnm.plaintext.split(' ') {
+    let end = start + word.chars().count();
+    if word.is_empty() {
+      start = end + 1;
+      continue;
+    }
+    let range = DNMRange {
+      start,
+      end,
+      dnm: &dnm,
+    };
+    let options = LexicalOptions {
+      discard_math: true,
+      ..LexicalOptions::default()
+    };
+    normalized.push(ams_normalize_word_range(&range, &mut context, options).unwrap());
+    start = end + 1;
+  }
+  assert_eq!(
+    normalized,
+    vec![
+      "NUM",
+      "",
+      "citationelement",
+      "citationelement",
+      "citationelement",
+      "theorem"
+    ]
+  );
+}
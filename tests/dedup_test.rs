@@ -0,0 +1,98 @@
+//! Tests for near-duplicate detection
+extern crate llamapun;
+
+use llamapun::dedup::*;
+use llamapun::parallel_data::Corpus;
+use std::env;
+use std::fs;
+
+const ORIGINAL: &str = "We prove that every bounded sequence of real numbers has a convergent \
+                        subsequence, using a bisection argument on the interval containing it.";
+const REVISED: &str = "We prove that every bounded sequence of real numbers has a convergent \
+                       subsequence, using a bisection argument on the interval containing it";
+const UNRELATED: &str = "The experimental setup consists of a laser, two mirrors and a beam \
+                         splitter mounted on an optical table in a dark room.";
+
+#[test]
+fn test_fingerprints() {
+  let hasher = MinHasher::default();
+  let original = hasher.fingerprint(ORIGINAL);
+  assert_eq!(original.0.len(), 128);
+  assert_eq!(original.similarity(&hasher.fingerprint(ORIGINAL)), 1.0);
+  assert!(original.similarity(&hasher.fingerprint(REVISED)) > 0.8);
+  assert!(original.similarity(&hasher.fingerprint(UNRELATED)) < 0.2);
+  assert_eq!(
+    hasher.fingerprint("").similarity(&hasher.fingerprint("")),
+    0.0
+  );
+
+  let original = SimHash::new(ORIGINAL, 2);
+  assert_eq!(original.hamming_distance(&SimHash::new(ORIGINAL, 2)), 0);
+  assert!(
+    original.hamming_distance(&SimHash::new(REVISED, 2))
+      < original.hamming_distance(&SimHash::new(UNRELATED, 2))
+  );
+}
+
+#[test]
+fn test_lsh_index() {
+  let hasher = MinHasher::default();
+  let mut index = LshIndex::new(32);
+  index.insert("original".to_string(), hasher.fingerprint(ORIGINAL));
+  index.insert("revised".to_string(), hasher.fingerprint(REVISED));
+  index.insert("unrelated".to_string(), hasher.fingerprint(UNRELATED));
+  assert_eq!(index.len(), 3);
+
+  let matches = index.query(&hasher.fingerprint(ORIGINAL), 0.8);
+  assert_eq!(matches.len(), 2);
+  assert_eq!(matches[0], ("original", 1.0));
+  assert_eq!(matches[1].0, "revised");
+  assert_eq!(
+    index.clusters(0.8),
+    vec![vec!["original".to_string(), "revised".to_string()]]
+  );
+
+  let mut simhash_index = LshIndex::new(8);
+  simhash_index.insert("original".to_string(), SimHash::new(ORIGINAL, 2));
+  simhash_index.insert("unrelated".to_string(), SimHash::new(UNRELATED, 2));
+  assert_eq!(
+    simhash_index.query(&SimHash::new(ORIGINAL, 2), 0.9),
+    vec![("original", 1.0)]
+  );
+}
+
+#[test]
+fn test_corpus_near_duplicates() {
+  let directory = env::temp_dir().join("llamapun_near_duplicates");
+  let _ = fs::remove_dir_all(&directory);
+  fs::create_dir_all(&directory).unwrap();
+  let page = |paragraphs: &[&str]| -> String {
+    let divs: String = paragraphs
+      .iter()
+      .enumerate()
+      .map(|(index, text)| format!("<div class=\"ltx_para\" id=\"p{index}\"><p>{text}</p></div>"))
+      .collect();
+    format!("<html><body>{divs}</body></html>")
+  };
+  fs::write(directory.join("a.html"), page(&[ORIGINAL, UNRELATED])).unwrap();
+  fs::write(directory.join("b.html"), page(&[REVISED, UNRELATED])).unwrap();
+  fs::write(directory.join("c.html"), page(&[UNRELATED])).unwrap();
+
+  let corpus = Corpus::new(directory.to_str().unwrap().to_string());
+  let duplicates = corpus.near_duplicates(&NearDuplicateOptions::default());
+  let path = |name: &str| directory.join(name).to_str().unwrap().to_string();
+  assert!(duplicates.paragraphs.contains(&vec![
+    format!("{}#p0", path("a.html")),
+    format!("{}#p0", path("b.html"))
+  ]));
+  assert!(duplicates.paragraphs.contains(&vec![
+    format!("{}#p1", path("a.html")),
+    format!("{}#p1", path("b.html")),
+    format!("{}#p0", path("c.html"))
+  ]));
+  assert_eq!(
+    duplicates.documents,
+    vec![vec![path("a.html"), path("b.html")]]
+  );
+  fs::remove_dir_all(&directory).unwrap();
+}
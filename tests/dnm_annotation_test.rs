@@ -0,0 +1,107 @@
+//! Tests for the standoff annotation store
+use libxml::parser::Parser;
+use llamapun::dnm::*;
+use llamapun::tokenizer::Tokenizer;
+use std::env;
+
+#[test]
+fn test_annotation_layers() {
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let dnm = DNM::new(doc.get_root_readonly().unwrap(), DNMParameters::default());
+  let range = |start, end| DNMRange {
+    start,
+    end,
+    dnm: &dnm,
+  };
+
+  let mut store = AnnotationStore::new();
+  store.add(
+    "entities",
+    Annotation::from_range(&range(25, 29))
+      .with_attribute("type", "link")
+      .with_attribute("score", 0.5),
+  );
+  store.add(
+    "entities",
+    Annotation::new(0, 5).with_attribute("heading", true),
+  );
+  store.add_range("chunks", &range(15, 33));
+  assert_eq!(
+    store.layer_names().collect::<Vec<_>>(),
+    vec!["chunks", "entities"]
+  );
+
+  let entities = store.layer("entities").unwrap();
+  assert_eq!(entities.len(), 2);
+  // layers are sorted by offsets, regardless of insertion order
+  assert_eq!(entities.get(0).unwrap().start, 0);
+  let link = entities.get(1).unwrap();
+  assert_eq!(link.range(&dnm).get_plaintext(), "link");
+  assert_eq!(
+    link.get("type").and_then(AnnotationValue::as_str),
+    Some("link")
+  );
+  assert_eq!(
+    link.get("score").and_then(AnnotationValue::as_float),
+    Some(0.5)
+  );
+  assert_eq!(
+    entities
+      .get(0)
+      .unwrap()
+      .get("heading")
+      .and_then(AnnotationValue::as_bool),
+    Some(true)
+  );
+
+  let chunk = range(15, 33);
+  assert_eq!(store.within("entities", &chunk).count(), 1);
+  assert_eq!(store.overlapping("entities", &range(3, 26)).count(), 2);
+  assert_eq!(store.containing("chunks", &range(26, 28)).count(), 1);
+  assert_eq!(store.containing("chunks", &range(10, 20)).count(), 0);
+  assert_eq!(store.overlapping("missing", &chunk).count(), 0);
+
+  let path = env::temp_dir().join("llamapun_annotation_store.json");
+  let path = path.to_str().unwrap();
+  store.save(path).unwrap();
+  assert_eq!(AnnotationStore::load(path).unwrap(), store);
+
+  assert!(store.remove_layer("chunks").is_some());
+  assert!(store.layer("chunks").is_none());
+}
+
+#[test]
+fn test_tokenizer_annotations() {
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let dnm = DNM::new(
+    doc.get_root_readonly().unwrap(),
+    DNMParameters::llamapun_normalization(),
+  );
+  let tokenizer = Tokenizer::default();
+  let mut store = AnnotationStore::new();
+
+  let sentences = tokenizer.annotate_sentences(&dnm, &mut store);
+  assert!(sentences.len() > 10);
+  assert_eq!(store.layer(SENTENCE_LAYER).unwrap().len(), sentences.len());
+
+  let sentence = &sentences[5];
+  let words = tokenizer.annotate_words(sentence, &mut store);
+  assert!(!words.is_empty());
+  let annotated: Vec<&Annotation> = store.within(WORD_LAYER, sentence).collect();
+  assert_eq!(annotated.len(), words.len());
+  for (index, (annotation, word)) in annotated.iter().zip(words.iter()).enumerate() {
+    assert_eq!(annotation.range(&dnm), *word);
+    assert_eq!(
+      annotation.get("index").and_then(AnnotationValue::as_int),
+      Some(index as i64)
+    );
+  }
+
+  // every word lies in exactly one sentence
+  let word = &words[0];
+  let containing: Vec<&Annotation> = store.containing(SENTENCE_LAYER, word).collect();
+  assert_eq!(containing.len(), 1);
+  assert_eq!(containing[0].range(&dnm), *sentence);
+}
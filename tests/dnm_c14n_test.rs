@@ -61,3 +61,67 @@ fn test_c14n_math_hash() {
   }
   assert_eq!(formula_c14ns.len(), formula_hashes.len());
 }
+
+#[test]
+fn test_c14n_options() {
+  let parser = Parser::default();
+  let doc = parser
+    .parse_string(
+      "<div><p class=\"b a\" id=\"p1\">Hello <b>world</b><!-- note --></p>\n<math><semantics>\
+       <mi>x</mi><annotation>tex</annotation></semantics></math></div>",
+    )
+    .unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let dnm = DNM::new(root, DNMParameters::default());
+
+  // the default options are the basic canonical form
+  let options = C14nOptions::default();
+  assert_eq!(dnm.to_c14n(&options), dnm.to_c14n_basic());
+  assert_eq!(
+    dnm.to_c14n_basic(),
+    "<div><p class=\"a b\">Hello <b>world</b></p><math><mi>x</mi></math></div>"
+  );
+
+  // attribute allow-list, element drop and unwrap lists
+  let mut options = C14nOptions::default();
+  options.attributes.insert("id".to_string());
+  options.drop_elements.insert("b".to_string());
+  options.unwrap_elements.insert("math".to_string());
+  options.unwrap_elements.remove("semantics");
+  options.drop_elements.remove("annotation");
+  assert_eq!(
+    dnm.to_c14n(&options),
+    "<div><p class=\"a b\" id=\"p1\">Hello </p><semantics><mi>x</mi><annotation>tex</annotation>\
+     </semantics></div>"
+  );
+
+  // only class values are normalized, and attribute values are escaped
+  let doc = parser
+    .parse_string("<div><p class=\" b  a \" title=\"x  &lt; &quot;y&quot; &amp; z\">t</p></div>")
+    .unwrap();
+  let attribute_dnm = DNM::new(doc.get_root_readonly().unwrap(), DNMParameters::default());
+  let mut options = C14nOptions::default();
+  options.attributes.insert("title".to_string());
+  assert_eq!(
+    attribute_dnm.to_c14n(&options),
+    "<div><p class=\"a b\" title=\"x  &lt; &quot;y&quot; &amp; z\">t</p></div>"
+  );
+
+  // whitespace policies and indented output
+  let options = C14nOptions {
+    whitespace: C14nWhitespace::Collapse,
+    output: C14nOutput::Indented,
+    ..C14nOptions::default()
+  };
+  assert_eq!(
+    dnm.to_c14n(&options),
+    "<div>\n  <p class=\"a b\">\n    Hello\n    <b>\n      world\n    </b>\n  </p>\n  <math>\n    \
+     <mi>\n      x\n    </mi>\n  </math>\n</div>"
+  );
+  let options = C14nOptions {
+    whitespace: C14nWhitespace::Preserve,
+    ..C14nOptions::default()
+  };
+  // whitespace-only text nodes are kept, as normalized by the DNM
+  assert!(dnm.to_c14n(&options).contains("world</b></p> <math>"));
+}
@@ -0,0 +1,75 @@
+//! Tests for the structural diff of canonical forms
+extern crate libxml;
+extern crate llamapun;
+
+use libxml::parser::Parser;
+use llamapun::dnm::*;
+
+#[test]
+fn test_document_diff() {
+  let parser = Parser::default();
+  let old_doc = parser
+    .parse_string(
+      "<div><h1>Title</h1><p>The quick brown fox jumps.</p><p>Removed paragraph here.</p></div>",
+    )
+    .unwrap();
+  let new_doc = parser
+    .parse_string(
+      "<div><h1 class=\"ltx_title\">Title</h1><p>The quick red fox jumps.</p><p>Added one.</p>\
+       <p>Removed paragraph here.</p></div>",
+    )
+    .unwrap();
+  let old_dnm = DNM::new(
+    old_doc.get_root_readonly().unwrap(),
+    DNMParameters::default(),
+  );
+  let new_dnm = DNM::new(
+    new_doc.get_root_readonly().unwrap(),
+    DNMParameters::default(),
+  );
+  let options = C14nOptions::default();
+
+  assert!(old_dnm.diff(&old_dnm, &options).is_empty());
+
+  let diff = old_dnm.diff(&new_dnm, &options);
+  assert_eq!(diff.nodes.len(), 3);
+  match diff.nodes[0] {
+    NodeChange::Changed(old, new) => {
+      assert_eq!(old.get_name(), "h1");
+      assert_eq!(new.get_property("class").unwrap(), "ltx_title");
+    },
+    ref other => panic!("expected a changed heading, got {other:?}"),
+  }
+  match diff.nodes[1] {
+    NodeChange::Changed(old, new) => {
+      assert_eq!(old.get_content(), "The quick brown fox jumps.");
+      assert_eq!(new.get_content(), "The quick red fox jumps.");
+    },
+    ref other => panic!("expected a changed text node, got {other:?}"),
+  }
+  match diff.nodes[2] {
+    NodeChange::Inserted(new) => assert_eq!(new.get_content(), "Added one."),
+    ref other => panic!("expected an inserted paragraph, got {other:?}"),
+  }
+
+  assert_eq!(diff.text.len(), 2);
+  assert_eq!(diff.text[0].old.get_plaintext(), "brown");
+  assert_eq!(diff.text[0].new.get_plaintext(), "red");
+  // the inserted paragraph is mapped to an empty range before the following paragraph
+  assert!(diff.text[1].old.is_empty());
+  assert_eq!(
+    diff.text[1].old.start,
+    old_dnm.plaintext.find("Removed").unwrap()
+  );
+  assert_eq!(diff.text[1].new.get_plaintext(), "Added one.");
+
+  // in reverse, the paragraph is deleted
+  let reverse = new_dnm.diff(&old_dnm, &options);
+  assert!(reverse.nodes.iter().any(
+    |change| matches!(change, NodeChange::Deleted(node) if node.get_content() == "Added one.")
+  ));
+  assert!(reverse
+    .text
+    .iter()
+    .any(|change| change.old.get_plaintext() == "Added one." && change.new.is_empty()));
+}
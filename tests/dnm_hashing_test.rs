@@ -0,0 +1,95 @@
+//! Tests for the digests of canonical forms
+extern crate libxml;
+extern crate llamapun;
+
+use libxml::parser::Parser;
+use llamapun::dnm::*;
+
+#[test]
+fn test_hash_algorithms() {
+  assert_eq!(
+    HashAlgorithm::Sha256.digest(b"abc"),
+    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+  );
+  assert_eq!(
+    HashAlgorithm::Md5.digest(b"abc"),
+    "900150983cd24fb0d6963f7d28e17f72"
+  );
+  assert_eq!(HashAlgorithm::Blake3.digest(b"abc").len(), 64);
+  assert_eq!(HashAlgorithm::Xxh3.digest(b"abc").len(), 32);
+
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let dnm = DNM::new(doc.get_root_readonly().unwrap(), DNMParameters::default());
+  let options = C14nOptions::default();
+  // the basic hash is the MD5 digest of the basic canonical form
+  assert_eq!(
+    dnm.to_hash_basic(),
+    HashAlgorithm::Md5.digest(dnm.to_c14n_basic().as_bytes())
+  );
+  assert_eq!(
+    dnm.to_hash(&options, HashAlgorithm::Sha256),
+    HashAlgorithm::Sha256.digest(dnm.to_c14n_basic().as_bytes())
+  );
+  assert_ne!(
+    dnm.to_hash(&options, HashAlgorithm::Blake3),
+    dnm.to_hash(&options, HashAlgorithm::Xxh3)
+  );
+}
+
+#[test]
+fn test_merkle_tree() {
+  let parser = Parser::default();
+  let original = parser
+    .parse_string(
+      "<div><p id=\"a\">First paragraph.</p><p>Second <b>one</b>.</p><!-- comment --></div>",
+    )
+    .unwrap();
+  let revised = parser
+    .parse_string("<div><p id=\"b\">First paragraph.</p><p>Second <b>two</b>.</p></div>")
+    .unwrap();
+  let original_dnm = DNM::new(
+    original.get_root_readonly().unwrap(),
+    DNMParameters::default(),
+  );
+  let revised_dnm = DNM::new(
+    revised.get_root_readonly().unwrap(),
+    DNMParameters::default(),
+  );
+  let options = C14nOptions::default();
+
+  let original_tree = original_dnm
+    .to_merkle_tree(&options, HashAlgorithm::Blake3)
+    .unwrap();
+  let revised_tree = revised_dnm
+    .to_merkle_tree(&options, HashAlgorithm::Blake3)
+    .unwrap();
+  assert_eq!(original_tree.children.len(), 2);
+  // ids are not part of the canonical form, so the first paragraph is unchanged
+  assert_eq!(
+    original_tree.children[0].digest,
+    revised_tree.children[0].digest
+  );
+  assert_ne!(original_tree.digest, revised_tree.digest);
+  assert!(original_tree.changed_nodes(&original_tree).is_empty());
+
+  let changed = original_tree.changed_nodes(&revised_tree);
+  assert_eq!(changed.len(), 1);
+  assert_eq!(changed[0].get_content(), "one");
+  assert_eq!(changed[0].get_parent().unwrap().get_name(), "b");
+
+  // a paragraph missing from the other tree is reported as a whole
+  let shortened = parser
+    .parse_string("<div><p>First paragraph.</p></div>")
+    .unwrap();
+  let shortened_dnm = DNM::new(
+    shortened.get_root_readonly().unwrap(),
+    DNMParameters::default(),
+  );
+  let shortened_tree = shortened_dnm
+    .to_merkle_tree(&options, HashAlgorithm::Blake3)
+    .unwrap();
+  let changed = original_tree.changed_nodes(&shortened_tree);
+  assert_eq!(changed.len(), 1);
+  assert_eq!(changed[0].get_name(), "p");
+}
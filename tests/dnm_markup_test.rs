@@ -0,0 +1,112 @@
+//! Tests for writing DNM ranges back into the DOM
+use libxml::parser::Parser;
+use libxml::xpath::Context;
+use llamapun::dnm::*;
+use llamapun::tokenizer::Tokenizer;
+
+#[test]
+fn test_wrap_ranges_across_elements() {
+  let parser = Parser::default();
+  let mut doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let dnm = DNM::new(doc.get_root_readonly().unwrap(), DNMParameters::default());
+  let range = |start, end| DNMRange {
+    start,
+    end,
+    dnm: &dnm,
+  };
+  let crossing = range(15, 33);
+  let inner = range(36, 44);
+  assert_eq!(crossing.get_plaintext(), "Some text link and");
+  assert_eq!(inner.get_plaintext(), "bit more");
+
+  // overlapping ranges are rejected, without touching the document
+  assert!(dnm
+    .wrap_ranges(
+      &mut doc,
+      &[crossing.clone(), range(30, 40)],
+      "span",
+      SENTENCE_CLASS
+    )
+    .is_err());
+
+  let created = dnm
+    .wrap_ranges(&mut doc, &[inner, crossing], "span", SENTENCE_CLASS)
+    .unwrap();
+  // one wrapper per text node of the crossing range, and one for the inner range
+  assert_eq!(created, 4);
+
+  let serialized = doc.to_string();
+  assert!(serialized
+    .contains("<a href=\"https://kwarc.info/\"><span class=\"llamapun_sentence\">link</span></a>"));
+  assert!(serialized.contains("<span class=\"llamapun_sentence\">bit more</span> text."));
+
+  // the modified document serializes, reparses, and keeps its plaintext
+  let reparsed = parser.parse_string(&serialized).unwrap();
+  let rebuilt = DNM::new(
+    reparsed.get_root_readonly().unwrap(),
+    DNMParameters::default(),
+  );
+  assert_eq!(rebuilt.plaintext, dnm.plaintext);
+}
+
+#[test]
+fn test_wrap_ranges_across_block_separators() {
+  let parser = Parser::default();
+  let mut doc = parser
+    .parse_string("<div><p>First paragraph.</p><p>Second one.</p></div>")
+    .unwrap();
+  let dnm = DNM::new(
+    doc.get_root_readonly().unwrap(),
+    DNMParameters {
+      block_boundaries: Some(BlockBoundaries {
+        separator: " | ".to_string(),
+        ..BlockBoundaries::default()
+      }),
+      ..DNMParameters::default()
+    },
+  );
+  assert_eq!(dnm.plaintext, "First paragraph. | Second one. | ");
+  let start = dnm.plaintext.find("paragraph").unwrap();
+  let end = dnm.plaintext.find(" one").unwrap();
+  let crossing = DNMRange {
+    start,
+    end,
+    dnm: &dnm,
+  };
+  assert_eq!(crossing.get_plaintext(), "paragraph. | Second");
+
+  // the separator doesn't make the paragraphs part of the range, only their text is wrapped
+  let created = dnm
+    .wrap_ranges(&mut doc, &[crossing], "span", SENTENCE_CLASS)
+    .unwrap();
+  assert_eq!(created, 2);
+  let serialized = doc.to_string();
+  assert!(serialized.contains(
+    "<div><p>First <span class=\"llamapun_sentence\">paragraph.</span></p><p><span \
+     class=\"llamapun_sentence\">Second</span> one.</p></div>"
+  ));
+}
+
+#[test]
+fn test_wrap_sentences() {
+  let parser = Parser::default_html();
+  let mut doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let dnm = DNM::new(
+    doc.get_root_readonly().unwrap(),
+    DNMParameters::llamapun_normalization(),
+  );
+  let sentences = Tokenizer::default().sentences(&dnm);
+  let created = dnm
+    .wrap_ranges(&mut doc, &sentences, "span", SENTENCE_CLASS)
+    .unwrap();
+  assert!(created >= sentences.len());
+
+  let html = doc.to_string();
+  let reparsed = parser.parse_string(&html).unwrap();
+  let context = Context::new(&reparsed).unwrap();
+  let wrappers = context
+    .evaluate("//span[@class='llamapun_sentence']")
+    .unwrap()
+    .get_readonly_nodes_as_vec();
+  assert_eq!(wrappers.len(), created);
+}
@@ -0,0 +1,135 @@
+//! Tests for DNMParameters presets and config files
+use libxml::parser::Parser;
+use libxml::readonly::RoNode;
+use llamapun::dnm::*;
+use std::env;
+
+#[test]
+fn test_presets() {
+  let headings = DNMParameters::preset("headings").unwrap();
+  assert!(matches!(
+    headings.special_tag_name_options.get("cite"),
+    Some(SpecialTagsOption::Skip)
+  ));
+  assert!(matches!(
+    headings.special_tag_name_options.get("math"),
+    Some(SpecialTagsOption::Skip)
+  ));
+  let llamapun = DNMParameters::preset("llamapun_normalization").unwrap();
+  assert!(matches!(
+    llamapun.special_tag_name_options.get("math"),
+    Some(SpecialTagsOption::Normalize(_))
+  ));
+  assert!(DNMParameters::preset("no_such_preset").is_err());
+}
+
+#[test]
+fn test_load_config() {
+  let parameters = DNMParameters::load("tests/resources/discard_math.toml").unwrap();
+  let mut expected = DNMParameters::discard_math();
+  expected.wrap_tokens = false;
+  expected.unicode_normalization = UnicodeNormalization::Nfkc;
+  expected.special_tag_class_options.insert(
+    "ltx_ref".to_string(),
+    SpecialTagsOption::Normalize("REFERENCE".to_string()),
+  );
+  expected.special_tag_selector_options.push((
+    TagSelector::parse("figcaption span.ltx_tag").unwrap(),
+    SpecialTagsOption::Skip,
+  ));
+  assert_eq!(
+    DNMParametersConfig::from_parameters(&parameters).unwrap(),
+    DNMParametersConfig::from_parameters(&expected).unwrap()
+  );
+
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let dnm = DNM::new(doc.get_root_readonly().unwrap(), parameters);
+  assert!(!dnm.plaintext.contains("mathformula"));
+}
+
+#[test]
+fn test_save_config_roundtrip() {
+  let mut parameters = DNMParameters::llamapun_normalization();
+  parameters.block_boundaries = Some(BlockBoundaries::default());
+  parameters.special_tag_selector_options.push((
+    TagSelector::parse("a[href^='#bib']").unwrap(),
+    SpecialTagsOption::Normalize("CitationElement".to_string()),
+  ));
+  let config = DNMParametersConfig::from_parameters(&parameters).unwrap();
+
+  for extension in &["json", "toml"] {
+    let path = env::temp_dir().join(format!("llamapun_dnm_parameters.{extension}"));
+    let path = path.to_str().unwrap();
+    parameters.save(path).unwrap();
+    let reloaded = DNMParameters::load(path).unwrap();
+    assert_eq!(
+      DNMParametersConfig::from_parameters(&reloaded).unwrap(),
+      config
+    );
+  }
+
+  // closures can't be saved declaratively
+  parameters.special_tag_name_options.insert(
+    "math".to_string(),
+    SpecialTagsOption::function_normalize(|_| "formula".to_string()),
+  );
+  let path = env::temp_dir().join("llamapun_dnm_parameters_closure.json");
+  assert!(parameters.save(path.to_str().unwrap()).is_err());
+}
+
+#[test]
+fn test_validation_errors() {
+  assert!(DNMParameters::default().check().is_ok());
+  assert!(DNMParameters::llamapun_normalization().check().is_ok());
+
+  let conflicting = DNMParameters {
+    stem_words_once: true,
+    stem_words_full: true,
+    convert_to_lowercase: true,
+    normalize_unicode: true,
+    unicode_normalization: UnicodeNormalization::Nfc,
+    ..Default::default()
+  };
+  let error = conflicting.check().unwrap_err();
+  assert_eq!(
+    error.conflicts,
+    vec![
+      ParameterConflict::ConflictingStemming,
+      ParameterConflict::RedundantLowercase,
+      ParameterConflict::ConflictingUnicodeNormalization(UnicodeNormalization::Nfc),
+    ]
+  );
+  assert!(error
+    .to_string()
+    .contains("stem_words_once and stem_words_full"));
+
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  assert_eq!(
+    DNM::try_new(root, conflicting).unwrap_err(),
+    DNMError::InvalidParameters(error)
+  );
+  assert_eq!(
+    DNM::try_new(RoNode::null(), DNMParameters::default()).unwrap_err(),
+    DNMError::MissingRootNode
+  );
+  // a redundant lowercase setting is only a warning
+  let redundant = DNMParameters {
+    stem_words_once: true,
+    convert_to_lowercase: true,
+    ..Default::default()
+  };
+  assert_eq!(
+    redundant.check().unwrap_err().conflicts,
+    vec![ParameterConflict::RedundantLowercase]
+  );
+  assert!(ParameterConflict::RedundantLowercase.is_warning());
+  assert!(DNM::try_new(root, redundant).is_ok());
+  let dnm = DNM::try_new(root, DNMParameters::default()).unwrap();
+  assert_eq!(
+    dnm.plaintext,
+    "Title Subtitle Some text link and a bit more text. "
+  );
+}
@@ -0,0 +1,140 @@
+//! Tests for the DNMRange algebra
+use libxml::parser::Parser;
+use llamapun::dnm::*;
+use llamapun::tokenizer::Tokenizer;
+
+#[test]
+fn test_range_algebra() {
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let dnm = DNM::new(root, DNMParameters::default());
+  assert_eq!(
+    dnm.plaintext,
+    "Title Subtitle Some text link and a bit more text. "
+  );
+  let range = |start, end| DNMRange {
+    start,
+    end,
+    dnm: &dnm,
+  };
+
+  let some_text = range(15, 24);
+  let text_link = range(20, 28);
+  assert_eq!(some_text.get_plaintext(), "Some text");
+  assert_eq!(text_link.get_plaintext(), "text lin");
+
+  assert!(some_text.overlaps(&text_link));
+  assert!(!range(0, 5).overlaps(&range(5, 10)));
+  assert!(some_text.contains(&range(20, 24)));
+  assert!(!some_text.contains(&text_link));
+  assert!(some_text.contains_offset(15));
+  assert!(!some_text.contains_offset(24));
+
+  let intersection = some_text.intersect(&text_link).unwrap();
+  assert_eq!(intersection.get_plaintext(), "text");
+  assert!(range(0, 5).intersect(&range(6, 14)).is_none());
+
+  assert_eq!(
+    some_text.union(&text_link).unwrap().get_plaintext(),
+    "Some text lin"
+  );
+  assert_eq!(
+    range(0, 5).union(&range(5, 14)).unwrap().get_plaintext(),
+    "Title Subtitle"
+  );
+  assert!(range(0, 5).union(&range(15, 19)).is_none());
+  assert_eq!(
+    range(0, 5).cover(&range(15, 19)).unwrap().get_plaintext(),
+    "Title Subtitle Some"
+  );
+
+  assert_eq!(range(21, 24).expand_to_word().get_plaintext(), "text");
+  assert_eq!(range(26, 32).expand_to_word().get_plaintext(), "link and");
+  assert_eq!(range(5, 6).expand_to_word().get_plaintext(), " ");
+
+  let other_dnm = DNM::new(root, DNMParameters::default());
+  let other = DNMRange {
+    start: 0,
+    end: 5,
+    dnm: &other_dnm,
+  };
+  assert!(range(0, 5).cover(&other).is_err());
+  assert!(range(0, 5).union(&other).is_none());
+
+  let mut sorted = vec![text_link.clone(), range(20, 24), some_text.clone()];
+  sorted.sort();
+  assert_eq!(sorted, vec![some_text, range(20, 24), text_link]);
+}
+
+#[test]
+fn test_range_nodes() {
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let dnm = DNM::new(root, DNMParameters::default());
+  let range = DNMRange {
+    start: 15,
+    end: 33,
+    dnm: &dnm,
+  };
+  assert_eq!(range.get_plaintext(), "Some text link and");
+
+  // the covered text nodes, preceded by the elements they are nested in, below the root
+  let nodes: Vec<_> = range.nodes().collect();
+  assert_eq!(nodes.len(), 5);
+  assert_eq!(nodes[0].get_name(), "body");
+  assert!(nodes[1].is_text_node());
+  assert_eq!(nodes[1].get_parent(), Some(nodes[0]));
+  assert_eq!(nodes[2].get_name(), "a");
+  assert!(nodes[3].is_text_node());
+  assert_eq!(nodes[3].get_parent(), Some(nodes[2]));
+  assert!(nodes[4].is_text_node());
+  assert_eq!(nodes[4].get_parent(), Some(nodes[0]));
+  assert_eq!(range.get_node(), nodes[1]);
+  assert!(!nodes.contains(&root));
+
+  // ranges of DNMs without back-mapping cover no nodes
+  let parameters = DNMParameters {
+    support_back_mapping: false,
+    ..DNMParameters::default()
+  };
+  let dnm = DNM::new(root, parameters);
+  let range = DNMRange {
+    start: 15,
+    end: 33,
+    dnm: &dnm,
+  };
+  assert_eq!(range.nodes().count(), 0);
+}
+
+#[test]
+fn test_expand_to_sentence() {
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let dnm = DNM::new(root, DNMParameters::llamapun_normalization());
+  let tokenizer = Tokenizer::default();
+  let sentences = tokenizer.sentences(&dnm);
+  assert!(sentences.len() > 10);
+
+  for sentence in sentences.iter().skip(5).take(5) {
+    assert!(sentence.end - sentence.start > 5);
+    let inner = sentence.get_subrange(2, 5);
+    assert_eq!(&inner.expand_to_sentence(&sentences), sentence);
+    // a range spanning two sentences expands to both
+    let next = sentences
+      .iter()
+      .find(|other| other.start >= sentence.end)
+      .unwrap();
+    let spanning = DNMRange {
+      start: sentence.end - 1,
+      end: next.start + 1,
+      dnm: &dnm,
+    };
+    assert_eq!(
+      spanning.expand_to_sentence(&sentences),
+      sentence.cover(next).unwrap()
+    );
+  }
+}
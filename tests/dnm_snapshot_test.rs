@@ -0,0 +1,146 @@
+//! Tests for DNM snapshots
+use libxml::parser::Parser;
+use libxml::readonly::RoNode;
+use libxml::tree::Document;
+use libxml::xpath::Context;
+use llamapun::dnm::*;
+use std::env;
+
+#[test]
+fn test_snapshot_roundtrip() {
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let dnm = DNM::new(root, DNMParameters::llamapun_normalization());
+
+  let snapshot_path = env::temp_dir().join("llamapun_0903.1000.dnm.json");
+  let snapshot_path = snapshot_path.to_str().unwrap();
+  dnm.to_snapshot().unwrap().save(snapshot_path).unwrap();
+
+  // reload against a fresh parse of the same document
+  let reparsed = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let reparsed_root = reparsed.get_root_readonly().unwrap();
+  let snapshot = DNMSnapshot::load(snapshot_path).unwrap();
+  let reloaded = DNM::from_snapshot(
+    snapshot.clone(),
+    reparsed_root,
+    DNMParameters::llamapun_normalization(),
+  )
+  .unwrap();
+
+  assert_eq!(reloaded.plaintext, dnm.plaintext);
+  assert_eq!(reloaded.byte_offsets, dnm.byte_offsets);
+  assert_eq!(reloaded.node_map.len(), dnm.node_map.len());
+  assert_eq!(reloaded.back_map.len(), dnm.back_map.len());
+  assert_eq!(reloaded.to_snapshot().unwrap(), snapshot);
+
+  let range = DNMRange {
+    start: 100,
+    end: 150,
+    dnm: &dnm,
+  };
+  let reloaded_range = DNMRange {
+    start: 100,
+    end: 150,
+    dnm: &reloaded,
+  };
+  assert_eq!(reloaded_range.get_plaintext(), range.get_plaintext());
+  assert_eq!(reloaded_range.serialize(), range.serialize());
+
+  // plaintext-only consumers don't need the document at all
+  let plaintext_only = DNM::from_snapshot_plaintext(snapshot).unwrap();
+  assert_eq!(plaintext_only.plaintext, dnm.plaintext);
+  assert!(plaintext_only.back_map.is_empty());
+}
+
+#[test]
+fn test_snapshot_mismatched_document() {
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let dnm = DNM::new(
+    doc.get_root_readonly().unwrap(),
+    DNMParameters::llamapun_normalization(),
+  );
+  let snapshot = dnm.to_snapshot().unwrap();
+
+  let other_parser = Parser::default();
+  let other = other_parser
+    .parse_file("tests/resources/file01.xml")
+    .unwrap();
+  let reattached = DNM::from_snapshot(
+    snapshot,
+    other.get_root_readonly().unwrap(),
+    DNMParameters::llamapun_normalization(),
+  );
+  assert!(reattached.is_err());
+}
+
+#[test]
+fn test_snapshot_corrupt_byte_offsets() {
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let dnm = DNM::new(
+    first_paragraph(&doc),
+    DNMParameters::llamapun_normalization(),
+  );
+  let snapshot = dnm.to_snapshot().unwrap();
+
+  // a missing offset, a wrong total length, an offset inside a character and an out of order
+  // offset are all errors, rather than panics
+  let mut truncated = snapshot.clone();
+  truncated.byte_offsets.pop();
+  let mut wrong_length = snapshot.clone();
+  *wrong_length.byte_offsets.last_mut().unwrap() += 1;
+  let mut inside_char = DNMSnapshot {
+    plaintext: "é".to_string(),
+    byte_offsets: vec![0, 1],
+    ..snapshot.clone()
+  };
+  let mut out_of_order = snapshot.clone();
+  out_of_order.byte_offsets[1] = 1000;
+  for corrupt in [truncated, wrong_length, inside_char.clone(), out_of_order] {
+    assert!(DNM::from_snapshot_plaintext(corrupt.clone()).is_err());
+    assert!(DNM::from_snapshot(
+      corrupt,
+      first_paragraph(&doc),
+      DNMParameters::llamapun_normalization()
+    )
+    .is_err());
+  }
+  inside_char.byte_offsets = vec![0, 2];
+  assert_eq!(
+    DNM::from_snapshot_plaintext(inside_char).unwrap().plaintext,
+    "é"
+  );
+}
+
+#[test]
+fn test_snapshot_of_subnode() {
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let dnm = DNM::new(
+    first_paragraph(&doc),
+    DNMParameters::llamapun_normalization(),
+  );
+  let snapshot = dnm.to_snapshot().unwrap();
+  // only the nodes referenced by the DNM are recorded
+  assert!(snapshot.node_paths.len() <= dnm.node_map.len() + dnm.back_map.len());
+
+  let reparsed = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let reloaded = DNM::from_snapshot(
+    snapshot.clone(),
+    first_paragraph(&reparsed),
+    DNMParameters::llamapun_normalization(),
+  )
+  .unwrap();
+  assert_eq!(reloaded.plaintext, dnm.plaintext);
+  assert_eq!(reloaded.to_snapshot().unwrap(), snapshot);
+}
+
+fn first_paragraph(doc: &Document) -> RoNode {
+  Context::new(doc)
+    .unwrap()
+    .evaluate("//*[contains(@class,'ltx_para')]")
+    .unwrap()
+    .get_readonly_nodes_as_vec()[0]
+}
@@ -7,7 +7,10 @@ extern crate rustmorpha;
 use libxml::parser::Parser;
 use libxml::xpath::Context;
 use llamapun::dnm::*;
+use llamapun::tokenizer::Tokenizer;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[test]
 fn test_plaintext_simple() {
@@ -161,7 +164,7 @@ fn test_back_mapping_simple() {
 
   // test deserialization
   let xpath_context = Context::new(&doc).unwrap();
-  let range2 = DNMRange::deserialize(&string, &dnm, &xpath_context);
+  let range2 = DNMRange::deserialize(&string, &dnm, &xpath_context).unwrap();
   assert_eq!(range2.get_plaintext(), "and");
 
   let range3 = DNMRange {
@@ -174,7 +177,7 @@ fn test_back_mapping_simple() {
   let string2 = range3.serialize();
   assert_eq!(string2, "arange(//body[1]/a[1],//body[1]/text()[4])");
 
-  let range4 = DNMRange::deserialize(&string2, &dnm, &xpath_context);
+  let range4 = DNMRange::deserialize(&string2, &dnm, &xpath_context).unwrap();
 
   assert_eq!(range4.get_plaintext(), "[link]");
 }
@@ -200,6 +203,86 @@ fn test_plaintext_normalized_class_names() {
   assert_eq!(dnm.plaintext.trim(), "[NORMALIZED] Else");
 }
 
+#[test]
+fn test_plaintext_selector_rules() {
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file06.xml").unwrap();
+  let mut class_options: HashMap<String, SpecialTagsOption> = HashMap::new();
+  class_options.insert(
+    "ltx_tag".to_string(),
+    SpecialTagsOption::Normalize("TAG".to_string()),
+  );
+  let selector_options = vec![
+    (
+      TagSelector::parse("figcaption span.ltx_tag").unwrap(),
+      SpecialTagsOption::Skip,
+    ),
+    (
+      TagSelector::parse("p > a[href^='#bib']").unwrap(),
+      SpecialTagsOption::Normalize("CitationElement".to_string()),
+    ),
+  ];
+  let root = doc.get_root_readonly().unwrap();
+  let dnm = DNM::new(
+    root,
+    DNMParameters {
+      special_tag_class_options: class_options,
+      special_tag_selector_options: selector_options,
+      normalize_white_spaces: true,
+      ..Default::default()
+    },
+  );
+  // selector rules take priority over the class rules
+  assert_eq!(
+    dnm.plaintext.trim(),
+    "A caption TAG Some text CitationElement and Section 1."
+  );
+
+  assert!(TagSelector::parse("a[href").is_err());
+  assert!(TagSelector::parse("figcaption >").is_err());
+  assert!(TagSelector::parse("").is_err());
+}
+
+#[test]
+fn test_stateful_function_normalize() {
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/1307.8133.html").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let counter = Arc::new(AtomicUsize::new(0));
+  let closure_counter = Arc::clone(&counter);
+  let mut options: HashMap<String, SpecialTagsOption> = HashMap::new();
+  options.insert(
+    "math".to_string(),
+    SpecialTagsOption::function_normalize(move |node| {
+      closure_counter.fetch_add(1, Ordering::SeqCst);
+      node::lexematize_math_node(node)
+    }),
+  );
+  let dnm = DNM::new(
+    root,
+    DNMParameters {
+      special_tag_name_options: options,
+      ..Default::default()
+    },
+  );
+
+  let mut context = Context::new(&doc).unwrap();
+  let math_nodes = context
+    .evaluate("//*[local-name()='math']")
+    .unwrap()
+    .get_readonly_nodes_as_vec();
+  assert!(!math_nodes.is_empty());
+  assert_eq!(counter.load(Ordering::SeqCst), math_nodes.len());
+  for math in math_nodes {
+    let lexemes = node::lexematize_math(math, &mut context);
+    assert_eq!(node::lexematize_math_node(math), lexemes);
+    assert_eq!(
+      dnm.get_range_of_node(math).unwrap().get_plaintext(),
+      lexemes
+    );
+  }
+}
+
 #[test]
 fn test_unicode_normalization() {
   let parser = Parser::default();
@@ -237,5 +320,359 @@ fn test_morpha_stemming() {
     dnmrange.get_plaintext().trim(),
     "here be one sentence with multiple word."
   );
+  let plaintext = dnm.plaintext.clone();
+
+  // stemming also maintains the back-mapping, stemming the same way
+  let dnm = DNM::new(
+    root,
+    DNMParameters {
+      stem_words_once: true,
+      support_back_mapping: true,
+      ..Default::default()
+    },
+  );
+  assert_eq!(dnm.plaintext, plaintext);
+  assert_eq!(dnm.plaintext.chars().count(), dnm.back_map.len());
+  let dnmrange = dnm.get_range_of_node(root).unwrap().trim();
+  assert_eq!(
+    dnmrange.get_plaintext().trim(),
+    "here be one sentence with multiple word."
+  );
+  let start = dnm.plaintext.find("sentence").unwrap();
+  let sentence_range = DNMRange {
+    start,
+    end: start + 8,
+    dnm: &dnm,
+  };
+  let xpointer = sentence_range.serialize();
+  assert_eq!(
+    xpointer,
+    "arange(string-index(//body[1]/text()[1],22),string-index(//body[1]/text()[1],31))"
+  );
+  let xpath_context = Context::new(&doc).unwrap();
+  let roundtrip = DNMRange::deserialize(&xpointer, &dnm, &xpath_context).unwrap();
+  assert_eq!(roundtrip.get_plaintext(), "sentence");
   rustmorpha::close();
 }
+
+#[test]
+fn test_xpointer_forms() {
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let dnm = DNM::new(root, DNMParameters::default());
+  let xpath_context = Context::new(&doc).unwrap();
+  let plaintext_of = |pointer: &str| {
+    DNMRange::deserialize(pointer, &dnm, &xpath_context)
+      .unwrap()
+      .get_plaintext()
+      .to_string()
+  };
+
+  assert_eq!(
+    plaintext_of("xpointer(string-range(//body/text()[4], \"a bit\"))"),
+    "a bit"
+  );
+  assert_eq!(
+    plaintext_of("#xpointer(string-range(//body/text()[4], 'a bit', 3))"),
+    "bit"
+  );
+  assert_eq!(plaintext_of("element(/1/1/3)"), "link");
+  assert_eq!(
+    plaintext_of("xpointer(//h1/range-to(//h2))"),
+    "Title Subtitle"
+  );
+  assert_eq!(
+    plaintext_of("xmlns(h=http://www.w3.org/1999/xhtml) xpointer(//h1)"),
+    "Title"
+  );
+  assert_eq!(plaintext_of("element(missing) xpointer(//h2)"), "Subtitle");
+
+  let point = DNMRange::deserialize(
+    "xpointer(//body/text()[4]/point()[10])",
+    &dnm,
+    &xpath_context,
+  )
+  .unwrap();
+  assert!(point.is_empty());
+  assert_eq!(point.start, 30);
+  let start_point =
+    DNMRange::deserialize("xpointer(start-point(//a))", &dnm, &xpath_context).unwrap();
+  assert!(start_point.is_empty());
+  assert_eq!(start_point.start, 25);
+
+  // malformed and unresolvable pointers are reported, rather than aborting
+  assert!(DNMRange::deserialize("arange(//body[1]/a[1]", &dnm, &xpath_context).is_err());
+  assert_eq!(
+    DNMRange::deserialize("foo(bar)", &dnm, &xpath_context).unwrap_err(),
+    XPointerError::Unsupported("foo(bar)".to_string())
+  );
+  assert_eq!(
+    DNMRange::deserialize("//p", &dnm, &xpath_context).unwrap_err(),
+    XPointerError::NodeCount {
+      xpath: "//p".to_string(),
+      count: 0
+    }
+  );
+  assert_eq!(
+    DNMRange::deserialize("xpointer(string-range(//h1, 'nope'))", &dnm, &xpath_context)
+      .unwrap_err(),
+    XPointerError::StringNotFound("nope".to_string())
+  );
+}
+
+#[test]
+fn test_unicode_normalization_modes() {
+  let text = "The \u{FB01}nal \u{201C}Cafe\u{301}\u{201D} of \u{3B1}\u{B2} and \u{FF21}\u{FF22}";
+  let plaintext_for = |mode| {
+    let (_doc, dnm) = DNM::from_str(
+      text,
+      Some(DNMParameters {
+        unicode_normalization: mode,
+        ..Default::default()
+      }),
+    )
+    .unwrap();
+    // every plaintext char is still mapped back into the text node
+    assert_eq!(dnm.back_map.len(), dnm.plaintext.chars().count());
+    (dnm.plaintext.trim().to_string(), dnm)
+  };
+
+  let (nfc, _) = plaintext_for(UnicodeNormalization::Nfc);
+  assert_eq!(
+    nfc,
+    "The \u{FB01}nal \u{201C}Caf\u{E9}\u{201D} of \u{3B1}\u{B2} and \u{FF21}\u{FF22}"
+  );
+  let (nfkc, nfkc_dnm) = plaintext_for(UnicodeNormalization::Nfkc);
+  assert_eq!(
+    nfkc,
+    "The final \u{201C}Caf\u{E9}\u{201D} of \u{3B1}2 and AB"
+  );
+  // both letters of the ligature map back to it
+  assert_eq!(nfkc_dnm.back_map[4].1, 4);
+  assert_eq!(nfkc_dnm.back_map[5].1, 4);
+  assert_eq!(nfkc_dnm.back_map[6].1, 5);
+  let (casefolded, _) = plaintext_for(UnicodeNormalization::NfkcCasefold);
+  assert_eq!(
+    casefolded,
+    "the final \u{201C}caf\u{E9}\u{201D} of \u{3B1}2 and ab"
+  );
+  let (repaired, _) = plaintext_for(UnicodeNormalization::Repair);
+  assert_eq!(
+    repaired,
+    "The final \"Cafe\u{301}\" of \u{3B1}\u{B2} and \u{FF21}\u{FF22}"
+  );
+  let (ascii, _) = plaintext_for(UnicodeNormalization::Ascii);
+  assert!(ascii.is_ascii());
+  assert!(ascii.starts_with("The final \"Cafe\""));
+
+  // the legacy flag is a shorthand for ASCII transliteration
+  let (_doc, legacy) = DNM::from_str(
+    text,
+    Some(DNMParameters {
+      normalize_unicode: true,
+      ..Default::default()
+    }),
+  )
+  .unwrap();
+  assert_eq!(legacy.plaintext.trim(), ascii);
+}
+
+#[test]
+fn test_unicode_normalization_with_and_without_back_mapping() {
+  // starters which still compose with the preceding character (Kannada, Bengali and Oriya vowel
+  // signs, Hangul jamo), and characters whose case folding differs from their lowercase
+  let text = "\u{C95}\u{CBF}\u{CD5} \u{995}\u{9C7}\u{9BE} \u{B15}\u{B47}\u{B3E} \u{1100}\u{1161} \
+              Stra\u{DF}e \u{3A3}\u{391}\u{3A3} \u{3C3}\u{3C2} \u{FB01}";
+  let plaintext_for = |mode, support_back_mapping| {
+    let (_doc, dnm) = DNM::from_str(
+      text,
+      Some(DNMParameters {
+        unicode_normalization: mode,
+        support_back_mapping,
+        ..Default::default()
+      }),
+    )
+    .unwrap();
+    dnm.plaintext.trim().to_string()
+  };
+  let composed = "\u{C95}\u{CC0} \u{995}\u{9CB} \u{B15}\u{B4B} \u{AC00}";
+  assert_eq!(
+    plaintext_for(UnicodeNormalization::Nfc, true),
+    format!("{composed} Stra\u{DF}e \u{3A3}\u{391}\u{3A3} \u{3C3}\u{3C2} \u{FB01}")
+  );
+  assert_eq!(
+    plaintext_for(UnicodeNormalization::NfkcCasefold, true),
+    format!("{composed} strasse \u{3C3}\u{3B1}\u{3C3} \u{3C3}\u{3C3} fi")
+  );
+  // normalizing with offset bookkeeping gives the same plaintext as without
+  for mode in [
+    UnicodeNormalization::Nfc,
+    UnicodeNormalization::Nfkc,
+    UnicodeNormalization::NfkcCasefold,
+  ] {
+    assert_eq!(
+      plaintext_for(mode, true),
+      plaintext_for(mode, false),
+      "{mode:?}"
+    );
+    assert_eq!(plaintext_for(mode, true), mode.apply(text));
+  }
+}
+
+#[test]
+fn test_block_boundaries() {
+  let html = "<html><body><h1>Title</h1><div><p>First para. Still first.</p><p>Second \
+              para</p></div><ul><li>Item one</li><li>Item two</li></ul></body></html>";
+  let parser = Parser::default_html();
+  let doc = parser.parse_string(html).unwrap();
+  let root = doc.get_root_readonly().unwrap();
+
+  let raw = DNM::new(
+    root,
+    DNMParameters {
+      normalize_white_spaces: false,
+      ..Default::default()
+    },
+  );
+  assert_eq!(
+    raw.plaintext,
+    "TitleFirst para. Still first.Second paraItem oneItem two"
+  );
+
+  let dnm = DNM::new(
+    root,
+    DNMParameters {
+      normalize_white_spaces: false,
+      block_boundaries: Some(BlockBoundaries::default()),
+      ..Default::default()
+    },
+  );
+  assert_eq!(
+    dnm.plaintext,
+    "Title\n\nFirst para. Still first.\n\nSecond para\n\nItem one\n\nItem two\n\n"
+  );
+  // separators are mapped to their block element
+  let (separator_node, separator_offset) = dnm.back_map[5];
+  assert_eq!(separator_node.get_name(), "h1");
+  assert_eq!(separator_offset, -1);
+  // but are not part of its range
+  assert_eq!(
+    dnm
+      .get_range_of_node(separator_node)
+      .unwrap()
+      .get_plaintext(),
+    "Title"
+  );
+
+  let sentences = Tokenizer::default().sentences(&dnm);
+  let sentences: Vec<&str> = sentences.iter().map(|s| s.get_plaintext()).collect();
+  assert_eq!(sentences.len(), 6);
+  assert_eq!(sentences[0], "Title");
+  assert_eq!(sentences[1], "First para.");
+
+  // custom separators
+  let mut boundaries = BlockBoundaries::default();
+  boundaries.separator = " | ".to_string();
+  let dnm = DNM::new(
+    root,
+    DNMParameters {
+      block_boundaries: Some(boundaries),
+      ..Default::default()
+    },
+  );
+  assert!(dnm.plaintext.starts_with("Title | First para."));
+}
+
+#[test]
+fn test_compact_byte_offsets() {
+  let parser = Parser::default_html();
+  let doc = parser.parse_file("tests/resources/0903.1000.html").unwrap();
+  let dnm = DNM::new(
+    doc.get_root_readonly().unwrap(),
+    DNMParameters {
+      unicode_normalization: UnicodeNormalization::Nfc,
+      ..Default::default()
+    },
+  );
+  // the index agrees with the plaintext's own char boundaries
+  let expected: Vec<usize> = dnm
+    .plaintext
+    .char_indices()
+    .map(|(offset, _)| offset)
+    .chain(Some(dnm.plaintext.len()))
+    .collect();
+  assert!(!dnm.plaintext.is_ascii());
+  assert_eq!(dnm.byte_offsets.iter().collect::<Vec<_>>(), expected);
+  assert_eq!(dnm.byte_offsets.to_vec(), expected);
+  assert_eq!(dnm.byte_offsets.get(expected.len()), None);
+  assert_eq!(
+    ByteOffsets::try_from(expected.clone()).as_ref(),
+    Ok(&dnm.byte_offsets)
+  );
+  assert!(ByteOffsets::try_from(vec![0, 2, 1]).is_err());
+  assert!(ByteOffsets::try_from(vec![0, 256]).is_err());
+  assert_eq!(dnm.back_map.len(), expected.len() - 1);
+  // and takes less than two bytes per char
+  assert!(dnm.byte_offsets.heap_size() < 2 * expected.len());
+}
+
+#[test]
+fn test_offset_units() {
+  let parser = Parser::default();
+  let doc = parser
+    .parse_string("<p>\u{C7}a \u{1D465} e\u{301}!</p>")
+    .unwrap();
+  let dnm = DNM::new(doc.get_root_readonly().unwrap(), DNMParameters::default());
+  assert_eq!(dnm.plaintext, "\u{C7}a \u{1D465} e\u{301}!");
+
+  let utf16 = dnm.offset_index(OffsetUnit::Utf16);
+  assert_eq!(utf16.char_len(), 8);
+  assert_eq!(utf16.offset(8), Some(9));
+  // the end of a surrogate pair is no character boundary
+  assert_eq!(utf16.char_offset(4), None);
+
+  let variable = DNMRange {
+    start: 3,
+    end: 4,
+    dnm: &dnm,
+  };
+  // one index per unit, reused across ranges
+  let char_index = dnm.offset_index(OffsetUnit::Char);
+  let byte = dnm.offset_index(OffsetUnit::Byte);
+  let grapheme = dnm.offset_index(OffsetUnit::Grapheme);
+  assert_eq!(variable.offsets_with(&char_index), Ok((3, 4)));
+  assert_eq!(variable.offsets_with(&byte), Ok((4, 8)));
+  assert_eq!(variable.offsets_with(&utf16), Ok((3, 5)));
+  assert_eq!(variable.offsets_with(&grapheme), Ok((3, 4)));
+
+  let accented = DNMRange {
+    start: 5,
+    end: 7,
+    dnm: &dnm,
+  };
+  assert_eq!(accented.offsets_with(&byte), Ok((9, 12)));
+  assert_eq!(accented.offsets_with(&utf16), Ok((6, 8)));
+  assert_eq!(accented.offsets_with(&grapheme), Ok((5, 6)));
+  // a combining accent can't be separated from its base in graphemes
+  let base = DNMRange {
+    start: 5,
+    end: 6,
+    dnm: &dnm,
+  };
+  assert!(base.offsets_with(&grapheme).is_err());
+
+  for index in [&char_index, &byte, &utf16, &grapheme] {
+    let (start, end) = accented.offsets_with(index).unwrap();
+    assert_eq!(
+      DNMRange::from_offsets(&dnm, start, end, index).unwrap(),
+      accented
+    );
+  }
+  assert!(DNMRange::from_offsets(&dnm, 5, 7, &byte).is_err());
+  // converting between units goes through characters
+  assert_eq!(
+    byte.char_offset(9).and_then(|offset| utf16.offset(offset)),
+    Some(6)
+  );
+}
@@ -0,0 +1,203 @@
+//! Tests for the W3C Web Annotation selectors of DNM ranges
+use libxml::parser::Parser;
+use libxml::xpath::Context;
+use llamapun::dnm::*;
+use std::collections::HashMap;
+
+#[test]
+fn test_selectors_reanchor_across_normalizations() {
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let xpath_context = Context::new(&doc).unwrap();
+  let source_dnm = DNM::new(root, DNMParameters::default());
+
+  let mut options = HashMap::new();
+  options.insert(
+    "a".to_string(),
+    SpecialTagsOption::Normalize("[link]".to_string()),
+  );
+  let target_dnm = DNM::new(
+    root,
+    DNMParameters {
+      special_tag_name_options: options,
+      normalize_white_spaces: false,
+      ..Default::default()
+    },
+  );
+
+  let start = source_dnm.plaintext.find("a bit more").unwrap();
+  let range = DNMRange {
+    start,
+    end: start + "a bit more".len(),
+    dnm: &source_dnm,
+  };
+  let selectors = range.to_annotation_selectors().unwrap();
+  assert_eq!(selectors.len(), 3);
+  match &selectors[0] {
+    AnnotationSelector::TextQuoteSelector {
+      exact,
+      prefix,
+      suffix,
+    } => {
+      assert_eq!(exact, "a bit more");
+      assert!(prefix.ends_with("link\n        and "));
+      assert!(suffix.starts_with(" text."));
+    },
+    other => panic!("expected a TextQuoteSelector, got {other:?}"),
+  }
+  match &selectors[2] {
+    AnnotationSelector::XPathSelector { value, refined_by } => {
+      assert_eq!(
+        value,
+        "/*[local-name()='html'][1]/*[local-name()='body'][1]"
+      );
+      assert!(refined_by.is_some());
+    },
+    other => panic!("expected an XPathSelector, got {other:?}"),
+  }
+
+  for selector in &selectors {
+    let anchored = DNMRange::from_selector(selector, &target_dnm, &xpath_context).unwrap();
+    assert_eq!(anchored.get_plaintext(), "a bit more");
+    // anchoring in the originating DNM is lossless
+    let original = DNMRange::from_selector(selector, &source_dnm, &xpath_context).unwrap();
+    assert_eq!(original, range);
+  }
+
+  // a range inside a normalized element anchors to the normalization
+  let start = source_dnm.plaintext.find("link").unwrap();
+  let link = DNMRange {
+    start,
+    end: start + 4,
+    dnm: &source_dnm,
+  };
+  let selector = link.to_text_quote_selector(DEFAULT_QUOTE_CONTEXT).unwrap();
+  let anchored = DNMRange::from_selector(&selector, &target_dnm, &xpath_context).unwrap();
+  assert_eq!(anchored.get_plaintext(), "[link]");
+
+  // the source text can be indexed once and reused across ranges
+  let source_text = source_dnm.source_text(OffsetUnit::Char);
+  let target_text = target_dnm.source_text(OffsetUnit::Char);
+  for exported in [&range, &link] {
+    let selectors = exported.to_annotation_selectors_with(&source_text).unwrap();
+    assert_eq!(selectors, exported.to_annotation_selectors().unwrap());
+    for selector in &selectors {
+      assert_eq!(
+        DNMRange::from_selector_with(selector, &target_dnm, &xpath_context, &target_text).unwrap(),
+        DNMRange::from_selector(selector, &target_dnm, &xpath_context).unwrap()
+      );
+    }
+  }
+  // but only with DNMs of the same root node
+  let body = xpath_context
+    .evaluate("//body")
+    .unwrap()
+    .get_readonly_nodes_as_vec()[0];
+  let body_dnm = DNM::new(body, DNMParameters::default());
+  let body_range = body_dnm.get_range().unwrap();
+  assert!(body_range
+    .to_text_position_selector_with(&source_text)
+    .is_err());
+  assert!(
+    DNMRange::from_selector_with(&selectors[0], &body_dnm, &xpath_context, &source_text).is_err()
+  );
+}
+
+#[test]
+fn test_selectors_json_roundtrip() {
+  let parser = Parser::default();
+  let doc = parser.parse_file("tests/resources/file01.xml").unwrap();
+  let root = doc.get_root_readonly().unwrap();
+  let xpath_context = Context::new(&doc).unwrap();
+  let dnm = DNM::new(root, DNMParameters::default());
+  let range = DNMRange {
+    start: 15,
+    end: 24,
+    dnm: &dnm,
+  };
+  assert_eq!(range.get_plaintext(), "Some text");
+
+  let selectors = range.to_annotation_selectors().unwrap();
+  let json = serde_json::to_string(&selectors).unwrap();
+  assert!(json.contains("\"type\":\"TextQuoteSelector\""));
+  assert!(json.contains("\"type\":\"TextPositionSelector\""));
+  assert!(json.contains("\"refinedBy\":{\"type\":\"TextPositionSelector\""));
+
+  let parsed: Vec<AnnotationSelector> = serde_json::from_str(&json).unwrap();
+  assert_eq!(parsed, selectors);
+  for selector in &parsed {
+    let anchored = DNMRange::from_selector(selector, &dnm, &xpath_context).unwrap();
+    assert_eq!(anchored, range);
+  }
+
+  // a quote that is not in the document fails to anchor
+  let missing = AnnotationSelector::TextQuoteSelector {
+    exact: "not in the document".to_string(),
+    prefix: String::new(),
+    suffix: String::new(),
+  };
+  assert!(DNMRange::from_selector(&missing, &dnm, &xpath_context).is_err());
+
+  // without a back_map there is nothing to export from
+  let no_back_map = DNM::new(
+    root,
+    DNMParameters {
+      support_back_mapping: false,
+      ..Default::default()
+    },
+  );
+  let range = DNMRange {
+    start: 15,
+    end: 24,
+    dnm: &no_back_map,
+  };
+  assert!(range.to_text_position_selector().is_err());
+}
+
+#[test]
+fn test_selectors_in_utf16() {
+  let parser = Parser::default();
+  let doc = parser
+    .parse_string("<p>Let <i>\u{1D465}</i> be an element of <b>\u{1D53D}</b>.</p>")
+    .unwrap();
+  let xpath_context = Context::new(&doc).unwrap();
+  let dnm = DNM::new(doc.get_root_readonly().unwrap(), DNMParameters::default());
+  let start = dnm
+    .plaintext
+    .chars()
+    .position(|c| c == '\u{1D53D}')
+    .unwrap();
+  let range = DNMRange {
+    start,
+    end: start + 1,
+    dnm: &dnm,
+  };
+
+  let selector = range
+    .to_text_position_selector_in(OffsetUnit::Utf16)
+    .unwrap();
+  assert_eq!(
+    selector,
+    AnnotationSelector::TextPositionSelector { start: 24, end: 26 }
+  );
+  assert_eq!(
+    DNMRange::from_selector_in(&selector, &dnm, &xpath_context, OffsetUnit::Utf16).unwrap(),
+    range
+  );
+  let selector = range.to_xpath_selector_in(OffsetUnit::Utf16).unwrap();
+  assert_eq!(
+    DNMRange::from_selector_in(&selector, &dnm, &xpath_context, OffsetUnit::Utf16).unwrap(),
+    range
+  );
+  // the same positions are different characters
+  assert_ne!(
+    DNMRange::from_selector(
+      &AnnotationSelector::TextPositionSelector { start: 24, end: 26 },
+      &dnm,
+      &xpath_context
+    )
+    .unwrap(),
+    range
+  );
+}
@@ -1,6 +1,6 @@
 use libxml::readonly::RoNode;
 use llamapun::parallel_data::*;
-use llamapun::util::test::RESOURCE_DOCUMENTS;
+use llamapun::util::test::{ParagraphSegmenter, RESOURCE_DOCUMENTS};
 use std::collections::HashMap;
 
 #[test]
@@ -99,3 +99,29 @@ fn can_iterate_custom() {
     contact_count
   );
 }
+
+#[test]
+fn can_iterate_with_custom_segmenter() {
+  let mut corpus = Corpus::new("tests".to_string());
+  corpus.set_segmenter(ParagraphSegmenter);
+  let catalog = corpus.catalog_with_parallel_walk(|document| {
+    let mut t_catalog = HashMap::new();
+    let mut paragraph_count = 0;
+    let mut sentence_count = 0;
+    for mut paragraph in document.paragraph_iter() {
+      paragraph_count += 1;
+      for mut sentence in paragraph.iter() {
+        sentence_count += 1;
+        for word in sentence.word_iter() {
+          assert!(!word.range.get_plaintext().contains(char::is_whitespace));
+        }
+      }
+    }
+    t_catalog.insert(String::from("paragraph_count"), paragraph_count);
+    t_catalog.insert(String::from("sentence_count"), sentence_count);
+    t_catalog
+  });
+  let paragraph_count = catalog.get("paragraph_count").unwrap_or(&0);
+  assert!(*paragraph_count > 0);
+  assert_eq!(catalog.get("sentence_count").unwrap_or(&0), paragraph_count);
+}
@@ -0,0 +1,69 @@
+//! Tests for pattern matching
+use llamapun::data::Corpus;
+use llamapun::dnm::*;
+use llamapun::patterns::*;
+use senna::senna::SennaParseOptions;
+use std::cell::Cell;
+
+#[test]
+fn test_annotate_matches() {
+  let pattern_file = PatternFile::load("examples/declaration_pattern.xml").unwrap();
+  let mut corpus = Corpus::new("tests/resources/".to_string());
+  corpus.senna_options = Cell::new(SennaParseOptions {
+    pos: true,
+    psg: true,
+  });
+  corpus.dnm_parameters.support_back_mapping = true;
+  let mut document = corpus
+    .load_doc("tests/resources/1311.0066.xhtml".to_string())
+    .unwrap();
+
+  let mut declaration_count = 0;
+  for mut sentence in document.sentence_iter() {
+    let sentence = sentence.senna_parse();
+    let matches = match_sentence(
+      &pattern_file,
+      sentence.senna_sentence.as_ref().unwrap(),
+      &sentence.range,
+      "declaration",
+    )
+    .unwrap();
+    let mut store = AnnotationStore::new();
+    annotate_matches(&matches, sentence.range.dnm, &mut store).unwrap();
+    if matches.is_empty() {
+      assert!(store.layer(PATTERN_LAYER).is_none());
+      continue;
+    }
+    declaration_count += matches.len();
+
+    // one annotation per marker, each attributed to its top-level match
+    let layer = store.layer(PATTERN_LAYER).unwrap();
+    let marker_count: usize = matches.iter().map(|m| m.get_marker_list().len()).sum();
+    assert_eq!(layer.len(), marker_count);
+    let attribute = |annotation: &Annotation, key: &str| {
+      annotation
+        .get(key)
+        .and_then(AnnotationValue::as_str)
+        .unwrap()
+        .to_string()
+    };
+    for annotation in layer.iter() {
+      let kind = attribute(annotation, "kind");
+      assert!(kind == "text" || kind == "math", "unexpected kind {kind}");
+      if kind == "text" {
+        assert!(sentence.range.start <= annotation.start && annotation.end <= sentence.range.end);
+      }
+      let match_index = annotation.get("match").and_then(AnnotationValue::as_int);
+      assert!(match_index.is_some_and(|index| (index as usize) < matches.len()));
+    }
+    // the top-level marker of each match is its declaration
+    for match_index in 0..matches.len() {
+      assert!(layer.iter().any(|annotation| {
+        annotation.get("match").and_then(AnnotationValue::as_int) == Some(match_index as i64)
+          && attribute(annotation, "marker") == "declaration"
+          && attribute(annotation, "kind") == "text"
+      }));
+    }
+  }
+  assert!(declaration_count > 0, "expected declarations in 1311.0066");
+}
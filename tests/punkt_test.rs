@@ -0,0 +1,110 @@
+//! Tests for the Punkt sentence boundary detector
+extern crate llamapun;
+
+use llamapun::dnm::DNM;
+use llamapun::parallel_data::Corpus;
+use llamapun::punkt::*;
+use llamapun::tokenizer::Segmenter;
+use std::env;
+use std::fs;
+
+const TRAINING_SENTENCES: [&str; 5] = [
+  "We apply Thm. 2 to the bounded sequence of real numbers, i.e. the sequence has a limit point \
+   in the interval.",
+  "The limit point is unique, since the interval is compact and the sequence is bounded.",
+  "We conclude that the sequence converges to the limit point of the interval.",
+  "By Thm. 4 every bounded sequence in the interval has a convergent subsequence, i.e. a \
+   subsequence with a limit point.",
+  "The proof of the claim uses the compactness of the interval and the bounds of the sequence.",
+];
+
+fn training_text() -> String { TRAINING_SENTENCES.repeat(20).join(" ") }
+
+#[test]
+fn test_train_model() {
+  let mut trainer = PunktTrainer::new();
+  trainer.train_text(&training_text());
+  assert_eq!(trainer.token_count(), 1720);
+  let model = trainer.finalize();
+  assert!(model.abbreviations.contains("thm"));
+  assert!(model.abbreviations.contains("i.e"));
+  assert!(!model.abbreviations.contains("interval"));
+  assert!(model.sentence_starters.contains("we"));
+
+  // statistics of separately trained parts add up
+  let (first, second) = TRAINING_SENTENCES.split_at(2);
+  let mut first_trainer = PunktTrainer::new();
+  let mut second_trainer = PunktTrainer::new();
+  for _ in 0..20 {
+    first_trainer.train_text(&first.join(" "));
+    second_trainer.train_text(&second.join(" "));
+  }
+  assert_eq!(
+    first_trainer.merge(second_trainer).token_count(),
+    trainer.token_count()
+  );
+
+  let path = env::temp_dir().join("llamapun_punkt_model.json");
+  let path = path.to_str().unwrap();
+  model.save(path).unwrap();
+  assert_eq!(PunktModel::load(path).unwrap(), model);
+  fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_split_sentences() {
+  let mut trainer = PunktTrainer::new();
+  trainer.train_text(&training_text());
+  let model = trainer.finalize();
+
+  let (_doc, dnm) = DNM::from_str(
+    "We apply Thm. 2 to the sequence, i.e. the limit exists. The claim follows from the Thm. We \
+     conclude the proof! Is it (really) bounded?",
+    None,
+  )
+  .unwrap();
+  let sentences: Vec<String> = Segmenter::sentences(&model, &dnm)
+    .iter()
+    .map(|sentence| sentence.get_plaintext().to_string())
+    .collect();
+  assert_eq!(
+    sentences,
+    vec![
+      "We apply Thm. 2 to the sequence, i.e. the limit exists.",
+      "The claim follows from the Thm.",
+      "We conclude the proof!",
+      "Is it (really) bounded?",
+    ]
+  );
+  let words = model.words(&model.sentences(&dnm)[2]);
+  assert_eq!(words.len(), 4);
+
+  // an empty model only treats lowercase continuations as non-boundaries
+  let sentences = PunktModel::default().sentences(&dnm);
+  assert_eq!(sentences.len(), 5);
+}
+
+#[test]
+fn test_train_corpus() {
+  let directory = env::temp_dir().join("llamapun_punkt_corpus");
+  let _ = fs::remove_dir_all(&directory);
+  fs::create_dir_all(&directory).unwrap();
+  let divs: String = TRAINING_SENTENCES
+    .repeat(20)
+    .iter()
+    .map(|text| format!("<div class=\"ltx_para\"><p>{text}</p></div>"))
+    .collect();
+  fs::write(
+    directory.join("a.html"),
+    format!("<html><body>{divs}</body></html>"),
+  )
+  .unwrap();
+
+  let mut trainer = PunktTrainer::new();
+  trainer.train_corpus(&Corpus::new(directory.to_str().unwrap().to_string()));
+  assert_eq!(trainer.token_count(), 1720);
+  let model = trainer.finalize();
+  assert!(model.abbreviations.contains("thm"));
+  assert!(model.abbreviations.contains("i.e"));
+  fs::remove_dir_all(&directory).unwrap();
+}
@@ -1,5 +1,6 @@
 extern crate llamapun;
 use llamapun::stopwords;
+use whatlang::Lang;
 
 #[test]
 fn can_load_stopwords() {
@@ -14,3 +15,12 @@ fn can_use_stopwords() {
   assert!(stopwords.contains("about"));
   assert!(!stopwords.contains("equation"));
 }
+
+#[test]
+fn can_load_language_stopwords() {
+  let french = stopwords::load_language(Lang::Fra).unwrap();
+  assert!(french.contains("les"));
+  assert!(!french.contains("équation"));
+  assert_eq!(stopwords::load_language(Lang::Eng), Some(stopwords::load()));
+  assert!(stopwords::load_language(Lang::Jpn).is_none());
+}
@@ -2,8 +2,13 @@ use libxml::parser::Parser;
 use libxml::tree::*;
 use libxml::xpath::*;
 use llamapun::dnm::{DNMParameters, DNMRange, DNM};
+use llamapun::tokenizer::evaluation::*;
+use llamapun::tokenizer::language::*;
+use llamapun::tokenizer::token::*;
 use llamapun::tokenizer::*;
+use llamapun::util::test::ParagraphSegmenter;
 use regex::Regex;
+use whatlang::Lang;
 
 #[test]
 /// Test sentence tokenization of a simple document
@@ -54,6 +59,51 @@ fn test_sentence_tokenization_simple() {
   }
 }
 
+#[test]
+/// Test that sentence and word ranges count characters, not bytes, on non-ASCII text
+fn test_tokenization_non_ascii() {
+  let text = "\u{a0}The Schrödinger equation is linear.\u{2003}Its naïve solutions diverge. We \
+              prove Gödel’s theorem.\u{a0}"
+    .to_string();
+  let byte_offsets = text
+    .char_indices()
+    .map(|(offset, _)| offset)
+    .chain(Some(text.len()))
+    .collect();
+  let dnm = DNM {
+    plaintext: text,
+    byte_offsets,
+    parameters: DNMParameters::llamapun_normalization(),
+    ..DNM::default()
+  };
+
+  let tokenizer = Tokenizer::default();
+  let sentences = tokenizer.sentences(&dnm);
+  let sentence_texts: Vec<&str> = sentences.iter().map(|s| s.get_plaintext()).collect();
+  assert_eq!(
+    sentence_texts,
+    vec![
+      "The Schrödinger equation is linear.",
+      "Its naïve solutions diverge.",
+      "We prove Gödel’s theorem.",
+    ]
+  );
+  assert_eq!((sentences[1].start, sentences[1].end), (37, 65));
+
+  let words: Vec<&str> = tokenizer
+    .words(&sentences[2])
+    .iter()
+    .map(|w| w.get_plaintext())
+    .collect();
+  assert_eq!(words, vec!["We", "prove", "Gödel’s", "theorem"]);
+  let words: Vec<&str> = tokenizer
+    .words_and_punct(&sentences[2])
+    .iter()
+    .map(|w| w.get_plaintext())
+    .collect();
+  assert_eq!(words, vec!["We", "prove", "Gödel", "’s", "theorem", "."]);
+}
+
 #[test]
 /// Test sentence tokenization of an `arXMLiv` XHTML document
 fn test_sentence_tokenization_arxmliv_xhtml() {
@@ -76,6 +126,303 @@ fn test_sentence_tokenization_arxmliv_html() {
   test_each_paragraph(&doc, &expected);
 }
 
+#[test]
+/// Evaluate sentence tokenization against gold sentence boundaries
+fn test_sentence_tokenization_evaluation() {
+  let gold = load_gold("tests/resources/sentences_gold.json").unwrap();
+  let mut parameters = DNMParameters::llamapun_normalization();
+  parameters.wrap_tokens = false;
+  let evaluation = evaluate(&Tokenizer::default(), &gold, &parameters).unwrap();
+  assert_eq!(evaluation.documents.len(), 3);
+  assert_eq!(
+    evaluation.total(),
+    Scores {
+      true_positives: 12,
+      false_positives: 0,
+      false_negatives: 0
+    }
+  );
+  assert_eq!(evaluation.total().f1(), 1.0);
+  assert!(evaluation
+    .documents
+    .iter()
+    .all(|document| document.mismatches.is_empty()));
+
+  // a segmenter keeping paragraphs whole misses the inner boundaries
+  let evaluation = evaluate(&ParagraphSegmenter, &gold, &parameters).unwrap();
+  let scores = evaluation.total();
+  assert_eq!(scores.precision(), 1.0);
+  assert_eq!(scores.recall(), 4.0 / 12.0);
+  assert_eq!(evaluation.documents[1].scores.false_negatives, 4);
+  assert_eq!(evaluation.documents[2].scores.false_negatives, 2);
+  let mismatches = &evaluation.documents[0].mismatches;
+  assert_eq!(mismatches.len(), 2);
+  assert_eq!(mismatches[0].paragraph, "S1.p1");
+  assert_eq!(mismatches[0].kind, MismatchKind::Missed);
+  assert!(mismatches[0]
+    .context
+    .contains("algebraic geometry.| Relating with"));
+  assert!(evaluation.to_string().contains("- S1.p2@"));
+
+  // gold sentences may also be given as offsets into the paragraph's DNM
+  let parser = Parser::default();
+  let doc = parser
+    .parse_file("tests/resources/1311.0066.xhtml")
+    .unwrap();
+  let xpath_context = Context::new(&doc).unwrap();
+  let para = xpath_context
+    .evaluate("//*[@id='S1.p1']")
+    .unwrap()
+    .get_readonly_nodes_as_vec()[0];
+  let dnm = DNM::new(para, parameters.clone());
+  let offset = |needle: &str| {
+    dnm.plaintext[..dnm.plaintext.find(needle).unwrap()]
+      .chars()
+      .count()
+  };
+  let gold = vec![GoldDocument {
+    path: "tests/resources/1311.0066.xhtml".to_string(),
+    unit: Default::default(),
+    paragraphs: vec![GoldParagraph {
+      id: "S1.p1".to_string(),
+      sentences: vec![
+        GoldSentence::Offsets {
+          start: offset("The study"),
+          end: offset("Relating"),
+        },
+        GoldSentence::Offsets {
+          start: offset("Relating"),
+          end: dnm.plaintext.chars().count(),
+        },
+      ],
+    }],
+  }];
+  let evaluation = evaluate(&Tokenizer::default(), &gold, &parameters).unwrap();
+  assert_eq!(evaluation.total().f1(), 1.0);
+}
+
+#[test]
+/// Test sentence and word tokenization with the language profiles
+fn test_multilingual_tokenization() {
+  let tokenizer = MultilingualTokenizer::default();
+  let (_doc, dnm) = DNM::from_str(
+    "L'équation de Schrödinger est linéaire, cf. Thm. 2 dans l'article. Nous montrons qu'il \
+     existe une solution unique. Voir p. 5 pour la démonstration.",
+    None,
+  )
+  .unwrap();
+  assert_eq!(tokenizer.detect(&dnm.plaintext), Lang::Fra);
+  assert!(std::ptr::eq(
+    tokenizer.profile_for(&dnm),
+    &tokenizer.profiles[&Lang::Fra]
+  ));
+  // the per-DNM handle segments as the detected profile
+  let segmenter = Segmenter::for_dnm(&tokenizer, &dnm);
+  assert_eq!(
+    segmenter.sentences(&dnm),
+    tokenizer.profiles[&Lang::Fra].sentences(&dnm)
+  );
+  let sentences = Segmenter::sentences(&tokenizer, &dnm);
+  let sentence_texts: Vec<&str> = sentences.iter().map(|s| s.get_plaintext()).collect();
+  assert_eq!(
+    sentence_texts,
+    vec![
+      "L'équation de Schrödinger est linéaire, cf. Thm. 2 dans l'article.",
+      "Nous montrons qu'il existe une solution unique.",
+      "Voir p. 5 pour la démonstration.",
+    ]
+  );
+  let words: Vec<&str> = tokenizer
+    .words_and_punct(&sentences[0])
+    .iter()
+    .map(|w| w.get_plaintext())
+    .collect();
+  assert_eq!(&words[..3], &["L'", "équation", "de"]);
+  assert!(words.contains(&"l'"));
+  assert!(words.contains(&"article"));
+  let words: Vec<&str> = tokenizer
+    .words(&sentences[1])
+    .iter()
+    .map(|w| w.get_plaintext())
+    .collect();
+  assert_eq!(
+    words,
+    vec!["Nous", "montrons", "qu", "il", "existe", "une", "solution", "unique"]
+  );
+
+  let german = Tokenizer::for_language(Lang::Deu).unwrap();
+  let (_doc, dnm) = DNM::from_str(
+    "Die Lösung existiert, vgl. Satz 3. Sie ist eindeutig.",
+    None,
+  )
+  .unwrap();
+  assert_eq!(german.sentences(&dnm).len(), 2);
+  // full words such as "Hilfssatz" are not abbreviations
+  let (_doc, dnm) = DNM::from_str("Das folgt aus dem Hilfssatz. Er ist bekannt.", None).unwrap();
+  assert_eq!(german.sentences(&dnm).len(), 2);
+
+  let (_doc, dnm) =
+    DNM::from_str("Решение существует, см. Рис. 2. Оно единственно.", None).unwrap();
+  let russian = Tokenizer::for_language(Lang::Rus).unwrap();
+  assert_eq!(russian.sentences(&dnm).len(), 2);
+  assert_eq!(Tokenizer::default().sentences(&dnm).len(), 3);
+
+  assert!(Tokenizer::for_language(Lang::Jpn).is_none());
+}
+
+#[test]
+/// Test typed tokens, split by the normalized elements they cover
+fn test_typed_tokens() {
+  let html = "<html><body><div class=\"ltx_para\"><p>For <math alttext=\"k\">k</math>-dimensional \
+              spaces see <cite class=\"ltx_cite\">[1]</cite> and <a class=\"ltx_ref\" \
+              href=\"#S2\">Section 2</a>, with 3.14 <math alttext=\"k\">k</math>th steps + \
+              https://arxiv.org/abs/1234.</p></div></body></html>";
+  let doc = Parser::default_html().parse_string(html).unwrap();
+  let mut parameters = DNMParameters::llamapun_normalization();
+  parameters.wrap_tokens = false;
+  let dnm = DNM::new(doc.get_root_readonly().unwrap(), parameters);
+  let range = dnm.get_range().unwrap();
+  let tokens: Vec<(&str, TokenType)> = Tokenizer::default()
+    .tokens(&range)
+    .iter()
+    .map(|token| (token.range.get_plaintext(), token.token_type))
+    .collect();
+  use TokenType::*;
+  assert_eq!(
+    tokens,
+    vec![
+      ("For", Word),
+      ("mathformula", Math),
+      ("-", Punctuation),
+      ("dimensional", Word),
+      ("spaces", Word),
+      ("see", Word),
+      ("CitationElement", Citation),
+      ("and", Word),
+      ("REF", Reference),
+      (",", Punctuation),
+      ("with", Word),
+      ("3.14", Number),
+      ("mathformula", Math),
+      ("th", Word),
+      ("steps", Word),
+      ("+", Symbol),
+      ("https://arxiv.org/abs/1234", Url),
+      (".", Punctuation),
+    ]
+  );
+
+  // with back-mapping, the text "mathformula" is an ordinary word
+  let (_doc, dnm) = DNM::from_str("See mathformula here.", None).unwrap();
+  let word = dnm.get_range().unwrap().trim().get_subrange(4, 15);
+  assert_eq!(word.get_plaintext(), "mathformula");
+  assert_eq!(Token::classify(&word)[0].token_type, Word);
+  // without, llamapun's normalization tokens are recognized by their text
+  let parameters = DNMParameters {
+    support_back_mapping: false,
+    ..DNMParameters::default()
+  };
+  let (_doc, dnm) = DNM::from_str("See mathformula here.", Some(parameters)).unwrap();
+  let word = dnm.get_range().unwrap().trim().get_subrange(4, 15);
+  assert_eq!(Token::classify(&word)[0].token_type, Math);
+}
+
+#[test]
+/// Test the lazy sentence and word iterators
+fn test_lazy_iterators() {
+  let tokenizer = Tokenizer::default();
+  let (_doc, dnm) = DNM::from_str(
+    "Bernstein's theorem isn't new, cf. Eqn. 1. We derive the result. It is Prof. Kemperman's \
+     (1968)!",
+    None,
+  )
+  .unwrap();
+  let mut sentences = tokenizer.sentence_iter(&dnm);
+  let first = sentences.next().unwrap();
+  assert_eq!(
+    first.get_plaintext(),
+    "Bernstein's theorem isn't new, cf. Eqn. 1."
+  );
+  let rest: Vec<&str> = sentences.map(|sentence| sentence.get_plaintext()).collect();
+  assert_eq!(
+    rest,
+    vec!["We derive the result.", "It is Prof. Kemperman's (1968)!"]
+  );
+
+  let words: Vec<&str> = tokenizer
+    .word_iter(&first)
+    .map(|word| word.get_plaintext())
+    .collect();
+  assert_eq!(
+    words,
+    vec![
+      "Bernstein's",
+      "theorem",
+      "isn",
+      "t",
+      "new",
+      "cf",
+      "Eqn",
+      "1"
+    ]
+  );
+  let words: Vec<&str> = tokenizer
+    .words_and_punct_iter(&first)
+    .map(|word| word.get_plaintext())
+    .collect();
+  assert_eq!(
+    words,
+    vec![
+      "Bernstein",
+      "'s",
+      "theorem",
+      "isn",
+      "'t",
+      "new",
+      ",",
+      "cf",
+      ".",
+      "Eqn",
+      ".",
+      "1",
+      "."
+    ]
+  );
+  // the same segmentation through the lazy iterators of the Segmenter trait
+  let segmenter: &dyn Segmenter = &tokenizer;
+  let sentences: Vec<DNMRange> = segmenter.sentence_iter(&dnm).collect();
+  let words: Vec<&str> = segmenter
+    .word_iter(&sentences[2])
+    .map(|word| word.get_plaintext())
+    .collect();
+  assert_eq!(words, vec!["It", "is", "Prof", "Kemperman's", "1968"]);
+  let words: Vec<&str> = segmenter
+    .words_and_punct_iter(&sentences[1])
+    .map(|word| word.get_plaintext())
+    .collect();
+  assert_eq!(words, vec!["We", "derive", "the", "result", "."]);
+  let words: Vec<&str> = segmenter
+    .words_and_punct_iter(&sentences[2])
+    .map(|word| word.get_plaintext())
+    .collect();
+  assert_eq!(
+    words,
+    vec![
+      "It",
+      "is",
+      "Prof",
+      ".",
+      "Kemperman",
+      "'s",
+      "(",
+      "1968",
+      ")",
+      "!"
+    ]
+  );
+  assert_eq!(tokenizer.sentence_iter(&dnm).count(), 3);
+}
+
 /* ======================== */
 /* Auxiliary functions: */
 /* ======================== */